@@ -1,9 +1,9 @@
 // Example demonstrating Exercise 2.3: Advanced Channel Patterns
 // This shows how to use the MarketDataHub with dynamic subscriptions
 
-use financial_data_pipeline::MarketTick;
+use financial_data_pipeline::MarketEvent;
 use financial_data_pipeline::processor::{
-    MarketCommand, MarketDataHub, MarketDataProducer, PriceStats,
+    MarketCommand, MarketDataHub, MarketDataProducer, PriceStats, Subscription,
 };
 use tokio::sync::{mpsc, oneshot};
 
@@ -11,8 +11,8 @@ use tokio::sync::{mpsc, oneshot};
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Starting Hub Example - Exercise 2.3");
 
-    // This channel carries MarketTick messages from producers to the hub
-    let (tx, rx) = mpsc::channel::<MarketTick>(1000);
+    // This channel carries MarketEvent messages (trades, quotes, book updates) from producers to the hub
+    let (tx, rx) = mpsc::channel::<MarketEvent>(1000);
 
     // The constructor should return (hub, command_sender)
     // The command_sender is what clients use to interact with the hub
@@ -23,7 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // This task will handle all market data distribution and commands
     let hub_task = tokio::spawn(async move { hub.start().await });
 
-    // Each producer should send MarketTick messages to data_tx
+    // Each producer should send MarketEvent messages to data_tx
     // Spawn each producer in its own task for concurrent data generation
     let symbols = vec!["VZW", "JNJ", "AMZN", "AAPL", "SONO"];
     for symbol in symbols {
@@ -31,7 +31,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let symbol = symbol.to_string();
 
         tokio::spawn(async move {
-            let producer = MarketDataProducer::new(producer_tx, symbol);
+            let mut producer = MarketDataProducer::new(producer_tx, symbol);
             producer.start_producing().await.unwrap();
         });
     }
@@ -46,7 +46,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let client_command_sender = command_sender.clone();
 
     let client_task = tokio::spawn(async move {
-        let (tx, rx) = oneshot::channel::<mpsc::Receiver<MarketTick>>();
+        let (tx, rx) = oneshot::channel::<Subscription>();
         client_command_sender
             .send(MarketCommand::Subscribe("VZW".to_string(), tx))
             .await
@@ -58,7 +58,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
-        let (jnj_tx, jnj_rx) = oneshot::channel::<mpsc::Receiver<MarketTick>>();
+        let (jnj_tx, jnj_rx) = oneshot::channel::<Subscription>();
         client_command_sender
             .send(MarketCommand::Subscribe("JNJ".to_string(), jnj_tx))
             .await
@@ -86,7 +86,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("Statistics: {stats:?}");
 
         client_command_sender
-            .send(MarketCommand::Unsubscribe("VZW".to_string()))
+            .send(MarketCommand::UnsubscribeById(vzw_receiver.id()))
             .await
             .expect("Failed to unsubscribe");
 
@@ -113,6 +113,7 @@ async fn test_multiple_consumers(
     println!("total ticks: {total_ticks}");
     println!("Elapsed time: {elapsed:?}");
     println!("throughput: {throughput}");
+    proc.print_summary().await;
     Ok(elapsed)
 }
 
@@ -0,0 +1,534 @@
+use crate::models::{MarketEvent, MarketTick, TickSequencer};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+// Parsed rows buffered between the blocking reader and the paced sender
+const READ_AHEAD: usize = 1024;
+// Skipped rows logged individually; the rest are only counted in the summary
+const LOGGED_SKIPS: usize = 10;
+// Slowest accepted multiplier; keeps the widest chrono timestamp span within `Duration`/`Instant`
+const MIN_SPEED_MULTIPLIER: f64 = 1e-3;
+
+/// How fast to replay relative to the original tick timestamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    AsFastAsPossible,
+    RealTime,
+    /// N x real time, e.g. 10.0 replays an hour in six minutes; must be finite and at least 0.001
+    Multiplier(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimestampFormat {
+    Rfc3339,
+    UnixSeconds,
+    UnixMillis,
+    UnixNanos,
+    /// chrono `strftime` pattern; values without an offset are read in the config's time zone
+    Custom(String),
+}
+
+/// A CSV column, by header name or zero-based position
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMapping {
+    pub symbol: Column,
+    pub price: Column,
+    pub volume: Column,
+    pub timestamp: Column,
+}
+
+impl Default for ColumnMapping {
+    /// Headers named `symbol`, `price`, `volume` and `timestamp`
+    fn default() -> Self {
+        ColumnMapping {
+            symbol: "symbol".into(),
+            price: "price".into(),
+            volume: "volume".into(),
+            timestamp: "timestamp".into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Csv(csv::Error),
+    /// Mapped column is not in the header row
+    MissingColumn(String),
+    /// `ReplaySpeed::Multiplier` that is not finite or below the minimum
+    InvalidSpeed(f64),
+    /// Field could not be parsed; `line` is 1-based and includes the header
+    Parse {
+        line: u64,
+        field: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Csv(e) => write!(f, "csv error: {e}"),
+            ReplayError::MissingColumn(name) => write!(f, "column {name} not found in header"),
+            ReplayError::InvalidSpeed(n) => {
+                write!(
+                    f,
+                    "invalid replay speed multiplier {n}, need at least {MIN_SPEED_MULTIPLIER}"
+                )
+            }
+            ReplayError::Parse { line, field, value } => {
+                write!(f, "line {line}: invalid {field} {value:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<csv::Error> for ReplayError {
+    fn from(e: csv::Error) -> Self {
+        ReplayError::Csv(e)
+    }
+}
+
+/// Outcome of a replay run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplaySummary {
+    pub ticks_sent: usize,
+    pub rows_skipped: usize,
+}
+
+/// Replays historical ticks from a CSV file into the same channel `MarketDataProducer` feeds,
+/// as `MarketEvent::Trade`s
+pub struct CsvReplayProducer {
+    tx: mpsc::Sender<MarketEvent>,
+    path: PathBuf,
+    columns: ColumnMapping,
+    timestamp_format: TimestampFormat,
+    timezone: Tz,
+    delimiter: u8,
+    has_headers: bool,
+    speed: ReplaySpeed,
+    sequencer: TickSequencer,
+}
+
+impl CsvReplayProducer {
+    /// Comma-separated with a header row, RFC 3339 timestamps, replayed as fast as possible
+    /// Ticks are sequenced with the file path as their source
+    pub fn new(tx: mpsc::Sender<MarketEvent>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        CsvReplayProducer {
+            tx,
+            sequencer: TickSequencer::new(path.display().to_string()),
+            path,
+            columns: ColumnMapping::default(),
+            timestamp_format: TimestampFormat::Rfc3339,
+            timezone: Tz::UTC,
+            delimiter: b',',
+            has_headers: true,
+            speed: ReplaySpeed::AsFastAsPossible,
+        }
+    }
+
+    pub fn with_columns(mut self, columns: ColumnMapping) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+
+    /// Time zone for custom-format timestamps that carry no offset
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// File has no header row; columns must be mapped by index
+    pub fn without_headers(mut self) -> Self {
+        self.has_headers = false;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Replay the whole file, pacing ticks by their original timestamps
+    /// Malformed rows are logged and skipped; a missing file or column, a read error or an
+    /// invalid speed stops the replay
+    pub async fn start_producing(
+        &mut self,
+    ) -> Result<ReplaySummary, Box<dyn std::error::Error + Send + Sync>> {
+        if let ReplaySpeed::Multiplier(n) = self.speed
+            && !(n.is_finite() && n >= MIN_SPEED_MULTIPLIER)
+        {
+            return Err(ReplayError::InvalidSpeed(n).into());
+        }
+        let (row_tx, mut row_rx) = mpsc::channel(READ_AHEAD);
+        let reader = RowReader {
+            path: self.path.clone(),
+            columns: self.columns.clone(),
+            timestamp_format: self.timestamp_format.clone(),
+            timezone: self.timezone,
+            delimiter: self.delimiter,
+            has_headers: self.has_headers,
+        };
+        let reading = tokio::task::spawn_blocking(move || reader.read_into(row_tx));
+
+        let mut summary = ReplaySummary::default();
+        let mut origin: Option<(DateTime<Utc>, Instant)> = None;
+        while let Some(row) = row_rx.recv().await {
+            let tick = match row {
+                Ok(tick) => tick,
+                Err(e) => {
+                    summary.rows_skipped += 1;
+                    if summary.rows_skipped <= LOGGED_SKIPS {
+                        eprintln!("Skipping row in {}: {}", self.path.display(), e);
+                    } else if summary.rows_skipped == LOGGED_SKIPS + 1 {
+                        eprintln!(
+                            "Not logging further skipped rows in {}",
+                            self.path.display()
+                        );
+                    }
+                    continue;
+                }
+            };
+
+            let (first_timestamp, started) =
+                *origin.get_or_insert((tick.timestamp, Instant::now()));
+            if let Some(delay) = self.replay_delay(tick.timestamp - first_timestamp) {
+                tokio::time::sleep_until(started + delay).await;
+            }
+
+            let tick = self.sequencer.stamp(tick);
+            if self.tx.send(tick.into()).await.is_err() {
+                println!(
+                    "Consumer dropped, stopping replay of {}",
+                    self.path.display()
+                );
+                return Ok(summary);
+            }
+            summary.ticks_sent += 1;
+        }
+
+        reading.await??;
+        if summary.rows_skipped > 0 {
+            eprintln!(
+                "Skipped {} malformed rows in {}",
+                summary.rows_skipped,
+                self.path.display()
+            );
+        }
+        Ok(summary)
+    }
+
+    /// Wall-clock offset from the start of the replay for a tick `elapsed` after the first
+    fn replay_delay(&self, elapsed: chrono::TimeDelta) -> Option<Duration> {
+        let elapsed = elapsed.to_std().ok()?;
+        match self.speed {
+            ReplaySpeed::AsFastAsPossible => None,
+            ReplaySpeed::RealTime => Some(elapsed),
+            ReplaySpeed::Multiplier(n) => Some(elapsed.div_f64(n)),
+        }
+    }
+}
+
+// Owned copy of the parsing config, moved onto the blocking reader thread
+struct RowReader {
+    path: PathBuf,
+    columns: ColumnMapping,
+    timestamp_format: TimestampFormat,
+    timezone: Tz,
+    delimiter: u8,
+    has_headers: bool,
+}
+
+impl RowReader {
+    fn read_into(
+        self,
+        rows: mpsc::Sender<Result<MarketTick, ReplayError>>,
+    ) -> Result<(), ReplayError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_path(&self.path)?;
+
+        let headers = if self.has_headers {
+            Some(reader.headers()?.clone())
+        } else {
+            None
+        };
+        let index_of = |column: &Column| match (column, &headers) {
+            (Column::Index(i), _) => Ok(*i),
+            (Column::Name(name), Some(headers)) => headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| ReplayError::MissingColumn(name.clone())),
+            (Column::Name(name), None) => Err(ReplayError::MissingColumn(name.clone())),
+        };
+        let symbol = index_of(&self.columns.symbol)?;
+        let price = index_of(&self.columns.price)?;
+        let volume = index_of(&self.columns.volume)?;
+        let timestamp = index_of(&self.columns.timestamp)?;
+
+        let mut record = csv::StringRecord::new();
+        loop {
+            let row = match reader.read_record(&mut record) {
+                Ok(false) => return Ok(()),
+                Ok(true) => {
+                    let line = record.position().map_or(0, |p| p.line());
+                    self.parse_row(&record, line, [symbol, price, volume, timestamp])
+                }
+                // Only the row is bad; an I/O error would fail again on every read
+                Err(e) if e.is_io_error() => return Err(ReplayError::Csv(e)),
+                Err(e) => Err(ReplayError::Csv(e)),
+            };
+            if rows.blocking_send(row).is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_row(
+        &self,
+        record: &csv::StringRecord,
+        line: u64,
+        [symbol, price, volume, timestamp]: [usize; 4],
+    ) -> Result<MarketTick, ReplayError> {
+        let field = |index: usize, name: &'static str| {
+            record.get(index).ok_or(ReplayError::Parse {
+                line,
+                field: name,
+                value: String::new(),
+            })
+        };
+        let invalid = |name: &'static str, value: &str| ReplayError::Parse {
+            line,
+            field: name,
+            value: value.to_string(),
+        };
+
+        let symbol = field(symbol, "symbol")?;
+        if symbol.is_empty() {
+            return Err(invalid("symbol", symbol));
+        }
+        let price_field = field(price, "price")?;
+        let price = Decimal::from_str(price_field)
+            .or_else(|_| Decimal::from_scientific(price_field))
+            .map_err(|_| invalid("price", price_field))?;
+        let volume_field = field(volume, "volume")?;
+        let volume = volume_field
+            .parse()
+            .map_err(|_| invalid("volume", volume_field))?;
+        let timestamp_field = field(timestamp, "timestamp")?;
+        let timestamp = self
+            .parse_timestamp(timestamp_field)
+            .ok_or_else(|| invalid("timestamp", timestamp_field))?;
+
+        Ok(MarketTick::with_timestamp(
+            symbol.to_string(),
+            price,
+            volume,
+            timestamp,
+        ))
+    }
+
+    fn parse_timestamp(&self, value: &str) -> Option<DateTime<Utc>> {
+        match &self.timestamp_format {
+            TimestampFormat::Rfc3339 => DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|t| t.with_timezone(&Utc)),
+            TimestampFormat::UnixSeconds => DateTime::from_timestamp(value.parse().ok()?, 0),
+            TimestampFormat::UnixMillis => DateTime::from_timestamp_millis(value.parse().ok()?),
+            TimestampFormat::UnixNanos => Some(DateTime::from_timestamp_nanos(value.parse().ok()?)),
+            TimestampFormat::Custom(format) => {
+                if let Ok(t) = DateTime::parse_from_str(value, format) {
+                    return Some(t.with_timezone(&Utc));
+                }
+                let naive = NaiveDateTime::parse_from_str(value, format).ok()?;
+                self.timezone
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .map(|t| t.with_timezone(&Utc))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_csv(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.csv", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    async fn replay(
+        producer: CsvReplayProducer,
+        mut rx: mpsc::Receiver<MarketEvent>,
+    ) -> (ReplaySummary, Vec<MarketTick>) {
+        let mut producer = producer;
+        let summary = producer.start_producing().await.unwrap();
+        drop(producer);
+        let mut ticks = Vec::new();
+        while let Some(MarketEvent::Trade(tick)) = rx.recv().await {
+            ticks.push(tick);
+        }
+        (summary, ticks)
+    }
+
+    #[tokio::test]
+    async fn test_replay_with_column_mapping() {
+        let path = write_csv(
+            "replay_mapping",
+            "time;sym;px;qty;venue\n\
+             2025-01-14 09:30:00.250;AAPL;187.125;300;XNAS\n\
+             2025-01-14 09:30:01;AAPL;not-a-price;100;XNAS\n\
+             2025-01-14 09:30:02;MSFT;1.5e2;50;XNAS\n",
+        );
+        let (tx, rx) = mpsc::channel(10);
+        let producer = CsvReplayProducer::new(tx, &path)
+            .with_delimiter(b';')
+            .with_columns(ColumnMapping {
+                symbol: "sym".into(),
+                price: "px".into(),
+                volume: "qty".into(),
+                timestamp: 0.into(),
+            })
+            .with_timestamp_format(TimestampFormat::Custom("%Y-%m-%d %H:%M:%S%.f".to_string()))
+            .with_timezone(chrono_tz::America::New_York);
+
+        let (summary, ticks) = replay(producer, rx).await;
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            summary,
+            ReplaySummary {
+                ticks_sent: 2,
+                rows_skipped: 1
+            }
+        );
+        assert_eq!(ticks[0].price, Decimal::new(187125, 3));
+        assert_eq!(
+            ticks[0].timestamp.to_rfc3339(),
+            "2025-01-14T14:30:00.250+00:00"
+        );
+        assert_eq!(ticks[1].symbol, "MSFT");
+        assert_eq!(ticks[1].price, Decimal::new(150, 0));
+    }
+
+    #[tokio::test]
+    async fn test_replay_speed_multiplier() {
+        let path = write_csv(
+            "replay_speed",
+            "symbol,price,volume,timestamp\n\
+             AAPL,100,1,1700000000000\n\
+             AAPL,101,1,1700000001000\n\
+             AAPL,102,1,1700000002000\n",
+        );
+        let (tx, rx) = mpsc::channel(10);
+        let producer = CsvReplayProducer::new(tx, &path)
+            .with_timestamp_format(TimestampFormat::UnixMillis)
+            .with_speed(ReplaySpeed::Multiplier(20.0));
+
+        let started = std::time::Instant::now();
+        let (summary, ticks) = replay(producer, rx).await;
+        let elapsed = started.elapsed();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(summary.ticks_sent, 3);
+        assert_eq!(ticks[2].price, Decimal::new(102, 0));
+        // Two seconds of ticks at 20x is 100ms
+        assert!(elapsed >= Duration::from_millis(95), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn test_missing_column() {
+        let path = write_csv("replay_missing", "symbol,price,timestamp\nAAPL,1,0\n");
+        let (tx, _rx) = mpsc::channel(10);
+        let result = CsvReplayProducer::new(tx, &path).start_producing().await;
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "column volume not found in header"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_speed() {
+        let path = write_csv(
+            "replay_invalid_speed",
+            "symbol,price,volume,timestamp
+",
+        );
+        for n in [1e-300, 0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let (tx, _rx) = mpsc::channel(10);
+            let result = CsvReplayProducer::new(tx, &path)
+                .with_speed(ReplaySpeed::Multiplier(n))
+                .start_producing()
+                .await;
+            assert!(
+                result
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("invalid replay speed"),
+                "{n}"
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_error_stops_replay() {
+        // Opening a directory succeeds but every read fails with an I/O error
+        let (tx, _rx) = mpsc::channel(10);
+        let result = CsvReplayProducer::new(tx, std::env::temp_dir())
+            .without_headers()
+            .with_columns(ColumnMapping {
+                symbol: 0.into(),
+                price: 1.into(),
+                volume: 2.into(),
+                timestamp: 3.into(),
+            })
+            .start_producing()
+            .await;
+        assert!(result.unwrap_err().to_string().starts_with("csv error"));
+    }
+}
@@ -0,0 +1,7 @@
+//! Market data sources feeding the processor
+
+pub mod csv_replay;
+pub mod simulator;
+
+pub use csv_replay::*;
+pub use simulator::*;
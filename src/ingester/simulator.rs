@@ -0,0 +1,688 @@
+use crate::models::{ExchangeCalendar, MarketTick};
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::*;
+use std::fmt;
+
+/// Trading seconds in a year (252 days x 6.5 hours), used to scale annualised drift/volatility
+const TRADING_SECONDS_PER_YEAR: f64 = 252.0 * 6.5 * 3600.0;
+
+/// Geometric Brownian motion parameters for one simulated symbol
+#[derive(Debug, Clone)]
+pub struct SymbolParams {
+    pub starting_price: Decimal,
+    /// Annualised expected return, e.g. 0.05 for 5%
+    pub drift: f64,
+    /// Annualised volatility, e.g. 0.2 for 20%
+    pub volatility: f64,
+    /// Emitted prices are rounded to a multiple of this
+    pub tick_size: Decimal,
+    /// Volumes are drawn uniformly from 0..=2 * mean_volume
+    pub mean_volume: u64,
+}
+
+impl SymbolParams {
+    pub fn new(starting_price: Decimal) -> Self {
+        SymbolParams {
+            starting_price,
+            drift: 0.05,
+            volatility: 0.2,
+            tick_size: Decimal::new(1, 2),
+            mean_volume: 500,
+        }
+    }
+
+    pub fn with_drift(mut self, drift: f64) -> Self {
+        self.drift = drift;
+        self
+    }
+
+    pub fn with_volatility(mut self, volatility: f64) -> Self {
+        self.volatility = volatility;
+        self
+    }
+
+    pub fn with_tick_size(mut self, tick_size: Decimal) -> Self {
+        self.tick_size = tick_size;
+        self
+    }
+
+    pub fn with_mean_volume(mut self, mean_volume: u64) -> Self {
+        self.mean_volume = mean_volume;
+        self
+    }
+}
+
+impl Default for SymbolParams {
+    fn default() -> Self {
+        Self::new(Decimal::ONE_HUNDRED)
+    }
+}
+
+/// Something that moves a symbol's price or volume outside of normal diffusion
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioEventKind {
+    /// Instant gap by `return_pct` (0.08 = +8%) with a burst of volume
+    EarningsJump { return_pct: f64 },
+    /// Instant drop by `drop_pct` (0.1 = -10%), recovered evenly over `recovery_steps`
+    FlashCrash { drop_pct: f64, recovery_steps: u32 },
+    /// No ticks for `steps` steps; the price is frozen and volume spikes on reopen
+    Halt { steps: u32 },
+}
+
+/// An event fired at a point in simulated time for one symbol, or all symbols when `symbol` is None
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledEvent {
+    pub at: DateTime<Utc>,
+    pub symbol: Option<String>,
+    pub kind: ScenarioEventKind,
+}
+
+/// Record of an event the simulator applied, for checking downstream reactions
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredEvent {
+    pub at: DateTime<Utc>,
+    pub symbol: String,
+    pub kind: ScenarioEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulatorError {
+    /// Matrix is not n x n for the n configured symbols
+    DimensionMismatch { expected: usize },
+    /// Diagonal entries must be 1 and off-diagonal entries in [-1, 1] and symmetric
+    InvalidCorrelation(String),
+    /// Matrix has no Cholesky factor, so it cannot be a correlation matrix
+    NotPositiveDefinite,
+}
+
+impl fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulatorError::DimensionMismatch { expected } => {
+                write!(f, "correlation matrix must be {expected}x{expected}")
+            }
+            SimulatorError::InvalidCorrelation(reason) => {
+                write!(f, "invalid correlation matrix: {reason}")
+            }
+            SimulatorError::NotPositiveDefinite => {
+                write!(f, "correlation matrix is not positive definite")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulatorError {}
+
+// Volume multiplier set by events, decaying back to 1 each step
+const JUMP_VOLUME_BOOST: f64 = 5.0;
+const CRASH_VOLUME_BOOST: f64 = 10.0;
+const REOPEN_VOLUME_BOOST: f64 = 3.0;
+const VOLUME_BOOST_DECAY: f64 = 0.8;
+
+#[derive(Debug, Clone)]
+struct SimulatedSymbol {
+    symbol: String,
+    params: SymbolParams,
+    // Unrounded path, so tick-size rounding never feeds back into the walk
+    price: f64,
+    // Last emitted price, which the path falls back to if it leaves Decimal's range
+    last_price: Decimal,
+    volume_boost: f64,
+    halted_steps: u32,
+    // Per-step log return and steps left while recovering from a flash crash
+    recovery: Option<(f64, u32)>,
+}
+
+impl SimulatedSymbol {
+    fn apply(&mut self, kind: &ScenarioEventKind) {
+        match *kind {
+            ScenarioEventKind::EarningsJump { return_pct } => {
+                self.price *= 1.0 + return_pct;
+                self.volume_boost = JUMP_VOLUME_BOOST;
+            }
+            ScenarioEventKind::FlashCrash {
+                drop_pct,
+                recovery_steps,
+            } => {
+                let remaining = 1.0 - drop_pct.clamp(0.0, 0.99);
+                self.price *= remaining;
+                self.volume_boost = CRASH_VOLUME_BOOST;
+                if recovery_steps > 0 {
+                    self.recovery = Some((-remaining.ln() / recovery_steps as f64, recovery_steps));
+                }
+            }
+            ScenarioEventKind::Halt { steps } => self.halted_steps = steps,
+        }
+    }
+}
+
+/// Deterministic market simulator: one GBM price path per symbol on a simulated clock
+///
+/// Every random draw comes from a single seeded RNG and timestamps come from
+/// the simulated clock, so the same seed and configuration always reproduce
+/// exactly the same stream of ticks.
+#[derive(Debug, Clone)]
+pub struct MarketSimulator {
+    rng: StdRng,
+    clock: DateTime<Utc>,
+    step: TimeDelta,
+    symbols: Vec<SimulatedSymbol>,
+    // Lower-triangular Cholesky factor of the correlation matrix; None means independent
+    cholesky: Option<Vec<Vec<f64>>>,
+    scheduled: Vec<ScheduledEvent>,
+    random_events: Option<(f64, Vec<ScenarioEventKind>)>,
+    fired: Vec<FiredEvent>,
+    calendar: Option<ExchangeCalendar>,
+}
+
+impl MarketSimulator {
+    /// Starts at the Unix epoch with one-second steps; see `with_start_time` and `with_step`
+    pub fn new(seed: u64) -> Self {
+        MarketSimulator {
+            rng: StdRng::seed_from_u64(seed),
+            clock: DateTime::UNIX_EPOCH,
+            step: TimeDelta::seconds(1),
+            symbols: Vec::new(),
+            cholesky: None,
+            scheduled: Vec::new(),
+            random_events: None,
+            fired: Vec::new(),
+            calendar: None,
+        }
+    }
+
+    pub fn with_start_time(mut self, start_time: DateTime<Utc>) -> Self {
+        self.clock = start_time;
+        self
+    }
+
+    pub fn with_step(mut self, step: TimeDelta) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn with_symbol(mut self, symbol: &str, params: SymbolParams) -> Self {
+        self.add_symbol(symbol, params);
+        self
+    }
+
+    /// Add a symbol, replacing its parameters and path if it already exists
+    /// Adding a new symbol drops any correlation matrix, since its dimensions no longer match
+    pub fn add_symbol(&mut self, symbol: &str, params: SymbolParams) {
+        let price = params.starting_price.to_f64().unwrap_or_default();
+        let simulated = SimulatedSymbol {
+            symbol: symbol.to_string(),
+            last_price: params.starting_price,
+            params,
+            price,
+            volume_boost: 1.0,
+            halted_steps: 0,
+            recovery: None,
+        };
+        match self.symbols.iter_mut().find(|s| s.symbol == symbol) {
+            Some(existing) => *existing = simulated,
+            None => {
+                self.symbols.push(simulated);
+                self.cholesky = None;
+            }
+        }
+    }
+
+    /// Correlate returns across symbols; `matrix[i][j]` refers to symbols in the order they were added
+    pub fn with_correlation(mut self, matrix: Vec<Vec<f64>>) -> Result<Self, SimulatorError> {
+        self.cholesky = Some(cholesky(&matrix, self.symbols.len())?);
+        Ok(self)
+    }
+
+    /// Only produce while `calendar` has a session open; the clock jumps over closed periods
+    /// and ticks are tagged with their session
+    pub fn with_calendar(mut self, calendar: ExchangeCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Fire `kind` when the simulated clock reaches `at`
+    pub fn with_scheduled_event(mut self, event: ScheduledEvent) -> Self {
+        self.scheduled.push(event);
+        self.scheduled.sort_by_key(|e| e.at);
+        self
+    }
+
+    /// Each step, each symbol has `probability` of suffering one of `kinds`, chosen uniformly
+    pub fn with_random_events(mut self, probability: f64, kinds: Vec<ScenarioEventKind>) -> Self {
+        self.random_events = (!kinds.is_empty()).then_some((probability, kinds));
+        self
+    }
+
+    /// Events applied since the last call, in the order they fired
+    pub fn take_fired_events(&mut self) -> Vec<FiredEvent> {
+        std::mem::take(&mut self.fired)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().map(|s| s.symbol.as_str())
+    }
+
+    /// Move the clock forward to `time` if it is behind; the next step is stamped one step later
+    pub fn advance_to(&mut self, time: DateTime<Utc>) {
+        self.clock = self.clock.max(time);
+    }
+
+    /// Price increment a symbol's prices are rounded to
+    pub fn tick_size(&self, symbol: &str) -> Option<Decimal> {
+        self.symbols
+            .iter()
+            .find(|s| s.symbol == symbol)
+            .map(|s| s.params.tick_size)
+    }
+
+    /// Current simulated time, i.e. the timestamp of the last emitted ticks
+    pub fn clock(&self) -> DateTime<Utc> {
+        self.clock
+    }
+
+    /// Advance the clock one step and emit one tick per non-halted symbol, in insertion order
+    /// With a calendar, emits nothing if no session opens within the calendar's lookahead
+    pub fn step(&mut self) -> Vec<MarketTick> {
+        self.clock += self.step;
+        let session = match &self.calendar {
+            Some(calendar) => {
+                let Some(open) = calendar.next_session_start(self.clock) else {
+                    return Vec::new();
+                };
+                self.clock = open;
+                calendar.session_at(open)
+            }
+            None => None,
+        };
+        self.fire_scheduled_events();
+        self.fire_random_events();
+
+        let dt = self.step.as_seconds_f64() / TRADING_SECONDS_PER_YEAR;
+        let shocks = self.correlated_shocks();
+        let mut ticks = Vec::with_capacity(self.symbols.len());
+        for (symbol, shock) in self.symbols.iter_mut().zip(shocks) {
+            if symbol.halted_steps > 0 {
+                symbol.halted_steps -= 1;
+                if symbol.halted_steps == 0 {
+                    symbol.volume_boost = REOPEN_VOLUME_BOOST;
+                }
+                continue;
+            }
+
+            let params = &symbol.params;
+            let mut exponent = (params.drift - params.volatility.powi(2) / 2.0) * dt
+                + params.volatility * dt.sqrt() * shock;
+            if let Some((log_return, steps)) = symbol.recovery {
+                exponent += log_return;
+                symbol.recovery = (steps > 1).then_some((log_return, steps - 1));
+            }
+            symbol.price *= exponent.exp();
+
+            let base_volume = self.rng.random_range(0..=params.mean_volume * 2);
+            let volume = (base_volume as f64 * symbol.volume_boost).round() as u64;
+            symbol.volume_boost = 1.0 + (symbol.volume_boost - 1.0) * VOLUME_BOOST_DECAY;
+
+            let price = match round_to_tick(symbol.price, params.tick_size) {
+                Some(price) => price,
+                None => {
+                    println!(
+                        "Simulated price {} for {} is out of range, holding at {}",
+                        symbol.price, symbol.symbol, symbol.last_price
+                    );
+                    symbol.price = symbol.last_price.to_f64().unwrap_or_default();
+                    symbol.last_price
+                }
+            };
+            symbol.last_price = price;
+
+            let mut tick =
+                MarketTick::with_timestamp(symbol.symbol.clone(), price, volume, self.clock);
+            tick.session = session;
+            ticks.push(tick);
+        }
+        ticks
+    }
+
+    fn fire_scheduled_events(&mut self) {
+        let due = self.scheduled.partition_point(|e| e.at <= self.clock);
+        for event in self.scheduled.drain(..due) {
+            for symbol in self.symbols.iter_mut() {
+                if event.symbol.as_ref().is_none_or(|s| *s == symbol.symbol) {
+                    symbol.apply(&event.kind);
+                    self.fired.push(FiredEvent {
+                        at: self.clock,
+                        symbol: symbol.symbol.clone(),
+                        kind: event.kind.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn fire_random_events(&mut self) {
+        let Some((probability, kinds)) = &self.random_events else {
+            return;
+        };
+        for symbol in self.symbols.iter_mut() {
+            // Always draw both numbers so the stream stays aligned whatever fires
+            let roll: f64 = self.rng.random();
+            let choice = self.rng.random_range(0..kinds.len());
+            if roll < *probability {
+                symbol.apply(&kinds[choice]);
+                self.fired.push(FiredEvent {
+                    at: self.clock,
+                    symbol: symbol.symbol.clone(),
+                    kind: kinds[choice].clone(),
+                });
+            }
+        }
+    }
+
+    /// One standard normal shock per symbol, correlated through the Cholesky factor if set
+    fn correlated_shocks(&mut self) -> Vec<f64> {
+        let independent: Vec<f64> = (0..self.symbols.len())
+            .map(|_| standard_normal(&mut self.rng))
+            .collect();
+        let Some(lower) = &self.cholesky else {
+            return independent;
+        };
+        lower
+            .iter()
+            .map(|row| row.iter().zip(&independent).map(|(l, z)| l * z).sum())
+            .collect()
+    }
+}
+
+/// Validate a correlation matrix and return its lower-triangular Cholesky factor
+fn cholesky(matrix: &[Vec<f64>], n: usize) -> Result<Vec<Vec<f64>>, SimulatorError> {
+    if matrix.len() != n || matrix.iter().any(|row| row.len() != n) {
+        return Err(SimulatorError::DimensionMismatch { expected: n });
+    }
+    for (i, row) in matrix.iter().enumerate() {
+        if (row[i] - 1.0).abs() > 1e-9 {
+            return Err(SimulatorError::InvalidCorrelation(format!(
+                "diagonal entry {i} is not 1"
+            )));
+        }
+        for (j, value) in row.iter().enumerate().take(i) {
+            if (value - matrix[j][i]).abs() > 1e-9 || value.abs() > 1.0 {
+                return Err(SimulatorError::InvalidCorrelation(format!(
+                    "entry ({i}, {j}) is out of range or not symmetric"
+                )));
+            }
+        }
+    }
+
+    let mut lower = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                let diagonal = matrix[i][i] - dot;
+                if diagonal <= 0.0 {
+                    return Err(SimulatorError::NotPositiveDefinite);
+                }
+                lower[i][j] = diagonal.sqrt();
+            } else {
+                lower[i][j] = (matrix[i][j] - dot) / lower[j][j];
+            }
+        }
+    }
+    Ok(lower)
+}
+
+/// Standard normal draw via Box-Muller, so we only depend on uniform samples from `rand`
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    // 1 - u keeps the argument of ln in (0, 1]
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Round to the nearest tick, never below one tick
+/// `None` for NaN, infinity or a price too large for a `Decimal`
+fn round_to_tick(price: f64, tick_size: Decimal) -> Option<Decimal> {
+    let price = Decimal::from_f64(price)?;
+    if tick_size <= Decimal::ZERO {
+        return Some(price);
+    }
+    let rounded = price
+        .checked_div(tick_size)?
+        .round()
+        .checked_mul(tick_size)?;
+    Some(rounded.max(tick_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulator(seed: u64) -> MarketSimulator {
+        MarketSimulator::new(seed)
+            .with_symbol("AAPL", SymbolParams::new(Decimal::new(19000, 2)))
+            .with_symbol(
+                "TSLA",
+                SymbolParams::new(Decimal::new(25000, 2))
+                    .with_volatility(0.6)
+                    .with_tick_size(Decimal::new(5, 2)),
+            )
+    }
+
+    #[test]
+    fn test_same_seed_same_stream() {
+        let mut a = simulator(42);
+        let mut b = simulator(42);
+        let mut c = simulator(7);
+        let mut differs = false;
+        for _ in 0..500 {
+            let (ta, tb, tc) = (a.step(), b.step(), c.step());
+            for (x, y) in ta.iter().zip(&tb) {
+                assert_eq!(
+                    (&x.symbol, x.price, x.volume, x.timestamp),
+                    (&y.symbol, y.price, y.volume, y.timestamp)
+                );
+            }
+            differs |= ta.iter().zip(&tc).any(|(x, z)| x.price != z.price);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_advance_to_only_moves_forward() {
+        let mut sim = simulator(3);
+        let later = DateTime::UNIX_EPOCH + TimeDelta::seconds(100);
+        sim.advance_to(later);
+        sim.advance_to(DateTime::UNIX_EPOCH);
+        assert_eq!(sim.clock(), later);
+        assert_eq!(sim.step()[0].timestamp, later + TimeDelta::seconds(1));
+    }
+
+    #[test]
+    fn test_path_is_continuous_and_on_tick_grid() {
+        let mut sim = simulator(1);
+        let mut last = Decimal::new(25000, 2);
+        for _ in 0..1000 {
+            let tick = sim.step().pop().unwrap();
+            assert_eq!(tick.symbol, "TSLA");
+            assert!((tick.price % Decimal::new(5, 2)).is_zero());
+            // One-second steps at 60% annual vol never move anywhere near 5%
+            assert!((tick.price - last).abs() < last / Decimal::from(20));
+            last = tick.price;
+        }
+        assert_eq!(sim.clock(), DateTime::UNIX_EPOCH + TimeDelta::seconds(1000));
+    }
+
+    fn log_returns(ticks: &[Vec<MarketTick>], idx: usize) -> Vec<f64> {
+        ticks
+            .windows(2)
+            .map(|w| (w[1][idx].price / w[0][idx].price).to_f64().unwrap().ln())
+            .collect()
+    }
+
+    fn correlation(x: &[f64], y: &[f64]) -> f64 {
+        let n = x.len() as f64;
+        let (mx, my) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+        let cov: f64 = x.iter().zip(y).map(|(a, b)| (a - mx) * (b - my)).sum();
+        let vx: f64 = x.iter().map(|a| (a - mx).powi(2)).sum();
+        let vy: f64 = y.iter().map(|b| (b - my).powi(2)).sum();
+        cov / (vx * vy).sqrt()
+    }
+
+    #[test]
+    fn test_correlated_returns() {
+        // Coarse tick size would swamp one-second returns, so use high vol and fine ticks
+        let params = SymbolParams::default()
+            .with_volatility(5.0)
+            .with_tick_size(Decimal::new(1, 6));
+        let mut sim = MarketSimulator::new(3)
+            .with_step(TimeDelta::minutes(1))
+            .with_symbol("A", params.clone())
+            .with_symbol("B", params.clone())
+            .with_symbol("C", params)
+            .with_correlation(vec![
+                vec![1.0, 0.9, -0.5],
+                vec![0.9, 1.0, -0.4],
+                vec![-0.5, -0.4, 1.0],
+            ])
+            .unwrap();
+        let ticks: Vec<_> = (0..5000).map(|_| sim.step()).collect();
+        let (a, b, c) = (
+            log_returns(&ticks, 0),
+            log_returns(&ticks, 1),
+            log_returns(&ticks, 2),
+        );
+        assert!((correlation(&a, &b) - 0.9).abs() < 0.05);
+        assert!((correlation(&a, &c) + 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_invalid_correlation() {
+        let sim = simulator(1);
+        assert_eq!(
+            sim.clone().with_correlation(vec![vec![1.0]]).err(),
+            Some(SimulatorError::DimensionMismatch { expected: 2 })
+        );
+        assert!(matches!(
+            sim.clone()
+                .with_correlation(vec![vec![1.0, 0.5], vec![0.4, 1.0]])
+                .err(),
+            Some(SimulatorError::InvalidCorrelation(_))
+        ));
+        assert_eq!(
+            sim.with_correlation(vec![vec![1.0, 1.0], vec![1.0, 1.0]])
+                .err(),
+            Some(SimulatorError::NotPositiveDefinite)
+        );
+    }
+
+    #[test]
+    fn test_scheduled_events() {
+        let at = |secs| DateTime::UNIX_EPOCH + TimeDelta::seconds(secs);
+        let mut sim = simulator(9)
+            .with_scheduled_event(ScheduledEvent {
+                at: at(10),
+                symbol: Some("AAPL".to_string()),
+                kind: ScenarioEventKind::FlashCrash {
+                    drop_pct: 0.2,
+                    recovery_steps: 10,
+                },
+            })
+            .with_scheduled_event(ScheduledEvent {
+                at: at(5),
+                symbol: Some("TSLA".to_string()),
+                kind: ScenarioEventKind::Halt { steps: 3 },
+            });
+
+        let ticks: Vec<_> = (0..30).map(|_| sim.step()).collect();
+        // TSLA is halted for steps 5-7
+        assert_eq!(ticks[4].len(), 1);
+        assert_eq!(ticks[7].len(), 2);
+
+        let aapl = |step: usize| ticks[step][0].price.to_f64().unwrap();
+        assert!(aapl(9) / aapl(8) < 0.85);
+        // Recovered to roughly where it was before the crash
+        assert!((aapl(19) / aapl(8) - 1.0).abs() < 0.01);
+
+        let fired = sim.take_fired_events();
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].symbol, "TSLA");
+        assert!(sim.take_fired_events().is_empty());
+    }
+
+    #[test]
+    fn test_out_of_range_price_holds_at_last_price() {
+        let mut sim = simulator(5).with_scheduled_event(ScheduledEvent {
+            at: DateTime::UNIX_EPOCH + TimeDelta::seconds(3),
+            symbol: Some("AAPL".to_string()),
+            kind: ScenarioEventKind::EarningsJump { return_pct: 1e300 },
+        });
+
+        let ticks: Vec<_> = (0..5).map(|_| sim.step()).collect();
+        assert_eq!(ticks[2][0].price, ticks[1][0].price);
+        let last = ticks[1][0].price;
+        assert!((ticks[4][0].price - last).abs() < last / Decimal::from(20));
+    }
+
+    #[test]
+    fn test_random_events_are_reproducible() {
+        let build = || {
+            simulator(11).with_random_events(
+                0.05,
+                vec![
+                    ScenarioEventKind::EarningsJump { return_pct: 0.1 },
+                    ScenarioEventKind::Halt { steps: 2 },
+                ],
+            )
+        };
+        let (mut a, mut b) = (build(), build());
+        for _ in 0..200 {
+            a.step();
+            b.step();
+        }
+        let fired = a.take_fired_events();
+        assert!(!fired.is_empty());
+        assert_eq!(fired, b.take_fired_events());
+    }
+
+    #[test]
+    fn test_calendar_skips_closed_periods() {
+        use crate::models::TradingSession;
+        use chrono::TimeZone;
+
+        let nyse = ExchangeCalendar::nyse();
+        // Friday 17 Jan 2025, 19:00 New York, one hour before post-market closes
+        let start = chrono_tz::America::New_York
+            .with_ymd_and_hms(2025, 1, 17, 19, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut sim = simulator(5)
+            .with_start_time(start)
+            .with_step(TimeDelta::minutes(30))
+            .with_calendar(nyse.clone());
+
+        let sessions: Vec<_> = (0..4)
+            .map(|_| {
+                let ticks = sim.step();
+                assert!(nyse.is_open(ticks[0].timestamp));
+                ticks[0].session
+            })
+            .collect();
+        assert_eq!(
+            sessions,
+            vec![
+                Some(TradingSession::PostMarket),
+                Some(TradingSession::PreMarket),
+                Some(TradingSession::PreMarket),
+                Some(TradingSession::PreMarket),
+            ]
+        );
+        // Weekend and the MLK holiday are skipped
+        assert_eq!(
+            nyse.trading_day(sim.clock()),
+            chrono::NaiveDate::from_ymd_opt(2025, 1, 21).unwrap()
+        );
+    }
+}
@@ -4,8 +4,12 @@
 
 pub mod models;
 
+pub mod ingester;
+
 // Module 1 complete, Module 2 in progress
 pub mod processor;
 
+pub mod storage;
+
 // Re-export common types
 pub use models::*;
@@ -14,7 +14,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let symbol_owned = symbol.to_string();
 
         tokio::spawn(async move {
-            let producer = MarketDataProducer::new(producer_tx, symbol_owned);
+            let mut producer = MarketDataProducer::new(producer_tx, symbol_owned);
             if let Err(e) = producer.start_producing().await {
                 eprintln!("Producer error: {e}");
             }
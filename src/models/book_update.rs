@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BookAction {
+    /// A new price level appears
+    Add,
+    /// The resting size at an existing level changes
+    Modify,
+    /// A price level is removed
+    Delete,
+}
+
+/// A single price-level change in a symbol's limit order book
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookUpdate {
+    pub symbol: String,
+    pub side: BookSide,
+    pub action: BookAction,
+    pub price: Decimal,
+    pub size: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl BookUpdate {
+    pub fn new(
+        symbol: String,
+        side: BookSide,
+        action: BookAction,
+        price: Decimal,
+        size: u64,
+    ) -> Self {
+        BookUpdate {
+            symbol,
+            side,
+            action,
+            price,
+            size,
+            timestamp: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// How far ahead to look for the next open before giving up on a calendar
+const MAX_DAYS_TO_NEXT_SESSION: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TradingSession {
+    PreMarket,
+    Regular,
+    PostMarket,
+}
+
+/// Local session times for a normal trading day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionHours {
+    pub pre_market_open: Option<NaiveTime>,
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+    pub post_market_close: Option<NaiveTime>,
+}
+
+impl SessionHours {
+    /// Regular session only, no extended hours
+    pub fn regular(open: NaiveTime, close: NaiveTime) -> Self {
+        SessionHours {
+            pre_market_open: None,
+            open,
+            close,
+            post_market_close: None,
+        }
+    }
+
+    pub fn with_pre_market(mut self, open: NaiveTime) -> Self {
+        self.pre_market_open = Some(open);
+        self
+    }
+
+    pub fn with_post_market(mut self, close: NaiveTime) -> Self {
+        self.post_market_close = Some(close);
+        self
+    }
+}
+
+// Holidays and early closes that follow from a rule for every year, on top of explicit dates
+#[derive(Debug, Clone, Copy)]
+enum HolidayRules {
+    Nyse,
+}
+
+impl HolidayRules {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        match self {
+            HolidayRules::Nyse => nyse_holidays(date.year()).contains(&Some(date)),
+        }
+    }
+
+    fn early_close(&self, date: NaiveDate) -> Option<NaiveTime> {
+        match self {
+            HolidayRules::Nyse => nyse_early_closes(date.year())
+                .contains(&Some(date))
+                .then(|| NaiveTime::from_hms_opt(13, 0, 0).expect("valid time")),
+        }
+    }
+}
+
+/// An exchange's time zone, session hours, holidays and early closes
+#[derive(Debug, Clone)]
+pub struct ExchangeCalendar {
+    name: String,
+    timezone: Tz,
+    hours: SessionHours,
+    holidays: BTreeSet<NaiveDate>,
+    early_closes: BTreeMap<NaiveDate, NaiveTime>,
+    rules: Option<HolidayRules>,
+}
+
+impl ExchangeCalendar {
+    pub fn new(name: &str, timezone: Tz, hours: SessionHours) -> Self {
+        ExchangeCalendar {
+            name: name.to_string(),
+            timezone,
+            hours,
+            holidays: BTreeSet::new(),
+            early_closes: BTreeMap::new(),
+            rules: None,
+        }
+    }
+
+    /// NYSE with 04:00-09:30 pre-market, 09:30-16:00 regular and 16:00-20:00 post-market
+    /// Holidays and 13:00 early closes follow the exchange's standing rules for any year;
+    /// one-off closures (e.g. national days of mourning) must be added with `with_holiday`
+    pub fn nyse() -> Self {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).expect("valid time");
+        let hours = SessionHours::regular(time(9, 30), time(16, 0))
+            .with_pre_market(time(4, 0))
+            .with_post_market(time(20, 0));
+        let mut calendar = ExchangeCalendar::new("NYSE", chrono_tz::America::New_York, hours);
+        calendar.rules = Some(HolidayRules::Nyse);
+        calendar
+    }
+
+    pub fn with_holiday(mut self, date: NaiveDate) -> Self {
+        self.holidays.insert(date);
+        self
+    }
+
+    /// Close the regular session early on `date`; post-market then runs from the early close
+    pub fn with_early_close(mut self, date: NaiveDate, close: NaiveTime) -> Self {
+        self.early_closes.insert(date, close);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// Weekday that is not a holiday
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+            && !self.holidays.contains(&date)
+            && !self.rules.is_some_and(|rules| rules.is_holiday(date))
+    }
+
+    /// Exchange-local date of `timestamp`, which is the trading day its session belongs to
+    pub fn trading_day(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        timestamp.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Session in progress at `timestamp`, `None` if the market is closed
+    pub fn session_at(&self, timestamp: DateTime<Utc>) -> Option<TradingSession> {
+        let local = timestamp.with_timezone(&self.timezone);
+        let date = local.date_naive();
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let time = local.time();
+        let close = self.close_on(date);
+        if self.hours.open <= time && time < close {
+            Some(TradingSession::Regular)
+        } else if self.hours.pre_market_open.is_some_and(|open| open <= time)
+            && time < self.hours.open
+        {
+            Some(TradingSession::PreMarket)
+        } else if self.hours.post_market_close.is_some_and(|post| time < post) && close <= time {
+            Some(TradingSession::PostMarket)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_open(&self, timestamp: DateTime<Utc>) -> bool {
+        self.session_at(timestamp).is_some()
+    }
+
+    /// `timestamp` itself if a session is in progress, otherwise the start of the next one
+    pub fn next_session_start(&self, timestamp: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.is_open(timestamp) {
+            return Some(timestamp);
+        }
+        let first_open = self.hours.pre_market_open.unwrap_or(self.hours.open);
+        self.trading_day(timestamp)
+            .iter_days()
+            .take(MAX_DAYS_TO_NEXT_SESSION)
+            .filter(|date| self.is_trading_day(*date))
+            .filter_map(|date| {
+                self.timezone
+                    .from_local_datetime(&date.and_time(first_open))
+                    .earliest()
+            })
+            .map(|start| start.with_timezone(&Utc))
+            .find(|start| *start >= timestamp)
+    }
+
+    fn close_on(&self, date: NaiveDate) -> NaiveTime {
+        self.early_closes
+            .get(&date)
+            .copied()
+            .or_else(|| self.rules.and_then(|rules| rules.early_close(date)))
+            .unwrap_or(self.hours.close)
+    }
+}
+
+// Saturday holidays move to the Friday before, Sunday ones to the Monday after
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date.pred_opt().unwrap_or(date),
+        Weekday::Sun => date.succ_opt().unwrap_or(date),
+        _ => date,
+    }
+}
+
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+}
+
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> Option<NaiveDate> {
+    nth_weekday(year, month, weekday, 5).or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+// Gregorian Easter Sunday (anonymous Gregorian algorithm)
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+fn nyse_holidays(year: i32) -> [Option<NaiveDate>; 10] {
+    let date = |m, d| NaiveDate::from_ymd_opt(year, m, d);
+    // New Year's Day on a Saturday is not made up on the Friday before, which is a trading day
+    let new_year = date(1, 1)
+        .filter(|d| d.weekday() != Weekday::Sat)
+        .map(observed);
+    let juneteenth = date(6, 19).filter(|_| year >= 2022).map(observed);
+    [
+        new_year,
+        nth_weekday(year, 1, Weekday::Mon, 3),
+        nth_weekday(year, 2, Weekday::Mon, 3),
+        easter_sunday(year).and_then(|easter| easter.checked_sub_days(Days::new(2))),
+        last_weekday(year, 5, Weekday::Mon),
+        juneteenth,
+        date(7, 4).map(observed),
+        nth_weekday(year, 9, Weekday::Mon, 1),
+        nth_weekday(year, 11, Weekday::Thu, 4),
+        date(12, 25).map(observed),
+    ]
+}
+
+fn nyse_early_closes(year: i32) -> [Option<NaiveDate>; 3] {
+    let weekday_before_holiday = |d: NaiveDate| {
+        matches!(
+            d.weekday(),
+            Weekday::Mon | Weekday::Tue | Weekday::Wed | Weekday::Thu
+        )
+    };
+    [
+        NaiveDate::from_ymd_opt(year, 7, 3).filter(|d| weekday_before_holiday(*d)),
+        nth_weekday(year, 11, Weekday::Thu, 4).and_then(|d| d.succ_opt()),
+        NaiveDate::from_ymd_opt(year, 12, 24).filter(|d| weekday_before_holiday(*d)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_york(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        chrono_tz::America::New_York
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_sessions() {
+        let nyse = ExchangeCalendar::nyse();
+        // Tuesday 14 Jan 2025
+        assert_eq!(nyse.session_at(new_york(2025, 1, 14, 3, 59)), None);
+        assert_eq!(
+            nyse.session_at(new_york(2025, 1, 14, 4, 0)),
+            Some(TradingSession::PreMarket)
+        );
+        assert_eq!(
+            nyse.session_at(new_york(2025, 1, 14, 9, 30)),
+            Some(TradingSession::Regular)
+        );
+        assert_eq!(
+            nyse.session_at(new_york(2025, 1, 14, 16, 0)),
+            Some(TradingSession::PostMarket)
+        );
+        assert_eq!(nyse.session_at(new_york(2025, 1, 14, 20, 0)), None);
+        // Saturday, a holiday and an early close
+        assert_eq!(nyse.session_at(new_york(2025, 1, 18, 12, 0)), None);
+        assert_eq!(nyse.session_at(new_york(2025, 12, 25, 12, 0)), None);
+        assert_eq!(
+            nyse.session_at(new_york(2025, 12, 24, 13, 30)),
+            Some(TradingSession::PostMarket)
+        );
+    }
+
+    #[test]
+    fn test_next_session_start() {
+        let nyse = ExchangeCalendar::nyse();
+        let in_session = new_york(2025, 1, 14, 10, 0);
+        assert_eq!(nyse.next_session_start(in_session), Some(in_session));
+        // Friday night before a Monday holiday (MLK day) opens Tuesday pre-market
+        assert_eq!(
+            nyse.next_session_start(new_york(2025, 1, 17, 21, 0)),
+            Some(new_york(2025, 1, 21, 4, 0))
+        );
+
+        let regular_only = ExchangeCalendar::new(
+            "XLON",
+            chrono_tz::Europe::London,
+            SessionHours::regular(
+                NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(16, 30, 0).unwrap(),
+            ),
+        );
+        let start = regular_only
+            .next_session_start(new_york(2025, 7, 1, 0, 0))
+            .unwrap();
+        assert_eq!(
+            regular_only.session_at(start),
+            Some(TradingSession::Regular)
+        );
+        assert_eq!(
+            regular_only.trading_day(start),
+            NaiveDate::from_ymd_opt(2025, 7, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nyse_rules_in_any_year() {
+        let nyse = ExchangeCalendar::nyse();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        // The 2025 and 2026 exchange schedules, as published
+        let published_holidays = [
+            date(2025, 1, 1),
+            date(2025, 1, 20),
+            date(2025, 2, 17),
+            date(2025, 4, 18),
+            date(2025, 5, 26),
+            date(2025, 6, 19),
+            date(2025, 7, 4),
+            date(2025, 9, 1),
+            date(2025, 11, 27),
+            date(2025, 12, 25),
+            date(2026, 1, 1),
+            date(2026, 1, 19),
+            date(2026, 2, 16),
+            date(2026, 4, 3),
+            date(2026, 5, 25),
+            date(2026, 6, 19),
+            date(2026, 7, 3),
+            date(2026, 9, 7),
+            date(2026, 11, 26),
+            date(2026, 12, 25),
+        ];
+        let published_early_closes = [
+            date(2025, 7, 3),
+            date(2025, 11, 28),
+            date(2025, 12, 24),
+            date(2026, 11, 27),
+            date(2026, 12, 24),
+        ];
+        for day in date(2025, 1, 1).iter_days().take_while(|d| d.year() < 2027) {
+            let weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+            assert_eq!(
+                nyse.is_trading_day(day),
+                !weekend && !published_holidays.contains(&day),
+                "{day}"
+            );
+            assert_eq!(
+                nyse.close_on(day) != nyse.hours.close,
+                published_early_closes.contains(&day),
+                "{day}"
+            );
+        }
+
+        // 2027: Good Friday 26 March, Independence Day and Christmas observed on the weekday
+        for holiday in [date(2027, 3, 26), date(2027, 7, 5), date(2027, 12, 24)] {
+            assert!(!nyse.is_trading_day(holiday), "{holiday}");
+        }
+        assert!(nyse.is_trading_day(date(2027, 7, 2)));
+        assert_eq!(
+            nyse.session_at(new_york(2027, 11, 26, 13, 30)),
+            Some(TradingSession::PostMarket)
+        );
+        // New Year's Day 2028 is a Saturday and is not observed on the Friday before
+        assert!(nyse.is_trading_day(date(2027, 12, 31)));
+    }
+}
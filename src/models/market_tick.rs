@@ -1,16 +1,26 @@
 // this is where the async functions will go
 
+use crate::models::TradingSession;
 use chrono::{DateTime, Utc};
 use rand::random_range;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MarketTick {
     pub symbol: String,
     pub price: Decimal,
     pub volume: u64,
     pub timestamp: DateTime<Utc>,
+    /// Exchange session the tick traded in, when the source knows its calendar
+    #[serde(default)]
+    pub session: Option<TradingSession>,
+    /// Producer that stamped `sequence`
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Per-symbol, per-source sequence number, starting at 1
+    #[serde(default)]
+    pub sequence: Option<u64>,
 }
 
 impl MarketTick {
@@ -20,9 +30,41 @@ impl MarketTick {
             price,
             volume,
             timestamp: Utc::now(),
+            session: None,
+            source: None,
+            sequence: None,
         }
     }
 
+    /// Build a tick with an explicit timestamp, e.g. from a simulated clock or a replayed file
+    pub fn with_timestamp(
+        symbol: String,
+        price: Decimal,
+        volume: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        MarketTick {
+            symbol,
+            price,
+            volume,
+            timestamp,
+            session: None,
+            source: None,
+            sequence: None,
+        }
+    }
+
+    pub fn with_session(mut self, session: TradingSession) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    pub fn with_sequence(mut self, source: &str, sequence: u64) -> Self {
+        self.source = Some(source.to_string());
+        self.sequence = Some(sequence);
+        self
+    }
+
     pub fn is_significant_volume(&self) -> bool {
         self.volume > 1000
     }
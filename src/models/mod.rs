@@ -1,5 +1,13 @@
 //! Data models for financial market data
 
+mod book_update;
+mod calendar;
 mod market_tick;
+mod quote;
+mod sequence;
 
+pub use book_update::*;
+pub use calendar::*;
 pub use market_tick::*;
+pub use quote::*;
+pub use sequence::*;
@@ -0,0 +1,143 @@
+use crate::models::{BookUpdate, MarketTick};
+use chrono::{DateTime, Utc};
+use rand::random_range;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Top-of-book bid/ask quote from a single venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub symbol: String,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub bid_size: u64,
+    pub ask_size: u64,
+    pub venue: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Quote {
+    pub fn new(
+        symbol: String,
+        bid: Decimal,
+        ask: Decimal,
+        bid_size: u64,
+        ask_size: u64,
+        venue: String,
+    ) -> Self {
+        Quote {
+            symbol,
+            bid,
+            ask,
+            bid_size,
+            ask_size,
+            venue,
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn spread(&self) -> Decimal {
+        self.ask - self.bid
+    }
+
+    pub fn mid_price(&self) -> Decimal {
+        (self.bid + self.ask) / Decimal::TWO
+    }
+
+    /// Bid at or above the ask, which a healthy market should not show
+    pub fn is_crossed(&self) -> bool {
+        self.bid >= self.ask
+    }
+}
+
+/// Anything a producer can put on the wire for the hub
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MarketEvent {
+    Trade(MarketTick),
+    Quote(Quote),
+    Book(BookUpdate),
+}
+
+impl MarketEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketEvent::Trade(tick) => &tick.symbol,
+            MarketEvent::Quote(quote) => &quote.symbol,
+            MarketEvent::Book(update) => &update.symbol,
+        }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            MarketEvent::Trade(tick) => tick.timestamp,
+            MarketEvent::Quote(quote) => quote.timestamp,
+            MarketEvent::Book(update) => update.timestamp,
+        }
+    }
+}
+
+impl From<MarketTick> for MarketEvent {
+    fn from(tick: MarketTick) -> Self {
+        MarketEvent::Trade(tick)
+    }
+}
+
+impl From<Quote> for MarketEvent {
+    fn from(quote: Quote) -> Self {
+        MarketEvent::Quote(quote)
+    }
+}
+
+impl From<BookUpdate> for MarketEvent {
+    fn from(update: BookUpdate) -> Self {
+        MarketEvent::Book(update)
+    }
+}
+
+use tokio::time::{Duration, sleep};
+
+/// Quote counterpart of `fetch_market_data`: a random spread of 1-25 cents around a random mid
+pub async fn fetch_market_quote(symbol: &str, venue: &str) -> Result<Quote, String> {
+    sleep(Duration::from_millis(100)).await;
+    if symbol == "INVALID" {
+        return Err(format!("Invalid symbol {symbol}"));
+    }
+    let mid_cents = random_range(100i64..10000i64);
+    let half_spread_cents = random_range(1i64..=25i64);
+    Ok(Quote::new(
+        symbol.to_string(),
+        Decimal::new(mid_cents - half_spread_cents, 2),
+        Decimal::new(mid_cents + half_spread_cents, 2),
+        random_range(1..2000),
+        random_range(1..2000),
+        venue.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spread_and_mid() {
+        let quote = Quote::new(
+            "AAPL".to_string(),
+            Decimal::new(10000, 2),
+            Decimal::new(10010, 2),
+            100,
+            200,
+            "XNAS".to_string(),
+        );
+        assert_eq!(quote.spread(), Decimal::new(10, 2));
+        assert_eq!(quote.mid_price(), Decimal::new(10005, 2));
+        assert!(!quote.is_crossed());
+        assert_eq!(MarketEvent::from(quote).symbol(), "AAPL");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_market_quote() {
+        let quote = fetch_market_quote("BTI", "XNYS").await.unwrap();
+        assert!(quote.bid < quote.ask);
+        assert!(fetch_market_quote("INVALID", "XNYS").await.is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::models::MarketTick;
+use std::collections::HashMap;
+
+/// Sequence number a `TickSequencer` gives each symbol's first tick
+pub const FIRST_SEQUENCE: u64 = 1;
+
+/// Stamps ticks with per-symbol sequence numbers on behalf of one producer
+#[derive(Debug, Clone)]
+pub struct TickSequencer {
+    source: String,
+    next: HashMap<String, u64>,
+}
+
+impl TickSequencer {
+    pub fn new(source: impl Into<String>) -> Self {
+        TickSequencer {
+            source: source.into(),
+            next: HashMap::new(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Give `tick` the next sequence number for its symbol
+    pub fn stamp(&mut self, tick: MarketTick) -> MarketTick {
+        let next = self
+            .next
+            .entry(tick.symbol.clone())
+            .or_insert(FIRST_SEQUENCE);
+        let sequence = *next;
+        *next += 1;
+        tick.with_sequence(&self.source, sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_numbers_each_symbol_independently() {
+        let mut sequencer = TickSequencer::new("feed-a");
+        let tick = |symbol: &str| MarketTick::new(symbol.to_string(), Decimal::ONE, 1);
+        let stamped: Vec<_> = ["AAPL", "MSFT", "AAPL"]
+            .into_iter()
+            .map(|symbol| sequencer.stamp(tick(symbol)))
+            .collect();
+        assert_eq!(
+            stamped.iter().map(|t| t.sequence).collect::<Vec<_>>(),
+            vec![Some(1), Some(1), Some(2)]
+        );
+        assert_eq!(stamped[0].source.as_deref(), Some("feed-a"));
+    }
+}
@@ -1,91 +1,480 @@
-use crate::models::MarketTick;
+use crate::models::{ExchangeCalendar, MarketEvent, MarketTick, Quote};
+use crate::processor::bars::{Bar, BarBuilder, BarInterval};
+use crate::processor::gaps::GapStats;
+use crate::processor::sketch::Quantiles;
+use crate::processor::stats::{RunningQuoteStats, RunningStats};
+use crate::processor::window::{
+    MAX_TRACKED_WINDOWS, RollingWindow, StatsWindow, WindowError, WindowStats,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
-use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
-use std::collections::HashMap;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::time::Instant;
+use tokio::time::{Duration, Instant};
+
+/// Trading days of per-day statistics kept by default
+const DEFAULT_DAILY_RETENTION: usize = 30;
+
+/// What happens to cumulative statistics when a new trading day starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayBoundary {
+    /// Keep accumulating; per-day statistics are tracked alongside
+    Split,
+    /// Clear price and quote statistics so they only cover the current trading day
+    Reset,
+}
+
+/// Serializable aggregator state, for snapshots
+/// Rolling windows are not included; they refill from live ticks after a restore
+#[derive(Serialize, Deserialize)]
+pub struct AggregatorState {
+    pub symbol_stats: HashMap<String, RunningStats>,
+    pub quote_stats: HashMap<String, RunningQuoteStats>,
+    pub bars: BarBuilder,
+    pub latest_timestamp: Option<DateTime<Utc>>,
+    pub current_trading_day: Option<NaiveDate>,
+    pub daily_stats: BTreeMap<NaiveDate, HashMap<String, RunningStats>>,
+    /// Seconds the aggregator had been running, so `duration_secs` carries on from there
+    pub elapsed_secs: f64,
+}
 
 pub struct PriceAggregator {
-    symbol_prices: HashMap<String, Vec<Decimal>>,
+    symbol_stats: HashMap<String, RunningStats>,
+    quote_stats: HashMap<String, RunningQuoteStats>,
+    history_capacity: Option<usize>,
+    bars: BarBuilder,
+    windows: HashMap<StatsWindow, HashMap<String, RollingWindow>>,
+    latest_timestamp: Option<DateTime<Utc>>,
+    trading_days: Option<(ExchangeCalendar, DayBoundary)>,
+    current_trading_day: Option<NaiveDate>,
+    daily_stats: BTreeMap<NaiveDate, HashMap<String, RunningStats>>,
+    daily_retention: usize,
     start_time: Instant,
 }
 
+impl Default for PriceAggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PriceAggregator {
     pub fn new() -> Self {
         // todo implement constructor
         PriceAggregator {
-            symbol_prices: HashMap::new(),
+            symbol_stats: HashMap::new(),
+            quote_stats: HashMap::new(),
+            history_capacity: None,
+            bars: BarBuilder::new(&[]),
+            windows: HashMap::new(),
+            latest_timestamp: None,
+            trading_days: None,
+            current_trading_day: None,
+            daily_stats: BTreeMap::new(),
+            daily_retention: DEFAULT_DAILY_RETENTION,
             start_time: Instant::now(),
         }
     }
 
+    /// Also build OHLCV bars for the given intervals
+    /// Callers must take completed bars with `drain_completed_bars`; once
+    /// `MAX_COMPLETED_BARS` are waiting the oldest are dropped, see `dropped_bar_count`
+    pub fn with_bar_intervals(mut self, intervals: &[BarInterval]) -> Self {
+        self.bars = BarBuilder::new(intervals);
+        self
+    }
+
+    /// Keep the last `capacity` prices per symbol, available via `recent_prices`
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = Some(capacity);
+        self
+    }
+
+    /// Also maintain rolling statistics over `window`
+    pub fn with_window(mut self, window: StatsWindow) -> Self {
+        if let Err(e) = self.track_window(window) {
+            println!("Not tracking window {window:?}: {e}");
+        }
+        self
+    }
+
+    /// Also keep statistics per exchange trading day, rolling over as `boundary` says
+    pub fn with_trading_calendar(
+        mut self,
+        calendar: ExchangeCalendar,
+        boundary: DayBoundary,
+    ) -> Self {
+        self.trading_days = Some((calendar, boundary));
+        self
+    }
+
+    /// Keep per-day statistics for the most recent `days` trading days only
+    pub fn with_daily_retention(mut self, days: usize) -> Self {
+        self.daily_retention = days;
+        self.prune_daily_stats();
+        self
+    }
+
+    /// Start maintaining a rolling window; it only sees ticks added from now on
+    pub fn track_window(&mut self, window: StatsWindow) -> Result<(), WindowError> {
+        window.validate()?;
+        if !self.windows.contains_key(&window) && self.windows.len() >= MAX_TRACKED_WINDOWS {
+            return Err(WindowError::TooManyWindows);
+        }
+        self.windows.entry(window).or_default();
+        Ok(())
+    }
+
+    pub fn is_tracking_window(&self, window: StatsWindow) -> bool {
+        self.windows.contains_key(&window)
+    }
+
     pub fn add_tick(&mut self, tick: MarketTick) {
         let mut sum = 0.0;
         for i in 0..100 {
             sum += (tick.price.to_f64().unwrap() * i as f64).sin()
         }
-        self.symbol_prices
-            .entry(tick.symbol)
+        std::hint::black_box(sum);
+        self.roll_trading_day(&tick);
+        self.bars.add_tick(&tick);
+        self.latest_timestamp = self.latest_timestamp.max(Some(tick.timestamp));
+        for (window, symbol_windows) in self.windows.iter_mut() {
+            symbol_windows
+                .entry(tick.symbol.clone())
+                .or_insert_with(|| RollingWindow::new(*window))
+                .push(&tick);
+        }
+        let history_capacity = self.history_capacity;
+        self.symbol_stats
+            .entry(tick.symbol.clone())
+            .or_insert_with(|| RunningStats::new(history_capacity))
+            .add(&tick);
+    }
+
+    pub fn add_quote(&mut self, quote: &Quote) {
+        self.latest_timestamp = self.latest_timestamp.max(Some(quote.timestamp));
+        self.quote_stats
+            .entry(quote.symbol.clone())
             .or_default()
-            .push(tick.price);
+            .add(quote);
+    }
+
+    /// Route a trade or quote to the matching accumulator
+    /// Book updates carry no statistics here; book state lives in `OrderBook`
+    pub fn add_event(&mut self, event: MarketEvent) {
+        match event {
+            MarketEvent::Trade(tick) => self.add_tick(tick),
+            MarketEvent::Quote(quote) => self.add_quote(&quote),
+            MarketEvent::Book(_) => {}
+        }
+    }
+
+    pub fn get_quote_statistics(&self, symbol: &str) -> Option<QuoteStats> {
+        let stats = self.quote_stats.get(symbol)?;
+        let last = stats.last()?;
+
+        Some(QuoteStats {
+            symbol: symbol.to_string(),
+            count: stats.count(),
+            crossed_count: stats.crossed_count(),
+            last_bid: last.bid,
+            last_ask: last.ask,
+            last_mid_price: last.mid_price(),
+            avg_mid_price: stats.avg_mid_price()?,
+            avg_spread: stats.avg_spread()?,
+            min_spread: stats.min_spread()?,
+            max_spread: stats.max_spread()?,
+        })
     }
 
     pub fn get_statistics(&self, symbol: &str) -> Option<PriceStats> {
-        let prices = self.symbol_prices.get(symbol)?;
+        self.price_stats(symbol, self.symbol_stats.get(symbol)?)
+    }
 
-        if prices.is_empty() {
-            return None;
+    /// Copy of everything needed to rebuild this aggregator's statistics and bars
+    pub fn state(&self) -> AggregatorState {
+        AggregatorState {
+            symbol_stats: self.symbol_stats.clone(),
+            quote_stats: self.quote_stats.clone(),
+            bars: self.bars.clone(),
+            latest_timestamp: self.latest_timestamp,
+            current_trading_day: self.current_trading_day,
+            daily_stats: self.daily_stats.clone(),
+            elapsed_secs: (Instant::now() - self.start_time).as_secs_f64(),
         }
+    }
+
+    /// Replace statistics and bars with a previously captured state
+    /// Configuration (history capacity, bar intervals, windows, trading calendar, daily
+    /// retention) is kept from `self`; only accumulated values are taken from `state`
+    pub fn restore(&mut self, state: AggregatorState) {
+        let history_capacity = self.history_capacity;
+        let resize = |symbols: HashMap<String, RunningStats>| -> HashMap<String, RunningStats> {
+            symbols
+                .into_iter()
+                .map(|(symbol, stats)| (symbol, stats.with_history_capacity(history_capacity)))
+                .collect()
+        };
+        self.symbol_stats = resize(state.symbol_stats);
+        self.quote_stats = state.quote_stats;
+        self.bars.restore(state.bars);
+        self.latest_timestamp = state.latest_timestamp;
+        self.current_trading_day = state.current_trading_day;
+        self.daily_stats = state
+            .daily_stats
+            .into_iter()
+            .map(|(day, symbols)| (day, resize(symbols)))
+            .collect();
+        self.prune_daily_stats();
+        let elapsed = Duration::try_from_secs_f64(state.elapsed_secs).unwrap_or_default();
+        self.start_time = Instant::now()
+            .checked_sub(elapsed)
+            .unwrap_or(self.start_time);
+    }
+
+    /// Statistics for one trading day, if a trading calendar is configured
+    pub fn get_daily_statistics(&self, symbol: &str, day: NaiveDate) -> Option<PriceStats> {
+        self.price_stats(symbol, self.daily_stats.get(&day)?.get(symbol)?)
+    }
 
-        let min_price = *prices.iter().min()?;
-        let max_price = *prices.iter().max()?;
+    /// Trading days seen so far, oldest first
+    pub fn trading_days(&self) -> Vec<NaiveDate> {
+        self.daily_stats.keys().copied().collect()
+    }
 
-        let sum: Decimal = prices.iter().sum();
-        let count = prices.len();
+    /// Clear all price, quote and per-day statistics; bars and rolling windows are kept
+    pub fn reset_statistics(&mut self) {
+        self.symbol_stats.clear();
+        self.quote_stats.clear();
+        self.daily_stats.clear();
+        self.current_trading_day = None;
+    }
 
-        let avg_price = sum / Decimal::from_usize(count)?;
+    fn roll_trading_day(&mut self, tick: &MarketTick) {
+        let Some((calendar, boundary)) = &self.trading_days else {
+            return;
+        };
+        let day = calendar.trading_day(tick.timestamp);
+        if *boundary == DayBoundary::Reset && self.current_trading_day.is_some_and(|d| day > d) {
+            self.symbol_stats.clear();
+            self.quote_stats.clear();
+        }
+        self.current_trading_day = self.current_trading_day.max(Some(day));
+        let history_capacity = self.history_capacity;
+        self.daily_stats
+            .entry(day)
+            .or_default()
+            .entry(tick.symbol.clone())
+            .or_insert_with(|| RunningStats::new(history_capacity))
+            .add(tick);
+        self.prune_daily_stats();
+    }
 
+    fn prune_daily_stats(&mut self) {
+        while self.daily_stats.len() > self.daily_retention {
+            self.daily_stats.pop_first();
+        }
+    }
+
+    fn price_stats(&self, symbol: &str, stats: &RunningStats) -> Option<PriceStats> {
         Some(PriceStats {
-            min_price,
-            max_price,
-            count,
-            avg_price,
+            min_price: stats.min()?,
+            max_price: stats.max()?,
+            count: stats.count(),
+            avg_price: stats.mean()?,
+            std_dev: stats.std_dev().unwrap_or_default(),
+            total_volume: stats.total_volume(),
+            vwap: stats.vwap(),
+            notional: stats.notional(),
+            significant_volume_count: stats.significant_count(),
+            overflowed: stats.overflowed(),
+            price_quantiles: stats.price_quantiles().unwrap_or_default(),
+            volume_quantiles: stats.volume_quantiles().unwrap_or_default(),
             symbol: symbol.to_string(),
             duration_secs: (Instant::now() - self.start_time).as_secs_f64(),
+            gaps: GapStats::default(),
         })
     }
 
+    /// Fold another aggregator's per-symbol statistics and quantile sketches into this one
+    /// Bars and rolling windows are order-dependent and are not merged
+    pub fn merge(&mut self, other: PriceAggregator) {
+        for (symbol, stats) in other.symbol_stats {
+            match self.symbol_stats.get_mut(&symbol) {
+                Some(existing) => existing.merge(&stats),
+                None => {
+                    self.symbol_stats.insert(symbol, stats);
+                }
+            }
+        }
+        for (symbol, stats) in other.quote_stats {
+            self.quote_stats.entry(symbol).or_default().merge(&stats);
+        }
+        for (day, symbols) in other.daily_stats {
+            let day_stats = self.daily_stats.entry(day).or_default();
+            for (symbol, stats) in symbols {
+                match day_stats.get_mut(&symbol) {
+                    Some(existing) => existing.merge(&stats),
+                    None => {
+                        day_stats.insert(symbol, stats);
+                    }
+                }
+            }
+        }
+        self.prune_daily_stats();
+        self.current_trading_day = self.current_trading_day.max(other.current_trading_day);
+        self.latest_timestamp = self.latest_timestamp.max(other.latest_timestamp);
+    }
+
+    /// Rolling statistics for a symbol, `None` if the window is not tracked or empty
+    pub fn get_window_statistics(&self, symbol: &str, window: StatsWindow) -> Option<WindowStats> {
+        self.windows.get(&window)?.get(symbol)?.statistics(symbol)
+    }
+
+    /// Evict time-window entries that are older than their window as of `now`
+    /// Windows already evict on every tick; this catches up symbols that have gone quiet
+    pub fn expire_windows(&mut self, now: DateTime<Utc>) {
+        self.windows
+            .values_mut()
+            .flat_map(|symbol_windows| symbol_windows.values_mut())
+            .for_each(|window| window.expire(now));
+    }
+
+    /// Timestamp of the newest tick seen so far, i.e. the aggregator's notion of market time
+    pub fn latest_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.latest_timestamp
+    }
+
+    /// Most recent prices for a symbol, oldest first, when a history capacity is set
+    pub fn recent_prices(&self, symbol: &str) -> Option<&VecDeque<Decimal>> {
+        self.symbol_stats.get(symbol)?.recent_prices()
+    }
+
+    /// Bar currently being built for a symbol, if that interval is tracked
+    pub fn current_bar(&self, symbol: &str, interval: BarInterval) -> Option<&Bar> {
+        self.bars.current_bar(symbol, interval)
+    }
+
+    /// Complete bars whose interval has fully elapsed as of `now`
+    /// Quiet symbols only roll their bars over when this is called
+    pub fn close_elapsed_bars(&mut self, now: DateTime<Utc>) {
+        self.bars.close_elapsed(now);
+    }
+
+    /// Take all bars completed since the last call
+    pub fn drain_completed_bars(&mut self) -> Vec<Bar> {
+        self.bars.drain_completed()
+    }
+
+    /// Number of ticks that arrived after their bar was already completed
+    pub fn late_tick_count(&self) -> usize {
+        self.bars.late_ticks()
+    }
+
+    /// Number of completed bars dropped because they were not drained in time
+    pub fn dropped_bar_count(&self) -> usize {
+        self.bars.dropped_bars()
+    }
+
     pub fn print_summary(&self) {
         println!("\n=== Price Statistics Summary ===");
         println!(
             "Total runtime: {:.2}s",
             self.start_time.elapsed().as_secs_f64()
         );
-        self.symbol_prices.keys().for_each(|symbol| {
+        self.symbol_stats.keys().for_each(|symbol| {
             if let Some(stats) = self.get_statistics(symbol) {
                 println!("\n{symbol} Statistics");
                 println!("  Tick Count: {}", stats.count);
                 println!("  Min Price: ${}", stats.min_price);
                 println!("  Max Price: ${}", stats.max_price);
                 println!("  Avg Price: ${:.2}", stats.avg_price);
+                println!("  Std Dev: ${:.4}", stats.std_dev);
+                println!(
+                    "  Median / P95 / P99 Price: ${:.2} / ${:.2} / ${:.2}",
+                    stats.price_quantiles.median,
+                    stats.price_quantiles.p95,
+                    stats.price_quantiles.p99
+                );
+                match stats.vwap {
+                    Some(vwap) => println!("  VWAP: ${vwap:.2}"),
+                    None => println!("  VWAP: n/a (no volume)"),
+                }
+                println!("  Total Volume: {}", stats.total_volume);
+                println!("  Notional: ${:.2}", stats.notional);
+                println!(
+                    "  Significant Volume Ticks: {}",
+                    stats.significant_volume_count
+                );
                 println!("  Price Range: ${}", stats.max_price - stats.min_price)
             }
         });
-        println!("\nTotal symbols tracked: {}", self.symbol_prices.len());
-        let total_ticks: usize = self.symbol_prices.values().map(|v| v.len()).sum();
+        self.quote_stats.keys().for_each(|symbol| {
+            if let Some(stats) = self.get_quote_statistics(symbol) {
+                println!("\n{symbol} Quote Statistics");
+                println!("  Quote Count: {}", stats.count);
+                println!(
+                    "  Last Bid / Ask: ${} / ${}",
+                    stats.last_bid, stats.last_ask
+                );
+                println!("  Avg Mid Price: ${:.2}", stats.avg_mid_price);
+                println!("  Avg Spread: ${:.4}", stats.avg_spread);
+                println!(
+                    "  Min / Max Spread: ${} / ${}",
+                    stats.min_spread, stats.max_spread
+                );
+                println!("  Crossed Quotes: {}", stats.crossed_count);
+            }
+        });
+        println!("\nTotal symbols tracked: {}", self.symbol_stats.len());
+        let total_ticks: usize = self.symbol_stats.values().map(|s| s.count()).sum();
         println!("Total ticks processed: {total_ticks}")
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PriceStats {
     pub symbol: String,
     pub count: usize,
     pub min_price: Decimal,
     pub max_price: Decimal,
     pub avg_price: Decimal,
+    /// Population standard deviation of price
+    pub std_dev: Decimal,
+    pub total_volume: u64,
+    /// Volume-weighted average price, `None` if every tick had zero volume
+    pub vwap: Option<Decimal>,
+    /// Sum of price x volume over all ticks
+    pub notional: Decimal,
+    /// Ticks where `MarketTick::is_significant_volume` was true
+    pub significant_volume_count: usize,
+    /// Price sums overflowed; `avg_price`, `std_dev`, `vwap` and `notional` cover only the
+    /// ticks before that
+    pub overflowed: bool,
+    /// Estimated from a quantile sketch, not exact
+    pub price_quantiles: Quantiles,
+    pub volume_quantiles: Quantiles,
     pub duration_secs: f64,
+    /// Sequence gaps and duplicates seen by the hub; always zero straight from an aggregator
+    pub gaps: GapStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuoteStats {
+    pub symbol: String,
+    pub count: usize,
+    /// Quotes seen with bid >= ask
+    pub crossed_count: usize,
+    pub last_bid: Decimal,
+    pub last_ask: Decimal,
+    pub last_mid_price: Decimal,
+    pub avg_mid_price: Decimal,
+    pub avg_spread: Decimal,
+    pub min_spread: Decimal,
+    pub max_spread: Decimal,
 }
 
 pub struct HighThroughputProcessor {
@@ -112,9 +501,11 @@ impl HighThroughputProcessor {
         let mut handles = vec![];
         for _ in 0..self.consumer_count {
             let cloned_rx = rx.clone();
-            let aggregator = self.aggregator.clone();
 
+            // Each consumer aggregates into its own PriceAggregator so there is no
+            // shared lock on the hot path; the results are merged once the stream ends
             let handle = tokio::spawn(async move {
+                let mut local = PriceAggregator::new();
                 loop {
                     // Lock the receiver to get exclusive access
                     let tick = {
@@ -124,28 +515,194 @@ impl HighThroughputProcessor {
 
                     // Check if we got a tick or if channel is closed
                     match tick {
-                        Some(tick) => {
-                            // Process the tick
-                            let mut agg = aggregator.lock().await;
-                            agg.add_tick(tick);
-                            // Lock automatically released when agg goes out of scope
-                        }
+                        Some(tick) => local.add_tick(tick),
                         None => {
                             // Channel closed, no more ticks coming
                             break;
                         }
                     }
                 }
+                local
             });
 
             handles.push(handle);
         }
 
-        // Wait for all consumer tasks to complete
+        // Wait for all consumer tasks and merge every one that finished, even if another failed
+        let mut first_error = None;
         for handle in handles {
-            handle.await?;
+            match handle.await {
+                Ok(local) => self.aggregator.lock().await.merge(local),
+                Err(e) => {
+                    println!("Consumer task failed, its ticks are missing from the totals: {e}");
+                    first_error.get_or_insert(e);
+                }
+            }
         }
 
-        Ok(())
+        match first_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Combined statistics across all consumers
+    pub async fn get_statistics(&self, symbol: &str) -> Option<PriceStats> {
+        self.aggregator.lock().await.get_statistics(symbol)
+    }
+
+    pub async fn print_summary(&self) {
+        self.aggregator.lock().await.print_summary();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_weighted_statistics() {
+        let mut agg = PriceAggregator::new();
+        agg.add_tick(MarketTick::new(
+            "AAPL".to_string(),
+            Decimal::new(10000, 2),
+            100,
+        ));
+        agg.add_tick(MarketTick::new(
+            "AAPL".to_string(),
+            Decimal::new(11000, 2),
+            300,
+        ));
+        agg.add_tick(MarketTick::new(
+            "AAPL".to_string(),
+            Decimal::new(20000, 2),
+            1500,
+        ));
+
+        let stats = agg.get_statistics("AAPL").unwrap();
+        assert_eq!(stats.total_volume, 1900);
+        assert_eq!(stats.notional, Decimal::new(343000, 0));
+        // 343000 / 1900, not the plain mean of 143.33
+        assert_eq!(stats.vwap.unwrap().round_dp(4), Decimal::new(1805263, 4));
+        assert_eq!(stats.significant_volume_count, 1);
+    }
+
+    #[test]
+    fn test_vwap_without_volume() {
+        let mut agg = PriceAggregator::new();
+        agg.add_tick(MarketTick::new(
+            "MSFT".to_string(),
+            Decimal::new(5000, 2),
+            0,
+        ));
+
+        let stats = agg.get_statistics("MSFT").unwrap();
+        assert_eq!(stats.vwap, None);
+        assert_eq!(stats.notional, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_window_statistics() {
+        let mut agg = PriceAggregator::new().with_window(StatsWindow::Ticks(2));
+        for cents in [10000, 20000, 30000] {
+            agg.add_tick(MarketTick::new(
+                "AAPL".to_string(),
+                Decimal::new(cents, 2),
+                10,
+            ));
+        }
+
+        let window = agg
+            .get_window_statistics("AAPL", StatsWindow::Ticks(2))
+            .unwrap();
+        assert_eq!(window.count, 2);
+        assert_eq!(window.avg_price, Decimal::new(250, 0));
+        assert_eq!(agg.get_statistics("AAPL").unwrap().count, 3);
+        assert!(
+            agg.get_window_statistics("AAPL", StatsWindow::Seconds(60))
+                .is_none()
+        );
+
+        for n in 3..=MAX_TRACKED_WINDOWS + 1 {
+            assert_eq!(agg.track_window(StatsWindow::Ticks(n)), Ok(()));
+        }
+        assert_eq!(
+            agg.track_window(StatsWindow::Seconds(1)),
+            Err(WindowError::TooManyWindows)
+        );
+    }
+
+    #[test]
+    fn test_trading_day_split_and_reset() {
+        use chrono::TimeZone;
+
+        let new_york = |d, h| {
+            chrono_tz::America::New_York
+                .with_ymd_and_hms(2025, 1, d, h, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        // 19:00 New York on the 14th is already the 15th in UTC
+        let ticks = [(14, 10, 10000), (14, 19, 11000), (15, 10, 12000)];
+        let run = |boundary, retention| {
+            let mut agg = PriceAggregator::new()
+                .with_trading_calendar(ExchangeCalendar::nyse(), boundary)
+                .with_daily_retention(retention);
+            for (day, hour, cents) in ticks {
+                agg.add_tick(MarketTick::with_timestamp(
+                    "AAPL".to_string(),
+                    Decimal::new(cents, 2),
+                    10,
+                    new_york(day, hour),
+                ));
+            }
+            agg
+        };
+
+        let split = run(DayBoundary::Split, 10);
+        let first_day = NaiveDate::from_ymd_opt(2025, 1, 14).unwrap();
+        assert_eq!(split.trading_days().len(), 2);
+        assert_eq!(split.get_statistics("AAPL").unwrap().count, 3);
+        let daily = split.get_daily_statistics("AAPL", first_day).unwrap();
+        assert_eq!(daily.count, 2);
+        assert_eq!(daily.max_price, Decimal::new(110, 0));
+
+        // Only the most recent day is kept
+        let pruned = run(DayBoundary::Split, 1);
+        assert_eq!(pruned.trading_days(), [first_day.succ_opt().unwrap()]);
+        assert!(pruned.get_daily_statistics("AAPL", first_day).is_none());
+
+        let mut reset = run(DayBoundary::Reset, 10);
+        assert_eq!(reset.get_statistics("AAPL").unwrap().count, 1);
+        assert_eq!(
+            reset.get_daily_statistics("AAPL", first_day).unwrap().count,
+            2
+        );
+        reset.reset_statistics();
+        assert!(reset.get_statistics("AAPL").is_none());
+        assert!(reset.trading_days().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multiple_consumers_merge() {
+        let (tx, rx) = mpsc::channel(100);
+        let producer = tokio::spawn(async move {
+            for i in 1..=1000 {
+                let tick = MarketTick::new("SYM".to_string(), Decimal::from(i), 10);
+                tx.send(tick).await.unwrap();
+            }
+        });
+
+        let processor = HighThroughputProcessor::new(4);
+        processor.process_market_stream(rx).await.unwrap();
+        producer.await.unwrap();
+
+        let stats = processor.get_statistics("SYM").await.unwrap();
+        assert_eq!(stats.count, 1000);
+        assert_eq!(stats.min_price, Decimal::from(1));
+        assert_eq!(stats.max_price, Decimal::from(1000));
+        assert_eq!(stats.avg_price, Decimal::new(5005, 1));
+        let median = stats.price_quantiles.median;
+        assert!((median - Decimal::from(500)).abs() < Decimal::from(10));
     }
 }
@@ -0,0 +1,427 @@
+use crate::models::MarketTick;
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// Most empty bars emitted for one gap in a series; earlier intervals in a longer gap are skipped
+pub const MAX_FILLED_BARS: i64 = 1000;
+
+/// Most completed bars held for `BarBuilder::drain_completed`; older ones are dropped past this
+pub const MAX_COMPLETED_BARS: usize = 10_000;
+
+/// Supported bar lengths, aligned to wall-clock boundaries (e.g. 5m bars start at :00, :05, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BarInterval {
+    OneSecond,
+    OneMinute,
+    FiveMinutes,
+    OneHour,
+}
+
+impl BarInterval {
+    pub fn as_secs(&self) -> i64 {
+        match self {
+            BarInterval::OneSecond => 1,
+            BarInterval::OneMinute => 60,
+            BarInterval::FiveMinutes => 300,
+            BarInterval::OneHour => 3600,
+        }
+    }
+
+    pub fn duration(&self) -> TimeDelta {
+        TimeDelta::seconds(self.as_secs())
+    }
+
+    /// Start of the bar that contains `timestamp`
+    pub fn bar_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let secs = timestamp.timestamp();
+        let start = secs - secs.rem_euclid(self.as_secs());
+        DateTime::from_timestamp(start, 0).unwrap_or(timestamp)
+    }
+}
+
+/// One open/high/low/close/volume bar for a symbol and interval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bar {
+    pub symbol: String,
+    pub interval: BarInterval,
+    pub start: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: u64,
+    pub tick_count: usize,
+}
+
+impl Bar {
+    fn from_tick(tick: &MarketTick, interval: BarInterval, start: DateTime<Utc>) -> Self {
+        Bar {
+            symbol: tick.symbol.clone(),
+            interval,
+            start,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.volume,
+            tick_count: 1,
+        }
+    }
+
+    /// A bar for an interval with no trades, flat at the previous close
+    fn empty(symbol: &str, interval: BarInterval, start: DateTime<Utc>, price: Decimal) -> Self {
+        Bar {
+            symbol: symbol.to_string(),
+            interval,
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0,
+            tick_count: 0,
+        }
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        self.start + self.interval.duration()
+    }
+
+    /// True when no ticks fell inside this bar's interval
+    pub fn is_empty(&self) -> bool {
+        self.tick_count == 0
+    }
+}
+
+// Per (symbol, interval) state: the bar being built plus the last bar we handed out,
+// which is needed to fill empty intervals and to recognise late ticks
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct BarSeries {
+    open_bar: Option<Bar>,
+    last_tick_time: Option<DateTime<Utc>>,
+    last_closed: Option<(DateTime<Utc>, Decimal)>,
+}
+
+impl BarSeries {
+    fn close_open_bar(&mut self, completed: &mut Vec<Bar>) {
+        if let Some(bar) = self.open_bar.take() {
+            self.last_closed = Some((bar.start, bar.close));
+            completed.push(bar);
+        }
+    }
+
+    // Emit flat bars for the intervals strictly between the last closed bar and `until`,
+    // at most MAX_FILLED_BARS of them; returns how many intervals were skipped instead
+    fn fill_empty(
+        &mut self,
+        symbol: &str,
+        interval: BarInterval,
+        until: DateTime<Utc>,
+        completed: &mut Vec<Bar>,
+    ) -> usize {
+        let mut skipped = 0;
+        if let Some((start, close)) = self.last_closed {
+            let missing = (until - start).num_seconds() / interval.as_secs() - 1;
+            if missing > MAX_FILLED_BARS {
+                let skip = missing - MAX_FILLED_BARS;
+                self.last_closed =
+                    Some((start + TimeDelta::seconds(skip * interval.as_secs()), close));
+                skipped = skip as usize;
+            }
+        }
+        while let Some((start, close)) = self.last_closed {
+            let next = start + interval.duration();
+            if next >= until {
+                break;
+            }
+            completed.push(Bar::empty(symbol, interval, next, close));
+            self.last_closed = Some((next, close));
+        }
+        skipped
+    }
+}
+
+// JSON map keys must be strings, so the (symbol, interval) map is stored as a list of entries
+mod series_entries {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        series: &HashMap<(String, BarInterval), BarSeries>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(series.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(String, BarInterval), BarSeries>, D::Error> {
+        let entries = Vec::<((String, BarInterval), BarSeries)>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// Builds OHLCV bars per symbol for a set of intervals
+///
+/// Bars are completed when a tick for a later interval arrives or when
+/// `close_elapsed` is called with a time past the bar's end. Intervals with no
+/// ticks produce empty bars flat at the previous close, up to `MAX_FILLED_BARS`
+/// per gap; the rest of a longer gap is counted and skipped. Ticks that belong to a
+/// bar that has already been completed are counted and dropped. Completed bars wait
+/// for `drain_completed`; past `MAX_COMPLETED_BARS` the oldest are counted and dropped.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BarBuilder {
+    intervals: Vec<BarInterval>,
+    #[serde(with = "series_entries")]
+    series: HashMap<(String, BarInterval), BarSeries>,
+    completed: Vec<Bar>,
+    late_ticks: usize,
+    #[serde(default)]
+    skipped_empty_bars: usize,
+    #[serde(default)]
+    dropped_bars: usize,
+}
+
+impl BarBuilder {
+    pub fn new(intervals: &[BarInterval]) -> Self {
+        let mut intervals = intervals.to_vec();
+        intervals.sort();
+        intervals.dedup();
+        BarBuilder {
+            intervals,
+            series: HashMap::new(),
+            completed: Vec::new(),
+            late_ticks: 0,
+            skipped_empty_bars: 0,
+            dropped_bars: 0,
+        }
+    }
+
+    pub fn intervals(&self) -> &[BarInterval] {
+        &self.intervals
+    }
+
+    /// Take open and completed bars from `state` for the intervals configured here
+    /// Bars for intervals that are no longer configured are dropped
+    pub fn restore(&mut self, state: BarBuilder) {
+        let intervals = &self.intervals;
+        self.series = state
+            .series
+            .into_iter()
+            .filter(|((_, interval), _)| intervals.contains(interval))
+            .collect();
+        self.completed = state
+            .completed
+            .into_iter()
+            .filter(|bar| intervals.contains(&bar.interval))
+            .collect();
+        self.late_ticks = state.late_ticks;
+        self.skipped_empty_bars = state.skipped_empty_bars;
+        self.dropped_bars = state.dropped_bars;
+        self.cap_completed();
+    }
+
+    pub fn add_tick(&mut self, tick: &MarketTick) {
+        for &interval in &self.intervals {
+            let start = interval.bar_start(tick.timestamp);
+            let series = self
+                .series
+                .entry((tick.symbol.clone(), interval))
+                .or_default();
+
+            match &mut series.open_bar {
+                Some(bar) if bar.start == start => {
+                    bar.high = bar.high.max(tick.price);
+                    bar.low = bar.low.min(tick.price);
+                    bar.volume += tick.volume;
+                    bar.tick_count += 1;
+                    // Out-of-order ticks inside the bar still count, but only the latest sets the close
+                    if series.last_tick_time.is_none_or(|t| tick.timestamp >= t) {
+                        bar.close = tick.price;
+                        series.last_tick_time = Some(tick.timestamp);
+                    }
+                    continue;
+                }
+                Some(bar) if start < bar.start => {
+                    self.late_ticks += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if series
+                .last_closed
+                .is_some_and(|(closed_start, _)| start <= closed_start)
+            {
+                self.late_ticks += 1;
+                continue;
+            }
+
+            series.close_open_bar(&mut self.completed);
+            self.skipped_empty_bars +=
+                series.fill_empty(&tick.symbol, interval, start, &mut self.completed);
+            series.open_bar = Some(Bar::from_tick(tick, interval, start));
+            series.last_tick_time = Some(tick.timestamp);
+        }
+        self.cap_completed();
+    }
+
+    /// Complete every bar (including empty ones) whose interval ended at or before `now`
+    pub fn close_elapsed(&mut self, now: DateTime<Utc>) {
+        for ((symbol, interval), series) in self.series.iter_mut() {
+            let current_start = interval.bar_start(now);
+            if series.open_bar.as_ref().is_some_and(|bar| bar.end() <= now) {
+                series.close_open_bar(&mut self.completed);
+            }
+            if series.open_bar.is_none() {
+                self.skipped_empty_bars +=
+                    series.fill_empty(symbol, *interval, current_start, &mut self.completed);
+            }
+        }
+        self.cap_completed();
+    }
+
+    // Drop the oldest undrained bars beyond MAX_COMPLETED_BARS
+    fn cap_completed(&mut self) {
+        let excess = self.completed.len().saturating_sub(MAX_COMPLETED_BARS);
+        if excess > 0 {
+            self.completed.drain(..excess);
+            self.dropped_bars += excess;
+        }
+    }
+
+    /// Bar currently being built for a symbol and interval
+    pub fn current_bar(&self, symbol: &str, interval: BarInterval) -> Option<&Bar> {
+        self.series
+            .get(&(symbol.to_string(), interval))?
+            .open_bar
+            .as_ref()
+    }
+
+    /// Take all bars completed since the last call, in completion order
+    pub fn drain_completed(&mut self) -> Vec<Bar> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// Number of ticks dropped because their bar had already been completed
+    pub fn late_ticks(&self) -> usize {
+        self.late_ticks
+    }
+
+    /// Number of empty intervals not emitted because a gap exceeded `MAX_FILLED_BARS`
+    pub fn skipped_empty_bars(&self) -> usize {
+        self.skipped_empty_bars
+    }
+
+    /// Number of completed bars dropped because `MAX_COMPLETED_BARS` were waiting to be drained
+    pub fn dropped_bars(&self) -> usize {
+        self.dropped_bars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tick_at(secs: i64, cents: i64, volume: u64) -> MarketTick {
+        let mut tick = MarketTick::new("AAPL".to_string(), Decimal::new(cents, 2), volume);
+        tick.timestamp = Utc.timestamp_opt(secs, 0).unwrap();
+        tick
+    }
+
+    #[test]
+    fn test_bar_start_alignment() {
+        let ts = Utc.with_ymd_and_hms(2025, 7, 21, 14, 37, 42).unwrap();
+        assert_eq!(
+            BarInterval::FiveMinutes.bar_start(ts),
+            Utc.with_ymd_and_hms(2025, 7, 21, 14, 35, 0).unwrap()
+        );
+        assert_eq!(
+            BarInterval::OneHour.bar_start(ts),
+            Utc.with_ymd_and_hms(2025, 7, 21, 14, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ohlcv_and_empty_intervals() {
+        let mut builder = BarBuilder::new(&[BarInterval::OneMinute]);
+        builder.add_tick(&tick_at(0, 10000, 10));
+        builder.add_tick(&tick_at(10, 10500, 5));
+        builder.add_tick(&tick_at(20, 9900, 1));
+        builder.add_tick(&tick_at(30, 10100, 4));
+        // Skips the 60s and 120s bars entirely
+        builder.add_tick(&tick_at(185, 10200, 7));
+
+        let bars = builder.drain_completed();
+        assert_eq!(bars.len(), 3);
+        let first = &bars[0];
+        assert_eq!(first.open, Decimal::new(10000, 2));
+        assert_eq!(first.high, Decimal::new(10500, 2));
+        assert_eq!(first.low, Decimal::new(9900, 2));
+        assert_eq!(first.close, Decimal::new(10100, 2));
+        assert_eq!(first.volume, 20);
+        assert!(bars[1].is_empty() && bars[2].is_empty());
+        assert_eq!(bars[2].close, Decimal::new(10100, 2));
+        assert_eq!(
+            builder
+                .current_bar("AAPL", BarInterval::OneMinute)
+                .unwrap()
+                .start,
+            Utc.timestamp_opt(180, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_late_ticks() {
+        let mut builder = BarBuilder::new(&[BarInterval::OneSecond]);
+        builder.add_tick(&tick_at(5, 100, 1));
+        builder.add_tick(&tick_at(6, 101, 1));
+        builder.add_tick(&tick_at(5, 99, 1));
+        assert_eq!(builder.late_ticks(), 1);
+
+        builder.close_elapsed(Utc.timestamp_opt(9, 0).unwrap());
+        let bars = builder.drain_completed();
+        assert_eq!(bars.len(), 4);
+        assert_eq!(bars[0].low, Decimal::new(100, 2));
+        builder.add_tick(&tick_at(8, 100, 1));
+        assert_eq!(builder.late_ticks(), 2);
+    }
+
+    #[test]
+    fn test_long_gap_fills_a_bounded_number_of_bars() {
+        let mut builder = BarBuilder::new(&[BarInterval::OneSecond]);
+        builder.add_tick(&tick_at(0, 100, 1));
+        builder.add_tick(&tick_at(200_000, 101, 1));
+
+        let bars = builder.drain_completed();
+        assert_eq!(bars.len() as i64, MAX_FILLED_BARS + 1);
+        assert_eq!(bars[0].start, Utc.timestamp_opt(0, 0).unwrap());
+        assert_eq!(
+            bars.last().unwrap().start,
+            Utc.timestamp_opt(199_999, 0).unwrap()
+        );
+        assert_eq!(
+            builder.skipped_empty_bars() as i64,
+            199_999 - MAX_FILLED_BARS
+        );
+
+        builder.close_elapsed(Utc.timestamp_opt(400_000, 0).unwrap());
+        assert_eq!(builder.drain_completed().len() as i64, MAX_FILLED_BARS + 1);
+    }
+
+    #[test]
+    fn test_undrained_bars_are_capped() {
+        let mut builder = BarBuilder::new(&[BarInterval::OneSecond]);
+        let total = MAX_COMPLETED_BARS + 500;
+        for secs in 0..=total as i64 {
+            builder.add_tick(&tick_at(secs, 100, 1));
+        }
+
+        assert_eq!(builder.dropped_bars(), 500);
+        let bars = builder.drain_completed();
+        assert_eq!(bars.len(), MAX_COMPLETED_BARS);
+        assert_eq!(bars[0].start, Utc.timestamp_opt(500, 0).unwrap());
+    }
+}
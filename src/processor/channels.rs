@@ -1,51 +1,135 @@
-use crate::models::{MarketTick, fetch_market_data};
+use crate::ingester::simulator::{MarketSimulator, SymbolParams};
+use crate::models::{ExchangeCalendar, MarketEvent, MarketTick, Quote, TickSequencer};
+use chrono::{TimeDelta, Utc};
+use rand::random_range;
+use rust_decimal::Decimal;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+const PRODUCER_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Produces simulated trades, and optionally quotes, from a MarketSimulator, one step per interval
+/// Ticks are sequenced with the producer's symbol list as their source
 pub struct MarketDataProducer {
-    tx: mpsc::Sender<MarketTick>,
+    tx: mpsc::Sender<MarketEvent>,
     symbol: String,
+    simulator: MarketSimulator,
+    sequencer: TickSequencer,
+    quote_venue: Option<String>,
 }
 
 impl MarketDataProducer {
-    pub fn new(tx: mpsc::Sender<MarketTick>, symbol: String) -> Self {
-        MarketDataProducer { tx, symbol }
+    /// Random-seeded simulator for a single symbol, starting somewhere between $1 and $100
+    pub fn new(tx: mpsc::Sender<MarketEvent>, symbol: String) -> Self {
+        let starting_price = Decimal::new(random_range(100i64..10000i64), 2);
+        let simulator = MarketSimulator::new(rand::random())
+            .with_start_time(Utc::now())
+            .with_step(TimeDelta::from_std(PRODUCER_INTERVAL).unwrap_or(TimeDelta::zero()))
+            .with_symbol(&symbol, SymbolParams::new(starting_price));
+        MarketDataProducer {
+            tx,
+            sequencer: TickSequencer::new(symbol.clone()),
+            symbol,
+            simulator,
+            quote_venue: None,
+        }
+    }
+
+    /// Produce every symbol of a pre-configured (e.g. seeded) simulator
+    pub fn with_simulator(tx: mpsc::Sender<MarketEvent>, simulator: MarketSimulator) -> Self {
+        let symbol = simulator.symbols().collect::<Vec<_>>().join(",");
+        MarketDataProducer {
+            tx,
+            sequencer: TickSequencer::new(symbol.clone()),
+            symbol,
+            simulator,
+            quote_venue: None,
+        }
     }
 
-    pub async fn start_producing(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Also quote each symbol on `venue`, one tick either side of every simulated trade
+    /// At the one-tick price floor the bid stays at one tick and the ask moves out instead
+    pub fn with_quotes(mut self, venue: impl Into<String>) -> Self {
+        self.quote_venue = Some(venue.into());
+        self
+    }
+
+    /// Only produce in-session ticks; outside a session the producer sleeps until the next open
+    pub fn with_calendar(mut self, calendar: ExchangeCalendar) -> Self {
+        self.simulator = self.simulator.with_calendar(calendar);
+        self
+    }
+
+    /// Step the simulator once per interval, sending each step's events once its time arrives
+    /// The simulated clock is pulled up to wall time before every step, so time spent sending
+    /// never leaves live ticks stamped further and further in the past
+    pub async fn start_producing(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         loop {
-            match fetch_market_data(&self.symbol).await {
-                Ok(tick) => {
-                    if self.tx.send(tick).await.is_err() {
+            self.simulator.advance_to(Utc::now());
+            let ticks = self.simulator.step();
+            // The simulated clock is one step or more (e.g. the next open) ahead; never emit ticks from the future
+            if let Ok(wait) = (self.simulator.clock() - Utc::now()).to_std() {
+                tokio::time::sleep(wait).await;
+            }
+            for tick in ticks {
+                let tick = self.sequencer.stamp(tick);
+                let quote = self.quote_for(&tick);
+                let events =
+                    std::iter::once(MarketEvent::from(tick)).chain(quote.map(MarketEvent::from));
+                for event in events {
+                    if self.tx.send(event).await.is_err() {
                         println!("Consumser dropped, stopping producer for {}", self.symbol);
-                        break;
+                        return Ok(());
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error fetching market data for {}: {}", self.symbol, e);
-                }
             }
-            let _ = tokio::time::sleep(Duration::from_millis(50)).await;
         }
-        Ok(())
+    }
+
+    fn quote_for(&self, tick: &MarketTick) -> Option<Quote> {
+        let venue = self.quote_venue.as_ref()?;
+        let tick_size = self.simulator.tick_size(&tick.symbol)?;
+        let bid = (tick.price - tick_size).max(tick_size);
+        let mut quote = Quote::new(
+            tick.symbol.clone(),
+            bid,
+            bid + tick_size * Decimal::TWO,
+            tick.volume,
+            tick.volume,
+            venue.clone(),
+        );
+        quote.timestamp = tick.timestamp;
+        Some(quote)
     }
 }
 
 pub struct MarketDataConsumer {
-    rx: mpsc::Receiver<MarketTick>,
+    rx: mpsc::Receiver<MarketEvent>,
 }
 
 impl MarketDataConsumer {
-    pub fn new(rx: mpsc::Receiver<MarketTick>) -> Self {
+    pub fn new(rx: mpsc::Receiver<MarketEvent>) -> Self {
         MarketDataConsumer { rx }
     }
 
     pub async fn start_consuming(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         loop {
             match self.rx.recv().await {
-                Some(tick) => {
+                Some(MarketEvent::Trade(tick)) => {
                     println!("Tick: {}, {}, {}", tick.symbol, tick.price, tick.volume)
                 }
+                Some(MarketEvent::Quote(quote)) => {
+                    println!(
+                        "Quote: {}, {} x {}, {}",
+                        quote.symbol, quote.bid, quote.ask, quote.venue
+                    )
+                }
+                Some(MarketEvent::Book(update)) => {
+                    println!(
+                        "Book: {}, {:?} {:?} {} @ {}",
+                        update.symbol, update.side, update.action, update.size, update.price
+                    )
+                }
                 None => {
                     println!("No messages received. Terminating");
                     break;
@@ -55,3 +139,29 @@ impl MarketDataConsumer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_at_price_floor_keeps_a_positive_bid() {
+        let (tx, _rx) = mpsc::channel(1);
+        let simulator =
+            MarketSimulator::new(1).with_symbol("PNNY", SymbolParams::new(Decimal::ONE));
+        let producer = MarketDataProducer::with_simulator(tx, simulator).with_quotes("XNAS");
+        let tick_size = producer.simulator.tick_size("PNNY").unwrap();
+
+        let quote = producer
+            .quote_for(&MarketTick::new("PNNY".to_string(), Decimal::ONE, 10))
+            .unwrap();
+        assert_eq!(quote.bid, Decimal::ONE - tick_size);
+        assert_eq!(quote.ask, Decimal::ONE + tick_size);
+
+        let quote = producer
+            .quote_for(&MarketTick::new("PNNY".to_string(), tick_size, 10))
+            .unwrap();
+        assert_eq!(quote.bid, tick_size);
+        assert_eq!(quote.ask, tick_size * Decimal::from(3));
+    }
+}
@@ -0,0 +1,469 @@
+use crate::models::MarketTick;
+use crate::processor::hub::MarketUpdate;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{Notify, mpsc};
+use tokio::time::{Duration, timeout};
+
+/// Updates a subscriber can have queued before its delivery policy kicks in
+pub const SUBSCRIBER_CAPACITY: usize = 1000;
+// How long a final notice waits for room in a full `Blocking` queue before it is abandoned
+pub(crate) const LAST_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the hub does when a subscriber's queue is full
+/// Only ticks are ever dropped or conflated; gaps and snapshots are always queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryPolicy {
+    /// Wait for room; a slow subscriber holds up the whole hub
+    #[default]
+    Blocking,
+    /// Discard the incoming tick
+    DropNewest,
+    /// Discard the oldest queued tick to make room
+    DropOldest,
+    /// Queue at most one tick per symbol, replacing it with newer ones until it is received
+    /// A gap on the symbol keeps the queued tick ahead of it and starts a new one behind it
+    Conflate,
+}
+
+/// Updates a non-blocking subscriber never saw
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// Discarded by `DropNewest` or `DropOldest`
+    pub dropped: u64,
+    /// Replaced by a newer tick for the same symbol under `Conflate`
+    pub conflated: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriberClosed;
+
+impl fmt::Display for SubscriberClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "subscriber closed")
+    }
+}
+
+impl std::error::Error for SubscriberClosed {}
+
+enum Entry {
+    Update(MarketUpdate),
+    /// Placeholder for the conflated tick of this symbol
+    LatestTick(String),
+}
+
+#[derive(Default)]
+struct QueueState {
+    entries: VecDeque<Entry>,
+    latest: HashMap<String, MarketTick>,
+    stats: DeliveryStats,
+    received: u64,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+impl QueueState {
+    /// Fix the conflated tick for `symbol` in its place, so newer ticks queue behind it
+    fn settle_latest(&mut self, symbol: &str) {
+        let placeholder = self
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::LatestTick(queued) if queued == symbol));
+        if let Some(i) = placeholder
+            && let Some(tick) = self.latest.remove(symbol)
+        {
+            self.entries[i] = Entry::Update(MarketUpdate::Tick(tick));
+        }
+    }
+}
+
+/// Bounded queue shared by the hub and one non-blocking subscriber
+pub(crate) struct DeliveryQueue {
+    policy: DeliveryPolicy,
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+impl DeliveryQueue {
+    fn new(policy: DeliveryPolicy) -> Self {
+        DeliveryQueue {
+            policy,
+            state: Mutex::new(QueueState::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // Nothing panics while holding the lock, but don't take the hub down if it did
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, update: MarketUpdate) -> Result<(), SubscriberClosed> {
+        let mut state = self.lock();
+        if state.receiver_closed {
+            return Err(SubscriberClosed);
+        }
+        match (self.policy, update) {
+            (DeliveryPolicy::Conflate, MarketUpdate::Tick(tick)) => {
+                let symbol = tick.symbol.clone();
+                if state.latest.insert(symbol.clone(), tick).is_some() {
+                    state.stats.conflated += 1;
+                } else {
+                    state.entries.push_back(Entry::LatestTick(symbol));
+                }
+            }
+            (_, update @ MarketUpdate::Tick(_)) if state.entries.len() >= SUBSCRIBER_CAPACITY => {
+                state.stats.dropped += 1;
+                if self.policy == DeliveryPolicy::DropOldest {
+                    let oldest_tick = state
+                        .entries
+                        .iter()
+                        .position(|entry| matches!(entry, Entry::Update(MarketUpdate::Tick(_))));
+                    if let Some(i) = oldest_tick {
+                        state.entries.remove(i);
+                    }
+                    state.entries.push_back(Entry::Update(update));
+                }
+            }
+            // Gaps and snapshots are never dropped, even past capacity
+            (policy, update) => {
+                // A gap has to reach the subscriber before the tick that revealed it, so the
+                // symbol's conflated tick stops taking newer ones
+                if policy == DeliveryPolicy::Conflate
+                    && let MarketUpdate::Gap(gap) = &update
+                {
+                    state.settle_latest(&gap.symbol);
+                }
+                state.entries.push_back(Entry::Update(update));
+            }
+        }
+        drop(state);
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Queue a final notice regardless of the policy or capacity
+    fn push_last(&self, update: MarketUpdate) {
+        self.lock().entries.push_back(Entry::Update(update));
+        self.notify.notify_one();
+    }
+
+    fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Next update, or whether the hub has gone away if there is none
+    fn pop(&self) -> Result<MarketUpdate, bool> {
+        let mut state = self.lock();
+        let update = match state.entries.pop_front() {
+            Some(Entry::Update(update)) => Ok(update),
+            Some(Entry::LatestTick(symbol)) => state
+                .latest
+                .remove(&symbol)
+                .map(MarketUpdate::Tick)
+                .ok_or(false),
+            None => Err(state.sender_closed),
+        };
+        if update.is_ok() {
+            state.received += 1;
+        }
+        update
+    }
+}
+
+/// The hub's end of a subscription
+#[derive(Clone)]
+pub(crate) enum SubscriberTx {
+    /// Shares a count of received updates with the `SubscriberRx::Channel` end
+    Channel(mpsc::Sender<MarketUpdate>, Arc<AtomicU64>),
+    Queue(Arc<QueueSender>),
+}
+
+/// Marks the queue closed once the hub drops its last handle to it
+pub(crate) struct QueueSender(Arc<DeliveryQueue>);
+
+impl Drop for QueueSender {
+    fn drop(&mut self) {
+        self.0.lock().sender_closed = true;
+        self.0.notify.notify_one();
+    }
+}
+
+impl SubscriberTx {
+    /// Deliver per the subscription's policy; only `Blocking` ever waits
+    pub(crate) async fn send(&self, update: MarketUpdate) -> Result<(), SubscriberClosed> {
+        match self {
+            SubscriberTx::Channel(tx, _) => tx.send(update).await.map_err(|_| SubscriberClosed),
+            SubscriberTx::Queue(queue) => queue.0.push(update),
+        }
+    }
+
+    /// Updates queued and not yet received
+    pub(crate) fn depth(&self) -> usize {
+        match self {
+            SubscriberTx::Channel(tx, _) => tx.max_capacity() - tx.capacity(),
+            SubscriberTx::Queue(queue) => queue.0.len(),
+        }
+    }
+
+    /// Updates the subscriber has taken off its queue so far
+    pub(crate) fn received(&self) -> u64 {
+        match self {
+            SubscriberTx::Channel(_, received) => received.load(Ordering::Relaxed),
+            SubscriberTx::Queue(queue) => queue.0.lock().received,
+        }
+    }
+
+    /// Queue `update` behind everything already sent, without waiting for room
+    /// The subscription closes once the caller drops its remaining handles; a `Blocking`
+    /// subscriber that reads nothing for `LAST_SEND_TIMEOUT` is closed without the update
+    pub(crate) fn send_last(self, update: MarketUpdate) {
+        match self {
+            SubscriberTx::Channel(tx, _) => {
+                tokio::spawn(async move {
+                    let _ = timeout(LAST_SEND_TIMEOUT, tx.send(update)).await;
+                });
+            }
+            SubscriberTx::Queue(queue) => queue.0.push_last(update),
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        match self {
+            SubscriberTx::Channel(tx, _) => tx.is_closed(),
+            SubscriberTx::Queue(queue) => queue.0.lock().receiver_closed,
+        }
+    }
+}
+
+/// The client's end of a subscription
+pub(crate) enum SubscriberRx {
+    Channel(mpsc::Receiver<MarketUpdate>, Arc<AtomicU64>),
+    Queue(Arc<DeliveryQueue>),
+    /// Fed by a task relaying another subscriber end, whose queue holds the delivery stats
+    Forwarded(mpsc::Receiver<MarketUpdate>, Option<Arc<DeliveryQueue>>),
+}
+
+impl SubscriberRx {
+    /// A receiver for updates relayed from `source` by a forwarding task
+    pub(crate) fn forwarded(rx: mpsc::Receiver<MarketUpdate>, source: &SubscriberRx) -> Self {
+        let queue = match source {
+            SubscriberRx::Channel(..) => None,
+            SubscriberRx::Queue(queue) => Some(queue.clone()),
+            SubscriberRx::Forwarded(_, queue) => queue.clone(),
+        };
+        SubscriberRx::Forwarded(rx, queue)
+    }
+
+    pub(crate) async fn recv(&mut self) -> Option<MarketUpdate> {
+        match self {
+            SubscriberRx::Channel(rx, received) => {
+                let update = rx.recv().await;
+                if update.is_some() {
+                    received.fetch_add(1, Ordering::Relaxed);
+                }
+                update
+            }
+            SubscriberRx::Forwarded(rx, _) => rx.recv().await,
+            SubscriberRx::Queue(queue) => loop {
+                // A push between the check and the wait leaves a permit, so nothing is missed
+                match queue.pop() {
+                    Ok(update) => return Some(update),
+                    Err(true) => return None,
+                    Err(false) => queue.notify.notified().await,
+                }
+            },
+        }
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Result<MarketUpdate, mpsc::error::TryRecvError> {
+        match self {
+            SubscriberRx::Channel(rx, received) => {
+                let update = rx.try_recv()?;
+                received.fetch_add(1, Ordering::Relaxed);
+                Ok(update)
+            }
+            SubscriberRx::Forwarded(rx, _) => rx.try_recv(),
+            SubscriberRx::Queue(queue) => queue.pop().map_err(|closed| {
+                if closed {
+                    mpsc::error::TryRecvError::Disconnected
+                } else {
+                    mpsc::error::TryRecvError::Empty
+                }
+            }),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> DeliveryStats {
+        match self {
+            SubscriberRx::Channel(..) | SubscriberRx::Forwarded(_, None) => {
+                DeliveryStats::default()
+            }
+            SubscriberRx::Queue(queue) | SubscriberRx::Forwarded(_, Some(queue)) => {
+                queue.lock().stats
+            }
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        match self {
+            SubscriberRx::Channel(rx, _) => rx.close(),
+            SubscriberRx::Queue(queue) => queue.lock().receiver_closed = true,
+            SubscriberRx::Forwarded(rx, queue) => {
+                rx.close();
+                if let Some(queue) = queue {
+                    queue.lock().receiver_closed = true;
+                }
+            }
+        }
+    }
+}
+
+/// Both ends of a subscription delivered according to `policy`
+pub(crate) fn subscriber_channel(policy: DeliveryPolicy) -> (SubscriberTx, SubscriberRx) {
+    match policy {
+        DeliveryPolicy::Blocking => {
+            let (tx, rx) = mpsc::channel(SUBSCRIBER_CAPACITY);
+            let received = Arc::new(AtomicU64::new(0));
+            (
+                SubscriberTx::Channel(tx, received.clone()),
+                SubscriberRx::Channel(rx, received),
+            )
+        }
+        policy => {
+            let queue = Arc::new(DeliveryQueue::new(policy));
+            (
+                SubscriberTx::Queue(Arc::new(QueueSender(queue.clone()))),
+                SubscriberRx::Queue(queue),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::gaps::GapEvent;
+    use rust_decimal::Decimal;
+
+    fn tick(symbol: &str, volume: u64) -> MarketUpdate {
+        MarketUpdate::Tick(MarketTick::new(symbol.to_string(), Decimal::ONE, volume))
+    }
+
+    fn volume(update: Option<MarketUpdate>) -> u64 {
+        match update {
+            Some(MarketUpdate::Tick(tick)) => tick.volume,
+            other => panic!("expected a tick, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_policies() {
+        for (policy, first) in [
+            (DeliveryPolicy::DropNewest, 0),
+            (DeliveryPolicy::DropOldest, 5),
+        ] {
+            let (tx, mut rx) = subscriber_channel(policy);
+            for v in 0..SUBSCRIBER_CAPACITY as u64 + 5 {
+                tx.send(tick("AAPL", v)).await.unwrap();
+            }
+            assert_eq!(rx.stats().dropped, 5);
+            assert_eq!(volume(rx.recv().await), first);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_keeps_control_messages() {
+        for policy in [
+            DeliveryPolicy::DropNewest,
+            DeliveryPolicy::DropOldest,
+            DeliveryPolicy::Conflate,
+        ] {
+            let (tx, mut rx) = subscriber_channel(policy);
+            let gap = |expected| {
+                MarketUpdate::Gap(GapEvent {
+                    symbol: "AAPL".to_string(),
+                    source: "feed".to_string(),
+                    expected,
+                    received: expected + 1,
+                })
+            };
+            // A gap leads, then the queue is filled past capacity with ticks and gaps
+            tx.send(gap(0)).await.unwrap();
+            for v in 0..SUBSCRIBER_CAPACITY as u64 {
+                tx.send(tick(&format!("S{v}"), v)).await.unwrap();
+            }
+            tx.send(gap(1)).await.unwrap();
+            tx.send(tick("AAPL", 0)).await.unwrap();
+
+            let mut gaps = vec![];
+            while let Ok(update) = rx.try_recv() {
+                if let MarketUpdate::Gap(gap) = update {
+                    gaps.push(gap.expected);
+                }
+            }
+            assert_eq!(gaps, [0, 1], "{policy:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conflation_keeps_latest_per_symbol() {
+        let (tx, mut rx) = subscriber_channel(DeliveryPolicy::Conflate);
+        for (symbol, v) in [("AAPL", 1), ("MSFT", 2), ("AAPL", 3), ("AAPL", 4)] {
+            tx.send(tick(symbol, v)).await.unwrap();
+        }
+        assert_eq!(rx.stats().conflated, 2);
+        // AAPL keeps its place in the queue but carries its newest tick
+        assert_eq!(volume(rx.recv().await), 4);
+        assert_eq!(volume(rx.recv().await), 2);
+        tx.send(tick("AAPL", 5)).await.unwrap();
+        assert_eq!(volume(rx.recv().await), 5);
+
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+        let (tx, mut rx) = subscriber_channel(DeliveryPolicy::Conflate);
+        rx.close();
+        assert_eq!(tx.send(tick("AAPL", 6)).await, Err(SubscriberClosed));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_last_send_gives_up_on_full_channel() {
+        let (tx, mut rx) = subscriber_channel(DeliveryPolicy::Blocking);
+        for v in 0..SUBSCRIBER_CAPACITY as u64 {
+            tx.send(tick("AAPL", v)).await.unwrap();
+        }
+        tx.send_last(tick("AAPL", 0));
+        tokio::time::sleep(LAST_SEND_TIMEOUT * 2).await;
+
+        // The sender was dropped without ever finding room
+        for v in 0..SUBSCRIBER_CAPACITY as u64 {
+            assert_eq!(volume(rx.recv().await), v);
+        }
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_conflated_gap_precedes_its_tick() {
+        let (tx, mut rx) = subscriber_channel(DeliveryPolicy::Conflate);
+        tx.send(tick("AAPL", 1)).await.unwrap();
+        tx.send(tick("AAPL", 2)).await.unwrap();
+        tx.send(MarketUpdate::Gap(GapEvent {
+            symbol: "AAPL".to_string(),
+            source: "feed".to_string(),
+            expected: 3,
+            received: 4,
+        }))
+        .await
+        .unwrap();
+        tx.send(tick("AAPL", 4)).await.unwrap();
+        tx.send(tick("AAPL", 5)).await.unwrap();
+
+        // Ticks from before the gap stay ahead of it and later ones conflate behind it
+        assert_eq!(volume(rx.recv().await), 2);
+        assert!(matches!(rx.recv().await, Some(MarketUpdate::Gap(gap)) if gap.expected == 3));
+        assert_eq!(volume(rx.recv().await), 5);
+        assert_eq!(rx.stats().conflated, 2);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::models::{FIRST_SEQUENCE, MarketTick};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// How far a sequence number may fall behind the expected one before the source is taken
+/// to have restarted its numbering rather than resent old ticks
+pub const MAX_BACKWARD_JUMP: u64 = 10_000;
+
+/// Missing sequence numbers detected on one source's stream for a symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GapEvent {
+    pub symbol: String,
+    pub source: String,
+    /// The sequence number the hub was waiting for
+    pub expected: u64,
+    /// The sequence number that actually arrived
+    pub received: u64,
+}
+
+impl GapEvent {
+    pub fn missing(&self) -> u64 {
+        self.received - self.expected
+    }
+}
+
+/// Per-symbol totals across all sources
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GapStats {
+    pub gaps: u64,
+    pub missing_ticks: u64,
+    /// Ticks dropped because their sequence number was already seen
+    pub duplicates: u64,
+    /// Times a source restarted its numbering and was tracked afresh
+    pub restarts: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// Next in sequence, or not sequenced at all
+    InOrder,
+    /// Arrived after a gap; the tick itself is still valid
+    Gap(GapEvent),
+    /// Already seen or older than the latest; should be dropped
+    Duplicate,
+}
+
+/// Tracks the next expected sequence number per (source, symbol)
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    expected: HashMap<(String, String), u64>,
+    stats: HashMap<String, GapStats>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The first tick from a source sets its baseline, so joining a stream mid-way is not a gap
+    /// A source that jumps back to `FIRST_SEQUENCE`, or more than `MAX_BACKWARD_JUMP` behind,
+    /// has restarted (e.g. a producer process came back up), so that tick sets a new baseline
+    pub fn check(&mut self, tick: &MarketTick) -> SequenceCheck {
+        let (Some(source), Some(sequence)) = (&tick.source, tick.sequence) else {
+            return SequenceCheck::InOrder;
+        };
+        let expected = self
+            .expected
+            .entry((source.clone(), tick.symbol.clone()))
+            .or_insert(sequence);
+        let check = match sequence.cmp(expected) {
+            Ordering::Less
+                if *expected - sequence > MAX_BACKWARD_JUMP
+                    // Back to the start, and not merely tick 1 delivered twice
+                    || (sequence == FIRST_SEQUENCE && *expected > FIRST_SEQUENCE + 1) =>
+            {
+                println!(
+                    "{source} restarted {} at sequence {sequence} (expected {}), tracking it afresh",
+                    tick.symbol, *expected
+                );
+                self.stats.entry(tick.symbol.clone()).or_default().restarts += 1;
+                SequenceCheck::InOrder
+            }
+            Ordering::Less => {
+                self.stats
+                    .entry(tick.symbol.clone())
+                    .or_default()
+                    .duplicates += 1;
+                return SequenceCheck::Duplicate;
+            }
+            Ordering::Equal => SequenceCheck::InOrder,
+            Ordering::Greater => {
+                let gap = GapEvent {
+                    symbol: tick.symbol.clone(),
+                    source: source.clone(),
+                    expected: *expected,
+                    received: sequence,
+                };
+                let stats = self.stats.entry(tick.symbol.clone()).or_default();
+                stats.gaps += 1;
+                stats.missing_ticks += gap.missing();
+                SequenceCheck::Gap(gap)
+            }
+        };
+        *expected = sequence.saturating_add(1);
+        check
+    }
+
+    pub fn stats(&self, symbol: &str) -> GapStats {
+        self.stats.get(symbol).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn tick(source: &str, sequence: u64) -> MarketTick {
+        MarketTick::new("AAPL".to_string(), Decimal::ONE, 1).with_sequence(source, sequence)
+    }
+
+    #[test]
+    fn test_gaps_and_duplicates_per_source() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(&tick("a", 5)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("a", 6)), SequenceCheck::InOrder);
+        // Another source has its own numbering
+        assert_eq!(tracker.check(&tick("b", 1)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("a", 6)), SequenceCheck::Duplicate);
+        match tracker.check(&tick("a", 10)) {
+            SequenceCheck::Gap(gap) => {
+                assert_eq!((gap.expected, gap.received, gap.missing()), (7, 10, 3))
+            }
+            other => panic!("expected a gap, got {other:?}"),
+        }
+        assert_eq!(tracker.check(&tick("a", 8)), SequenceCheck::Duplicate);
+        assert_eq!(tracker.check(&tick("a", 11)), SequenceCheck::InOrder);
+        assert_eq!(
+            tracker.stats("AAPL"),
+            GapStats {
+                gaps: 1,
+                missing_ticks: 3,
+                duplicates: 2,
+                restarts: 0,
+            }
+        );
+        let unsequenced = MarketTick::new("AAPL".to_string(), Decimal::ONE, 1);
+        assert_eq!(tracker.check(&unsequenced), SequenceCheck::InOrder);
+    }
+
+    #[test]
+    fn test_restarted_source_is_tracked_afresh() {
+        let mut tracker = SequenceTracker::new();
+        assert_eq!(tracker.check(&tick("a", 1)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("a", 1)), SequenceCheck::Duplicate);
+        for sequence in 2..=50 {
+            tracker.check(&tick("a", sequence));
+        }
+        // The producer came back up and numbers from the start again
+        assert_eq!(tracker.check(&tick("a", 1)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("a", 2)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("a", 2)), SequenceCheck::Duplicate);
+
+        assert_eq!(tracker.check(&tick("b", 50_000)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&tick("b", 7)), SequenceCheck::InOrder);
+        assert_eq!(tracker.stats("AAPL").restarts, 2);
+
+        assert_eq!(tracker.check(&tick("c", u64::MAX)), SequenceCheck::InOrder);
+        assert_eq!(
+            tracker.check(&tick("c", u64::MAX - 1)),
+            SequenceCheck::Duplicate
+        );
+    }
+}
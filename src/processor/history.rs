@@ -0,0 +1,274 @@
+use crate::models::MarketTick;
+use crate::processor::delivery::{LAST_SEND_TIMEOUT, SubscriberRx};
+use crate::processor::gaps::{GapEvent, SequenceCheck, SequenceTracker};
+use crate::processor::hub::MarketUpdate;
+use crate::processor::slow_consumer::SlowConsumerReason;
+use crate::processor::subscription::{StartFrom, SubscriptionId, TickFilters};
+use crate::storage::recorder::{RecordingWatermark, recorded_ticks};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+/// The most recent ticks per symbol, kept so new subscribers can catch up
+#[derive(Debug)]
+pub struct TickHistory {
+    capacity: usize,
+    ticks: HashMap<String, VecDeque<MarketTick>>,
+}
+
+impl TickHistory {
+    pub fn new(capacity: usize) -> Self {
+        TickHistory {
+            capacity,
+            ticks: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, tick: &MarketTick) {
+        if self.capacity == 0 {
+            return;
+        }
+        let ticks = self.ticks.entry(tick.symbol.clone()).or_default();
+        if ticks.len() == self.capacity {
+            ticks.pop_front();
+        }
+        ticks.push_back(tick.clone());
+    }
+
+    /// Oldest tick still held for `symbol`
+    pub fn oldest(&self, symbol: &str) -> Option<&MarketTick> {
+        self.ticks.get(symbol)?.front()
+    }
+
+    /// Held ticks for `symbol` from the first one matching `start`, in arrival order
+    pub fn since(&self, symbol: &str, start: StartFrom) -> Vec<MarketTick> {
+        let Some(ticks) = self.ticks.get(symbol) else {
+            return Vec::new();
+        };
+        match ticks.iter().position(|tick| start.includes(tick)) {
+            Some(first) => ticks.range(first..).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Send `update` unless it is a tick the filters reject; false once the subscriber is gone
+async fn forward_filtered(
+    sender: &mpsc::Sender<MarketUpdate>,
+    filters: &mut TickFilters,
+    update: MarketUpdate,
+) -> bool {
+    if let MarketUpdate::Tick(tick) = &update
+        && !filters.accept(tick)
+    {
+        return true;
+    }
+    sender.send(update).await.is_ok()
+}
+
+/// A replaying subscription's forwarding state
+pub(crate) struct Replay {
+    pub(crate) id: SubscriptionId,
+    pub(crate) tx: mpsc::Sender<MarketUpdate>,
+    pub(crate) filters: TickFilters,
+    /// Drops ticks sent twice where recordings and buffer overlap and spots ticks in neither
+    pub(crate) sequences: SequenceTracker,
+    /// The sequence number the replay was asked to start from, until a sequenced tick is sent
+    pub(crate) first_expected: Option<u64>,
+}
+
+/// The part of a replay read back from a recordings directory
+pub(crate) struct RecordedRange {
+    pub(crate) dir: PathBuf,
+    pub(crate) symbol: String,
+    pub(crate) start: StartFrom,
+    pub(crate) from: DateTime<Utc>,
+    pub(crate) to: DateTime<Utc>,
+    /// Buffered ticks at `to`, which the recordings may hold too
+    pub(crate) seam: Vec<MarketTick>,
+    /// Ticks up to this timestamp must be flushed before the recordings are read
+    pub(crate) flushed_through: Option<(RecordingWatermark, DateTime<Utc>)>,
+}
+
+impl Replay {
+    /// Send recorded then buffered ticks, then relay live updates until either end goes away
+    pub(crate) async fn run(
+        mut self,
+        mut live: LiveLeg,
+        recorded: Option<RecordedRange>,
+        buffered: Vec<MarketTick>,
+    ) {
+        match self.catch_up(&mut live, recorded, buffered).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(reason) => {
+                println!("Evicting replaying subscriber {}: {reason}", self.id);
+                // The hub drops its end once it finds this one gone
+                drop(live);
+                let notice = self.tx.send(MarketUpdate::Evicted(reason));
+                let _ = timeout(LAST_SEND_TIMEOUT, notice).await;
+                return;
+            }
+        }
+        for update in live.staged.take().unwrap_or_default() {
+            if !forward_filtered(&self.tx, &mut self.filters, update).await {
+                return;
+            }
+        }
+        while let Some(update) = live.rx.recv().await {
+            if !forward_filtered(&self.tx, &mut self.filters, update).await {
+                return;
+            }
+        }
+    }
+
+    /// Send the recorded and buffered ticks; false once the subscriber is gone
+    async fn catch_up(
+        &mut self,
+        live: &mut LiveLeg,
+        recorded: Option<RecordedRange>,
+        buffered: Vec<MarketTick>,
+    ) -> Result<bool, SlowConsumerReason> {
+        if let Some(mut range) = recorded {
+            if let Some((mut watermark, newest)) = range.flushed_through
+                && !live
+                    .staging(watermark.wait_for(&range.symbol, newest))
+                    .await?
+            {
+                println!(
+                    "Recorder stopped before flushing {} ticks up to {newest}, replay may miss some",
+                    range.symbol
+                );
+            }
+            let mut ticks = recorded_ticks(&range.dir, &range.symbol, range.from, range.to);
+            while let Some(tick) = live.staging(ticks.recv()).await? {
+                let tick = match tick {
+                    Ok(tick) => tick,
+                    Err(e) => {
+                        println!("Error reading recordings for replay: {e}");
+                        break;
+                    }
+                };
+                if !range.start.includes(&tick) {
+                    continue;
+                }
+                if let Some(i) = range.seam.iter().position(|buffered| *buffered == tick) {
+                    range.seam.swap_remove(i);
+                    continue;
+                }
+                if !live.staging(self.send(tick)).await? {
+                    return Ok(false);
+                }
+            }
+        }
+        for tick in buffered {
+            if !live.staging(self.send(tick)).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Send a replayed tick, preceded by a `Gap` if ticks are missing; false once the
+    /// subscriber is gone
+    async fn send(&mut self, tick: MarketTick) -> bool {
+        let mut gap = match self.sequences.check(&tick) {
+            SequenceCheck::Duplicate => return true,
+            SequenceCheck::Gap(gap) => Some(gap),
+            SequenceCheck::InOrder => None,
+        };
+        if let Some(sequence) = tick.sequence
+            && let Some(expected) = self.first_expected.take()
+            && sequence > expected
+        {
+            gap = Some(GapEvent {
+                symbol: tick.symbol.clone(),
+                source: tick.source.clone().unwrap_or_default(),
+                expected,
+                received: sequence,
+            });
+        }
+        if let Some(gap) = gap
+            && !forward_filtered(&self.tx, &mut self.filters, MarketUpdate::Gap(gap)).await
+        {
+            return false;
+        }
+        forward_filtered(&self.tx, &mut self.filters, MarketUpdate::Tick(tick)).await
+    }
+}
+
+/// The live end of a replaying subscription, held while the replay is sent
+pub(crate) struct LiveLeg {
+    pub(crate) rx: SubscriberRx,
+    /// Updates taken off a `Blocking` live leg so the hub does not wait on the replay
+    pub(crate) staged: Option<VecDeque<MarketUpdate>>,
+    /// Most updates `staged` may hold
+    pub(crate) limit: usize,
+    /// False once the hub has dropped its end
+    pub(crate) open: bool,
+}
+
+impl LiveLeg {
+    /// Drive `step` of the replay to completion, staging live updates meanwhile if this leg
+    /// stages them; fails once more arrive than the staging limit allows
+    async fn staging<T>(&mut self, step: impl Future<Output = T>) -> Result<T, SlowConsumerReason> {
+        tokio::pin!(step);
+        loop {
+            let staging = self.open && self.staged.is_some();
+            tokio::select! {
+                output = &mut step => return Ok(output),
+                update = self.rx.recv(), if staging => match update {
+                    Some(update) => {
+                        let staged = self.staged.get_or_insert_default();
+                        if staged.len() >= self.limit {
+                            return Err(SlowConsumerReason::ReplayStaging { limit: self.limit });
+                        }
+                        staged.push_back(update);
+                    }
+                    None => self.open = false,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_bounded_and_queryable() {
+        let mut history = TickHistory::new(3);
+        for seq in 1..=5 {
+            let timestamp = DateTime::from_timestamp(1_700_000_000 + seq as i64, 0).unwrap();
+            let tick = MarketTick::with_timestamp("AAPL".to_string(), Decimal::ONE, 1, timestamp)
+                .with_sequence("feed", seq);
+            history.push(&tick);
+        }
+        assert_eq!(history.oldest("AAPL").unwrap().sequence, Some(3));
+        let sequences = |ticks: Vec<MarketTick>| {
+            ticks
+                .iter()
+                .map(|t| t.sequence.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sequences(history.since("AAPL", StartFrom::Sequence(4))),
+            [4, 5]
+        );
+        assert_eq!(
+            sequences(history.since("AAPL", StartFrom::Sequence(1))),
+            [3, 4, 5]
+        );
+        let t = DateTime::from_timestamp(1_700_000_005, 0).unwrap();
+        assert_eq!(
+            sequences(history.since("AAPL", StartFrom::Timestamp(t))),
+            [5]
+        );
+        assert!(history.since("MSFT", StartFrom::Sequence(1)).is_empty());
+    }
+}
@@ -1,24 +1,131 @@
-use crate::models::MarketTick;
-use crate::processor::aggregator::{PriceAggregator, PriceStats};
-use std::collections::HashMap;
+use crate::models::{BookUpdate, MarketEvent, MarketTick, Quote};
+use crate::processor::aggregator::{PriceAggregator, PriceStats, QuoteStats};
+use crate::processor::delivery::{DeliveryPolicy, SubscriberRx, SubscriberTx, subscriber_channel};
+use crate::processor::gaps::{GapEvent, SequenceCheck, SequenceTracker};
+use crate::processor::history::{LiveLeg, RecordedRange, Replay, TickHistory};
+use crate::processor::indicators::{IndicatorEngine, IndicatorUpdate};
+use crate::processor::order_book::{BookSnapshot, OrderBook};
+use crate::processor::slow_consumer::{
+    SlowConsumerMonitor, SlowConsumerPolicy, SlowConsumerReason,
+};
+use crate::processor::subscription::{
+    StartFrom, Subscription, SubscriptionId, SubscriptionRequest, SymbolPattern, TickFilters,
+};
+use crate::processor::window::{StatsWindow, WindowError, WindowStats};
+use crate::storage::recorder::RecordingWatermark;
+use crate::storage::snapshot::{Snapshot, SnapshotError, read_snapshot, write_snapshot};
+use crate::storage::wal::{FsyncPolicy, WalWriter, WriteAheadLog};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::path::PathBuf;
 use tokio::sync::{broadcast, mpsc, oneshot};
-use tokio::time::{Duration, timeout};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, Interval, interval, interval_at, timeout, timeout_at};
+
+const DEFAULT_HISTORY_CAPACITY: usize = 1000;
+// Updates a replaying subscription's forwarding task hands over ahead of the client
+const FORWARD_CAPACITY: usize = 64;
+const DEFAULT_REPLAY_STAGING: usize = 1000;
+
+/// How an order book subscriber wants to receive the book
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookSubscription {
+    /// A full snapshot on subscribe, then every price-level update as it is applied
+    Deltas,
+    /// The best `depth` levels per side, sent every `every`
+    Snapshots { depth: usize, every: Duration },
+}
+
+/// Messages delivered to order book subscribers
+#[derive(Debug, Clone)]
+pub enum BookMessage {
+    Delta(BookUpdate),
+    Snapshot(BookSnapshot),
+}
+
+/// Messages delivered to tick subscribers
+#[derive(Debug, Clone)]
+pub enum MarketUpdate {
+    Tick(MarketTick),
+    /// The symbol's last tick and current statistics, sent once on subscribe
+    /// Not a live update: the tick may be arbitrarily old
+    Snapshot {
+        tick: MarketTick,
+        // Boxed to keep the frequent Tick variant small
+        stats: Option<Box<PriceStats>>,
+    },
+    /// Sequence numbers were skipped on one of the symbol's sources
+    /// Sent just before the tick that revealed the gap
+    Gap(GapEvent),
+    /// The subscriber fell too far behind and was dropped; nothing follows this
+    Evicted(SlowConsumerReason),
+}
+
+#[derive(Clone)]
+struct TickSubscriber {
+    id: SubscriptionId,
+    tx: SubscriberTx,
+    // Each copy of a pattern subscriber only sees one symbol, so filter state stays per symbol
+    filters: TickFilters,
+}
+
+struct BookSubscriber {
+    tx: mpsc::Sender<BookMessage>,
+    mode: BookSubscription,
+    last_snapshot: Instant,
+}
 
 /// Commands that can be sent to the MarketDataHub
 /// This enum represents the command pattern - a way to encapsulate requests as objects
 #[derive(Debug)]
 pub enum MarketCommand {
-    /// Subscribe to a symbol and get a receiver for market ticks
+    /// Subscribe to a symbol and get a receiver for market ticks and gap notifications
+    /// The first message is a `MarketUpdate::Snapshot` if the symbol has traded before
     /// Uses oneshot channel to send back the receiver to the client
-    Subscribe(String, oneshot::Sender<mpsc::Receiver<MarketTick>>),
+    Subscribe(String, oneshot::Sender<Subscription>),
+
+    /// Subscribe with options, e.g. replaying recent history before live ticks
+    SubscribeWith(SubscriptionRequest, oneshot::Sender<Subscription>),
+
+    /// Subscribe to indicator updates for a symbol, published alongside each tick
+    SubscribeIndicators(String, oneshot::Sender<mpsc::Receiver<IndicatorUpdate>>),
+
+    /// Subscribe to bid/ask quotes for a symbol
+    SubscribeQuotes(String, oneshot::Sender<mpsc::Receiver<Quote>>),
+
+    /// Subscribe to a symbol's order book as a delta stream or periodic snapshots
+    SubscribeBook(
+        String,
+        BookSubscription,
+        oneshot::Sender<mpsc::Receiver<BookMessage>>,
+    ),
 
     /// Unsubscribe from a symbol (removes all subscribers for that symbol)
+    /// Pattern subscriptions are left in place and keep receiving it
     Unsubscribe(String),
 
+    /// Remove a single tick subscription; sent automatically when a `Subscription` is dropped
+    UnsubscribeById(SubscriptionId),
+
+    /// Request the ids of the tick subscriptions currently receiving a symbol
+    GetSubscriptions(String, oneshot::Sender<Vec<SubscriptionId>>),
+
     /// Request current statistics for all symbols
     /// Uses oneshot channel for request-response pattern
     GetStats(oneshot::Sender<Vec<PriceStats>>),
 
+    /// Request spread and mid-price statistics for all subscribed symbols
+    GetQuoteStats(oneshot::Sender<Vec<QuoteStats>>),
+
+    /// Request rolling statistics over a window for all subscribed symbols
+    /// A window the hub is not tracking yet starts being tracked, so it fills from that point on
+    /// Fails for windows that are too long or once too many windows are tracked
+    GetWindowStats(
+        StatsWindow,
+        oneshot::Sender<Result<Vec<WindowStats>, WindowError>>,
+    ),
+
     /// Signal graceful shutdown
     Shutdown,
 }
@@ -32,7 +139,26 @@ pub struct MarketDataHub {
 
     // Map of symbol -> list of subscribers (mpsc senders)
     // Each subscriber gets their own channel to receive market data
-    subscribers: HashMap<String, Vec<mpsc::Sender<MarketTick>>>,
+    subscribers: HashMap<String, Vec<TickSubscriber>>,
+    next_subscription_id: u64,
+
+    // Pattern subscribers are copied into `subscribers` for every matching symbol,
+    // including symbols first seen after they subscribed
+    pattern_subscribers: Vec<(SymbolPattern, TickSubscriber)>,
+    known_symbols: HashSet<String>,
+
+    // Next expected sequence number per source and symbol, for gap and duplicate detection
+    sequences: SequenceTracker,
+
+    // Recent ticks per symbol for replaying subscriptions, backed by on-disk recordings if set
+    history: TickHistory,
+    recordings: Option<PathBuf>,
+    recording_watermark: Option<RecordingWatermark>,
+    // Live updates a `Blocking` replaying subscription may hold back while its replay is sent
+    replay_staging: usize,
+
+    // Last tick per symbol, sent to new subscribers so they have something to show straight away
+    last_ticks: HashMap<String, MarketTick>,
 
     // Broadcast channel for coordinating shutdown across all components
     shutdown_tx: broadcast::Sender<()>,
@@ -41,13 +167,44 @@ pub struct MarketDataHub {
     // Aggregator for collecting statistics
     aggregator: PriceAggregator,
 
-    // Channel for receiving market data from producers
-    data_rx: mpsc::Receiver<MarketTick>,
+    // Per-symbol technical indicators, updated on every tick
+    indicators: IndicatorEngine,
+    indicator_subscribers: HashMap<String, Vec<mpsc::Sender<IndicatorUpdate>>>,
+
+    // Quote subscribers, kept apart from tick subscribers since they receive a different type
+    quote_subscribers: HashMap<String, Vec<mpsc::Sender<Quote>>>,
+
+    // Per-symbol limit order books built from MarketEvent::Book updates
+    books: HashMap<String, OrderBook>,
+    book_subscribers: HashMap<String, Vec<BookSubscriber>>,
+
+    // Channel for receiving trades, quotes and book updates from producers
+    data_rx: mpsc::Receiver<MarketEvent>,
+
+    // Receive every tick regardless of symbol, e.g. a TickRecorder
+    taps: Vec<mpsc::Sender<MarketTick>>,
+
+    // Where and how often to snapshot aggregator and indicator state
+    snapshots: Option<(PathBuf, Duration)>,
+    // The snapshot currently being serialized and written, off the actor
+    snapshot_task: Option<JoinHandle<()>>,
+
+    // Ticks are appended here before fan-out so a crash cannot lose them
+    // Opened by `with_wal` and handed to its own writer thread when the hub starts
+    wal: Option<WriteAheadLog>,
+    wal_writer: Option<WalWriter>,
+    // WAL sequence number of the last tick fanned out, recorded in snapshots
+    wal_fanned_out: u64,
+    // WAL sequence number the restored snapshot already includes
+    restored_wal_sequence: u64,
+
+    // Watches tick subscribers' queues, warning about and evicting those that fall behind
+    slow_consumers: SlowConsumerMonitor,
 }
 
 impl MarketDataHub {
     /// Create a new MarketDataHub
-    pub fn new(data_rx: mpsc::Receiver<MarketTick>) -> Self {
+    pub fn new(data_rx: mpsc::Receiver<MarketEvent>) -> Self {
         // Create command channel with buffer size of 100
         let (command_tx, command_rx) = mpsc::channel(100);
 
@@ -58,13 +215,111 @@ impl MarketDataHub {
             command_tx,
             command_rx,
             subscribers: HashMap::new(),
+            next_subscription_id: 1,
+            pattern_subscribers: Vec::new(),
+            known_symbols: HashSet::new(),
+            sequences: SequenceTracker::new(),
+            history: TickHistory::new(DEFAULT_HISTORY_CAPACITY),
+            recordings: None,
+            recording_watermark: None,
+            replay_staging: DEFAULT_REPLAY_STAGING,
+            last_ticks: HashMap::new(),
             shutdown_tx,
             shutdown_rx,
-            aggregator: PriceAggregator::new(),
+            aggregator: PriceAggregator::new()
+                .with_window(StatsWindow::Seconds(60))
+                .with_window(StatsWindow::Ticks(500)),
+            indicators: IndicatorEngine::default(),
+            indicator_subscribers: HashMap::new(),
+            quote_subscribers: HashMap::new(),
+            books: HashMap::new(),
+            book_subscribers: HashMap::new(),
             data_rx,
+            taps: Vec::new(),
+            snapshots: None,
+            snapshot_task: None,
+            wal: None,
+            wal_writer: None,
+            wal_fanned_out: 0,
+            restored_wal_sequence: 0,
+            slow_consumers: SlowConsumerMonitor::new(SlowConsumerPolicy::default()),
         }
     }
 
+    /// Copy every tick the hub processes to `tap`, before it is fanned out to subscribers
+    pub fn with_tap(mut self, tap: mpsc::Sender<MarketTick>) -> Self {
+        self.taps.push(tap);
+        self
+    }
+
+    /// Keep the last `capacity` ticks per symbol for replaying subscriptions
+    pub fn with_replay_buffer(mut self, capacity: usize) -> Self {
+        self.history = TickHistory::new(capacity);
+        self
+    }
+
+    /// Serve replays reaching back past the in-memory buffer from a `TickRecorder` directory
+    /// If a recorder is still writing there, also pass its watermark to
+    /// `with_recording_watermark`, or replays can miss ticks it has not flushed yet
+    pub fn with_recordings(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.recordings = Some(dir.into());
+        self
+    }
+
+    /// Have replays wait for the recorder behind `with_recordings` to flush every tick older
+    /// than the replay buffer before reading its files
+    pub fn with_recording_watermark(mut self, watermark: RecordingWatermark) -> Self {
+        self.recording_watermark = Some(watermark);
+        self
+    }
+
+    /// Live updates a `Blocking` replaying subscription may hold back while its replay is
+    /// sent, 1000 by default; a subscriber that needs more is evicted
+    pub fn with_replay_staging(mut self, limit: usize) -> Self {
+        self.replay_staging = limit;
+        self
+    }
+
+    /// Restore aggregator and indicator state from `path` if a snapshot exists, then
+    /// snapshot to it every `every` while running and once more on shutdown
+    pub fn with_snapshots(
+        mut self,
+        path: impl Into<PathBuf>,
+        every: Duration,
+    ) -> Result<Self, SnapshotError> {
+        if every.is_zero() {
+            return Err(SnapshotError::ZeroInterval);
+        }
+        let path = path.into();
+        if let Some(snapshot) = read_snapshot(&path)? {
+            println!(
+                "Restoring hub state from snapshot taken at {}",
+                snapshot.taken_at
+            );
+            self.aggregator.restore(snapshot.aggregator);
+            self.indicators = snapshot.indicators;
+            self.restored_wal_sequence = snapshot.wal_sequence;
+        }
+        self.snapshots = Some((path, every));
+        Ok(self)
+    }
+
+    /// Append every tick to a write-ahead log at `path` before fanning it out
+    /// Entries left unacknowledged by a crash are replayed when the hub starts, before anyone
+    /// can subscribe: they rebuild state and reach taps, and subscribers only see them through
+    /// the replay buffer (`SubscriptionRequest::from_sequence`) or the subscribe snapshot
+    pub fn with_wal(mut self, path: impl Into<PathBuf>, policy: FsyncPolicy) -> io::Result<Self> {
+        self.wal = Some(WriteAheadLog::open(path, policy)?);
+        Ok(self)
+    }
+
+    /// When to warn about and optionally evict tick subscribers that fall behind
+    /// By default the hub only warns
+    pub fn with_slow_consumer_policy(mut self, policy: SlowConsumerPolicy) -> Self {
+        self.slow_consumers = SlowConsumerMonitor::new(policy);
+        self
+    }
+
     /// Get a command sender for sending commands to this hub
     pub fn get_command_sender(&self) -> mpsc::Sender<MarketCommand> {
         self.command_tx.clone()
@@ -79,25 +334,84 @@ impl MarketDataHub {
         //   3. Shutdown signals from shutdown_rx
         // TODO: Call appropriate handler methods for each case
         // TODO: Break loop on shutdown and send shutdown signal to subscribers
+        // Drives periodic order book snapshots; each subscriber's own interval is checked on every tick
+        let mut book_timer = interval(Duration::from_millis(100));
+        let mut snapshot_timer = self
+            .snapshots
+            .as_ref()
+            .map(|(_, every)| interval_at(Instant::now() + *every, *every));
+        let pending = match self.wal.take() {
+            Some(mut wal) => {
+                if self.restored_wal_sequence > wal.last_sequence() {
+                    println!(
+                        "Snapshot is ahead of WAL {}, replaying all of its unacknowledged ticks",
+                        wal.path().display()
+                    );
+                    self.restored_wal_sequence = 0;
+                }
+                self.wal_fanned_out = wal.acknowledged();
+                let pending = wal.take_pending();
+                self.wal_writer = Some(wal.spawn_writer());
+                pending
+            }
+            None => Vec::new(),
+        };
+        let mut wal_sync_timer = match self.wal_writer.as_ref().map(|wal| wal.policy()) {
+            Some(FsyncPolicy::Interval(every)) => Some(interval(every)),
+            _ => None,
+        };
+        let mut slow_consumer_timer = interval(self.slow_consumers.policy().check_every);
+        self.replay_wal(pending).await;
+
         loop {
             tokio::select! {
                 // handle incoming data
-                Some(tick) = self.data_rx.recv() => {
-                    self.process_market_tick(tick).await;
+                Some(event) = self.data_rx.recv() => {
+                    self.process_market_event(event).await;
                 }
 
                 // handle commands
                 Some(command) = self.command_rx.recv() => {
                     match command {
                         MarketCommand::Subscribe(symbol, tx) => {
-                            self.handle_subscribe(symbol, tx).await;
+                            self.handle_subscribe(
+                                symbol,
+                                TickFilters::default(),
+                                DeliveryPolicy::Blocking,
+                                tx,
+                            )
+                            .await;
+                        }
+                        MarketCommand::SubscribeWith(request, tx) => {
+                            self.handle_subscribe_with(request, tx).await;
+                        }
+                        MarketCommand::SubscribeIndicators(symbol, tx) => {
+                            self.handle_subscribe_indicators(symbol, tx).await;
+                        }
+                        MarketCommand::SubscribeQuotes(symbol, tx) => {
+                            self.handle_subscribe_quotes(symbol, tx).await;
+                        }
+                        MarketCommand::SubscribeBook(symbol, mode, tx) => {
+                            self.handle_subscribe_book(symbol, mode, tx).await;
                         }
                         MarketCommand::Unsubscribe(symbol) => {
                             self.handle_unsubscribe(symbol).await;
                         }
+                        MarketCommand::UnsubscribeById(id) => {
+                            self.handle_unsubscribe_by_id(id);
+                        }
+                        MarketCommand::GetSubscriptions(symbol, tx) => {
+                            self.handle_get_subscriptions(&symbol, tx);
+                        }
                         MarketCommand::GetStats(tx) => {
                             self.handle_get_stats(tx).await;
                         }
+                        MarketCommand::GetQuoteStats(tx) => {
+                            self.handle_get_quote_stats(tx).await;
+                        }
+                        MarketCommand::GetWindowStats(window, tx) => {
+                            self.handle_get_window_stats(window, tx).await;
+                        }
                         MarketCommand::Shutdown => {
                             self.shutdown_tx.send(()).ok();
                             break;
@@ -105,52 +419,576 @@ impl MarketDataHub {
                     }
                 }
 
+                _ = book_timer.tick() => {
+                    self.publish_book_snapshots().await;
+                }
+
+                _ = tick_optional(&mut snapshot_timer) => {
+                    self.save_snapshot();
+                }
+
+                _ = tick_optional(&mut wal_sync_timer) => {
+                    if let Some(wal) = &self.wal_writer {
+                        wal.sync().await;
+                    }
+                }
+
+                _ = slow_consumer_timer.tick() => {
+                    self.check_slow_consumers();
+                }
+
                 _ = self.shutdown_rx.recv() => {
                     println!("Shutdown signal received!");
                     break;
                 }
             }
         }
+        if let Some(wal) = self.wal_writer.take() {
+            wal.close().await;
+        }
+        self.finish_snapshot().await;
+        self.save_snapshot();
+        self.finish_snapshot().await;
         Ok(())
     }
 
-    /// Process a market tick - add to aggregator and distribute to subscribers
+    /// Fan out ticks a previous run logged but never finished delivering
+    /// Runs before any client can subscribe, so replayed ticks rebuild statistics and
+    /// indicators, reach taps, and land in the replay buffer and last values. Ticks the
+    /// restored snapshot already counted skip statistics and indicators
+    async fn replay_wal(&mut self, pending: Vec<(u64, MarketTick)>) {
+        if !pending.is_empty() {
+            println!("Replaying {} unacknowledged ticks from WAL", pending.len());
+        }
+        for (seq, tick) in pending {
+            if seq <= self.restored_wal_sequence {
+                self.history.push(&tick);
+                self.last_ticks.insert(tick.symbol.clone(), tick.clone());
+                self.send_to_taps(&tick).await;
+            } else {
+                self.fan_out_tick(tick).await;
+            }
+            self.wal_fanned_out = seq;
+            self.acknowledge_wal(seq).await;
+        }
+    }
+
+    async fn acknowledge_wal(&self, seq: u64) {
+        if let Some(wal) = &self.wal_writer {
+            wal.acknowledge(seq).await;
+        }
+    }
+
+    /// Start writing aggregator and indicator state to the snapshot file, if snapshots are enabled
+    fn save_snapshot(&mut self) {
+        let Some((path, _)) = &self.snapshots else {
+            return;
+        };
+        if self
+            .snapshot_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            println!("Previous snapshot is still being written, skipping this one");
+            return;
+        }
+        // Only the copy happens on the actor; serializing and writing run on a blocking thread
+        let snapshot = Snapshot::new(self.aggregator.state(), self.indicators.clone())
+            .with_wal_sequence(self.wal_fanned_out);
+        let path = path.clone();
+        self.snapshot_task = Some(tokio::task::spawn_blocking(move || {
+            match snapshot.to_bytes() {
+                Ok(bytes) => {
+                    if let Err(e) = write_snapshot(&path, &bytes) {
+                        println!("Error writing snapshot: {e}");
+                    }
+                }
+                Err(e) => println!("Error serializing snapshot: {e}"),
+            }
+        }));
+    }
+
+    /// Wait for the snapshot being written, if any
+    async fn finish_snapshot(&mut self) {
+        if let Some(task) = self.snapshot_task.take()
+            && let Err(e) = task.await
+        {
+            println!("Snapshot task failed: {e}");
+        }
+    }
+
+    /// Process a MarketEvent - trades take the normal tick path, quotes go to quote subscribers
+    async fn process_market_event(&mut self, event: MarketEvent) {
+        match event {
+            MarketEvent::Trade(tick) => self.process_market_tick(tick).await,
+            MarketEvent::Quote(quote) => self.process_quote(quote).await,
+            MarketEvent::Book(update) => self.process_book_update(update).await,
+        }
+    }
+
+    /// Apply a price-level update to the symbol's book and stream it to delta subscribers
+    async fn process_book_update(&mut self, update: BookUpdate) {
+        let book = self
+            .books
+            .entry(update.symbol.clone())
+            .or_insert_with(|| OrderBook::new(update.symbol.clone()));
+        if let Err(e) = book.apply(&update) {
+            println!("Rejected book update for {}: {e}", update.symbol);
+            return;
+        }
+
+        if let Some(subscribers) = self.book_subscribers.get_mut(&update.symbol) {
+            let mut failed_channels = vec![];
+            for (idx, subscriber) in subscribers.iter().enumerate() {
+                if subscriber.mode != BookSubscription::Deltas {
+                    continue;
+                }
+                if let Err(e) = subscriber.tx.send(BookMessage::Delta(update.clone())).await {
+                    println!("Error sending book update to subscriber: {e}");
+                    failed_channels.push(idx);
+                }
+            }
+            for idx in failed_channels.iter().rev() {
+                subscribers.remove(*idx);
+            }
+        }
+    }
+
+    /// Send snapshots to every snapshot subscriber whose interval has elapsed
+    async fn publish_book_snapshots(&mut self) {
+        let now = Instant::now();
+        for (symbol, subscribers) in self.book_subscribers.iter_mut() {
+            let Some(book) = self.books.get(symbol) else {
+                continue;
+            };
+            let mut failed_channels = vec![];
+            for (idx, subscriber) in subscribers.iter_mut().enumerate() {
+                let BookSubscription::Snapshots { depth, every } = subscriber.mode else {
+                    continue;
+                };
+                if now.duration_since(subscriber.last_snapshot) < every {
+                    continue;
+                }
+                subscriber.last_snapshot = now;
+                if let Err(e) = subscriber
+                    .tx
+                    .send(BookMessage::Snapshot(book.depth(depth)))
+                    .await
+                {
+                    println!("Error sending book snapshot to subscriber: {e}");
+                    failed_channels.push(idx);
+                }
+            }
+            for idx in failed_channels.iter().rev() {
+                subscribers.remove(*idx);
+            }
+        }
+    }
+
+    /// Process a quote - add to aggregator and distribute to quote subscribers
+    async fn process_quote(&mut self, quote: Quote) {
+        self.aggregator.add_quote(&quote);
+        if let Some(subscribers) = self.quote_subscribers.get_mut(&quote.symbol) {
+            let mut failed_channels = vec![];
+            for (idx, subscriber) in subscribers.iter().enumerate() {
+                if let Err(e) = subscriber.send(quote.clone()).await {
+                    println!("Error sending quote to subscriber: {e}");
+                    failed_channels.push(idx);
+                }
+            }
+            for idx in failed_channels.iter().rev() {
+                subscribers.remove(*idx);
+            }
+        }
+    }
+
+    /// Process a market tick - log it to the WAL if enabled, then distribute it
     async fn process_market_tick(&mut self, tick: MarketTick) {
         // TODO: Add tick to aggregator for statistics
         // TODO: Find subscribers for this symbol
         // TODO: Send tick to all subscribers, removing closed channels
         // TODO: Handle full channels gracefully (log warning, don't block)
+        match self.sequences.check(&tick) {
+            SequenceCheck::InOrder => {}
+            SequenceCheck::Gap(gap) => {
+                println!(
+                    "Gap on {} from {}: {} ticks missing",
+                    gap.symbol,
+                    gap.source,
+                    gap.missing()
+                );
+                let symbol = gap.symbol.clone();
+                self.send_to_subscribers(&symbol, MarketUpdate::Gap(gap))
+                    .await;
+            }
+            SequenceCheck::Duplicate => return,
+        }
+
+        // Wait for the writer thread: the tick must be logged before anyone sees it
+        let seq = match &self.wal_writer {
+            Some(wal) => match wal.append(tick.clone()).await {
+                Ok(seq) => Some(seq),
+                Err(e) => {
+                    println!("Error appending tick to WAL, delivering without it: {e}");
+                    None
+                }
+            },
+            None => None,
+        };
+        self.fan_out_tick(tick).await;
+        if let Some(seq) = seq {
+            self.wal_fanned_out = seq;
+            self.acknowledge_wal(seq).await;
+        }
+    }
+
+    /// Update statistics and indicators and deliver the tick to taps and subscribers
+    async fn fan_out_tick(&mut self, tick: MarketTick) {
         self.aggregator.add_tick(tick.clone());
-        let mut failed_channels = vec![];
-        if let Some(subscribers) = self.subscribers.get_mut(&tick.symbol) {
+        self.history.push(&tick);
+        self.last_ticks.insert(tick.symbol.clone(), tick.clone());
+        self.send_to_taps(&tick).await;
+
+        // Indicators only run for symbols someone listens to, or whose state was restored
+        let has_indicator_subscribers = self.indicator_subscribers.contains_key(&tick.symbol);
+        let update = if has_indicator_subscribers {
+            Some(self.indicators.on_tick(&tick))
+        } else {
+            if self.indicators.is_tracking(&tick.symbol) {
+                self.indicators.advance_tick(&tick);
+            }
+            None
+        };
+        self.send_to_subscribers(&tick.symbol, MarketUpdate::Tick(tick.clone()))
+            .await;
+
+        if let Some(update) = update
+            && let Some(subscribers) = self.indicator_subscribers.get_mut(&tick.symbol)
+        {
+            let mut failed_channels = vec![];
             for (idx, subscriber) in subscribers.iter().enumerate() {
-                match subscriber.send(tick.clone()).await {
-                    Ok(()) => true,
-                    Err(e) => {
+                if let Err(e) = subscriber.send(update.clone()).await {
+                    println!("Error sending indicator update to subscriber: {e}");
+                    failed_channels.push(idx);
+                }
+            }
+            for idx in failed_channels.iter().rev() {
+                subscribers.remove(*idx);
+            }
+            if subscribers.is_empty() {
+                self.indicator_subscribers.remove(&tick.symbol);
+                self.indicators.forget(&tick.symbol);
+            }
+        }
+    }
+
+    /// Copy `tick` to every tap, dropping the ones that have closed
+    async fn send_to_taps(&mut self, tick: &MarketTick) {
+        let mut closed_taps = vec![];
+        for (idx, tap) in self.taps.iter().enumerate() {
+            if tap.send(tick.clone()).await.is_err() {
+                println!("Tap closed, no longer copying ticks to it");
+                closed_taps.push(idx);
+            }
+        }
+        for idx in closed_taps.iter().rev() {
+            self.taps.remove(*idx);
+        }
+    }
+
+    /// Send `update` to every subscriber of `symbol`, removing closed channels
+    /// With eviction on, a full `Blocking` subscriber is only waited on until its grace runs out
+    async fn send_to_subscribers(&mut self, symbol: &str, update: MarketUpdate) {
+        if !self.known_symbols.contains(symbol) {
+            self.route_new_symbol(symbol);
+        }
+        let mut failed_channels = vec![];
+        let mut blocked = vec![];
+        if let Some(subscribers) = self.subscribers.get_mut(symbol) {
+            for (idx, subscriber) in subscribers.iter_mut().enumerate() {
+                if let MarketUpdate::Tick(tick) = &update
+                    && !subscriber.filters.accept(tick)
+                {
+                    continue;
+                }
+                let started = Instant::now();
+                let sent = match self.slow_consumers.send_deadline(subscriber.id, started) {
+                    Some(deadline) => {
+                        timeout_at(deadline, subscriber.tx.send(update.clone())).await
+                    }
+                    None => Ok(subscriber.tx.send(update.clone()).await),
+                };
+                match sent {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
                         println!("Error sending message to subscriber: {e}");
                         failed_channels.push(idx);
-                        false
                     }
-                };
+                    Err(_) => {
+                        let waited = started.elapsed();
+                        blocked.push((subscriber.id, subscriber.tx.clone(), waited));
+                    }
+                }
             }
             for idx in failed_channels.iter().rev() {
                 subscribers.remove(*idx);
             }
         }
+        for (id, tx, waited) in blocked {
+            self.evict(id, tx, SlowConsumerReason::Blocked { waited });
+        }
     }
 
     /// Handle subscription request - create new channel and add to subscribers
     async fn handle_subscribe(
         &mut self,
         symbol: String,
-        response_tx: oneshot::Sender<mpsc::Receiver<MarketTick>>,
+        filters: TickFilters,
+        policy: DeliveryPolicy,
+        response_tx: oneshot::Sender<Subscription>,
     ) {
         // TODO: Create new mpsc channel for this subscriber
         // TODO: Add sender to subscribers map for the symbol
         // TODO: Send receiver back to client via oneshot channel
         // TODO: Handle case where client dropped the oneshot receiver
-        let (sender, receiver) = mpsc::channel::<MarketTick>(1000);
-        self.subscribers.entry(symbol).or_default().push(sender);
+        let (sender, receiver) = subscriber_channel(policy);
+        if let Some(tick) = self.last_ticks.get(&symbol) {
+            let snapshot = MarketUpdate::Snapshot {
+                tick: tick.clone(),
+                stats: self.price_stats(&symbol).map(Box::new),
+            };
+            if sender.send(snapshot).await.is_err() {
+                println!("Error sending last value to subscriber");
+            }
+        }
+        let id = self.add_subscriber(symbol, sender, filters);
+        self.respond_with_subscription(id, receiver, response_tx);
+    }
+
+    /// Add matching pattern subscribers to a symbol seen for the first time
+    fn route_new_symbol(&mut self, symbol: &str) {
+        self.known_symbols.insert(symbol.to_string());
+        self.pattern_subscribers
+            .retain(|(_, subscriber)| !subscriber.tx.is_closed());
+        for (pattern, subscriber) in &self.pattern_subscribers {
+            if pattern.matches(symbol) {
+                self.subscribers
+                    .entry(symbol.to_string())
+                    .or_default()
+                    .push(subscriber.clone());
+            }
+        }
+    }
+
+    fn next_subscription_id(&mut self) -> SubscriptionId {
+        let id = SubscriptionId(self.next_subscription_id);
+        self.next_subscription_id += 1;
+        id
+    }
+
+    fn add_subscriber(
+        &mut self,
+        symbol: String,
+        tx: SubscriberTx,
+        filters: TickFilters,
+    ) -> SubscriptionId {
+        let id = self.next_subscription_id();
+        self.subscribers
+            .entry(symbol)
+            .or_default()
+            .push(TickSubscriber { id, tx, filters });
+        id
+    }
+
+    fn respond_with_subscription(
+        &self,
+        id: SubscriptionId,
+        receiver: SubscriberRx,
+        response_tx: oneshot::Sender<Subscription>,
+    ) {
+        let subscription = Subscription::new(id, receiver, self.command_tx.clone());
+        if response_tx.send(subscription).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
+    /// Handle subscription with options - replayed history is followed by live ticks
+    /// The live leg is registered in the same step the buffer is read and queues under the
+    /// subscription's delivery policy while the replay runs; a `Blocking` live leg is drained
+    /// into a staging buffer instead, and the subscriber is evicted if that outgrows
+    /// `with_replay_staging`. Recordings are read up to and including the buffer's oldest
+    /// timestamp, once the recording watermark shows they hold it; ticks seen in both are sent
+    /// once, and a sequenced tick missing from both is reported as a `Gap`
+    async fn handle_subscribe_with(
+        &mut self,
+        request: SubscriptionRequest,
+        response_tx: oneshot::Sender<Subscription>,
+    ) {
+        let filters = TickFilters::new(request.filters);
+        let symbol = match request.symbols {
+            SymbolPattern::Exact(symbol) => symbol,
+            pattern => {
+                if request.start.is_some() {
+                    println!("Replay needs an exact symbol, subscribing to {pattern:?} live only");
+                }
+                self.handle_subscribe_pattern(pattern, filters, request.delivery, response_tx);
+                return;
+            }
+        };
+        let Some(start) = request.start else {
+            self.handle_subscribe(symbol, filters, request.delivery, response_tx)
+                .await;
+            return;
+        };
+        let (live_tx, live_rx) = subscriber_channel(request.delivery);
+        let (client_tx, client_rx) = mpsc::channel(FORWARD_CAPACITY);
+        let receiver = SubscriberRx::forwarded(client_rx, &live_rx);
+        let live = LiveLeg {
+            rx: live_rx,
+            staged: (request.delivery == DeliveryPolicy::Blocking).then(VecDeque::new),
+            limit: self.replay_staging,
+            open: true,
+        };
+        // Filters run in the forwarding task so replayed and live ticks share their state
+        let id = self.add_subscriber(symbol.clone(), live_tx, TickFilters::default());
+
+        let buffered = self.history.since(&symbol, start);
+        let oldest = self.history.oldest(&symbol);
+        let reaches_disk = match (start, oldest) {
+            (_, None) => true,
+            (StartFrom::Timestamp(t), Some(oldest)) => t <= oldest.timestamp,
+            (StartFrom::Sequence(n), Some(oldest)) => oldest.sequence.is_some_and(|seq| seq > n),
+        };
+        let mut recorded = None;
+        if let Some(dir) = &self.recordings
+            && reaches_disk
+        {
+            let from = match start {
+                StartFrom::Timestamp(t) => t,
+                StartFrom::Sequence(_) => DateTime::<Utc>::MIN_UTC,
+            };
+            let to = oldest.map_or_else(Utc::now, |tick| tick.timestamp);
+            // Every tick the hub has sent the recorder up to the buffer's oldest must be on disk
+            let newest_needed = oldest
+                .or_else(|| self.last_ticks.get(&symbol))
+                .map(|tick| tick.timestamp);
+            recorded = Some(RecordedRange {
+                dir: dir.clone(),
+                symbol: symbol.clone(),
+                start,
+                from,
+                to,
+                seam: self
+                    .history
+                    .since(&symbol, StartFrom::Timestamp(to))
+                    .into_iter()
+                    .filter(|tick| tick.timestamp == to)
+                    .collect(),
+                flushed_through: self.recording_watermark.clone().zip(newest_needed),
+            });
+        }
+
+        let replay = Replay {
+            id,
+            tx: client_tx,
+            filters,
+            sequences: SequenceTracker::new(),
+            first_expected: match start {
+                StartFrom::Sequence(n) => Some(n),
+                StartFrom::Timestamp(_) => None,
+            },
+        };
+        tokio::spawn(replay.run(live, recorded, buffered));
+
+        self.respond_with_subscription(id, receiver, response_tx);
+    }
+
+    /// Handle pattern subscription - routed to every known matching symbol now and new ones later
+    fn handle_subscribe_pattern(
+        &mut self,
+        pattern: SymbolPattern,
+        filters: TickFilters,
+        policy: DeliveryPolicy,
+        response_tx: oneshot::Sender<Subscription>,
+    ) {
+        let (sender, receiver) = subscriber_channel(policy);
+        let subscriber = TickSubscriber {
+            id: self.next_subscription_id(),
+            tx: sender,
+            filters,
+        };
+        for symbol in &self.known_symbols {
+            if pattern.matches(symbol) {
+                self.subscribers
+                    .entry(symbol.clone())
+                    .or_default()
+                    .push(subscriber.clone());
+            }
+        }
+        let id = subscriber.id;
+        self.pattern_subscribers.push((pattern, subscriber));
+        self.respond_with_subscription(id, receiver, response_tx);
+    }
+
+    /// Handle indicator subscription - same flow as handle_subscribe, on the indicator stream
+    async fn handle_subscribe_indicators(
+        &mut self,
+        symbol: String,
+        response_tx: oneshot::Sender<mpsc::Receiver<IndicatorUpdate>>,
+    ) {
+        let (sender, receiver) = mpsc::channel::<IndicatorUpdate>(1000);
+        self.indicator_subscribers
+            .entry(symbol)
+            .or_default()
+            .push(sender);
+        if response_tx.send(receiver).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
+    /// Handle quote subscription - same flow as handle_subscribe, on the quote stream
+    async fn handle_subscribe_quotes(
+        &mut self,
+        symbol: String,
+        response_tx: oneshot::Sender<mpsc::Receiver<Quote>>,
+    ) {
+        let (sender, receiver) = mpsc::channel::<Quote>(1000);
+        self.quote_subscribers
+            .entry(symbol)
+            .or_default()
+            .push(sender);
+        if response_tx.send(receiver).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
+    /// Handle order book subscription - snapshot first so delta subscribers start from known state
+    async fn handle_subscribe_book(
+        &mut self,
+        symbol: String,
+        mode: BookSubscription,
+        response_tx: oneshot::Sender<mpsc::Receiver<BookMessage>>,
+    ) {
+        let (sender, receiver) = mpsc::channel::<BookMessage>(1000);
+        let snapshot = match mode {
+            BookSubscription::Deltas => self.books.get(&symbol).map(|book| book.snapshot()),
+            BookSubscription::Snapshots { depth, .. } => {
+                self.books.get(&symbol).map(|book| book.depth(depth))
+            }
+        };
+        if let Some(snapshot) = snapshot
+            && sender.send(BookMessage::Snapshot(snapshot)).await.is_err()
+        {
+            println!("Error sending initial book snapshot");
+        }
+        self.book_subscribers
+            .entry(symbol)
+            .or_default()
+            .push(BookSubscriber {
+                tx: sender,
+                mode,
+                last_snapshot: Instant::now(),
+            });
         if response_tx.send(receiver).is_err() {
             println!("Error sending message to response oneshot channel");
         }
@@ -160,14 +998,71 @@ impl MarketDataHub {
     async fn handle_unsubscribe(&mut self, symbol: String) {
         // TODO: Remove all subscribers for the symbol
         // TODO: Log the unsubscription
-        match self.subscribers.remove(&symbol) {
-            Some(_) => {
-                println!("Unsubscribe processed for {symbol}!");
-            }
-            None => {
-                println!("Unsubscribe failed, {symbol} has no subscribers!");
-            }
+        if self.indicator_subscribers.remove(&symbol).is_some() {
+            self.indicators.forget(&symbol);
+        }
+        self.quote_subscribers.remove(&symbol);
+        self.book_subscribers.remove(&symbol);
+        let patterned: HashSet<SubscriptionId> = self
+            .pattern_subscribers
+            .iter()
+            .map(|(_, subscriber)| subscriber.id)
+            .collect();
+        let Some(subscribers) = self.subscribers.get_mut(&symbol) else {
+            println!("Unsubscribe failed, {symbol} has no subscribers!");
+            return;
         };
+        subscribers.retain(|subscriber| patterned.contains(&subscriber.id));
+        if subscribers.is_empty() {
+            self.subscribers.remove(&symbol);
+        }
+        println!("Unsubscribe processed for {symbol}!");
+    }
+
+    /// Handle unsubscription of a single subscriber - everyone else on the symbol keeps receiving
+    fn handle_unsubscribe_by_id(&mut self, id: SubscriptionId) {
+        self.pattern_subscribers
+            .retain(|(_, subscriber)| subscriber.id != id);
+        self.subscribers.retain(|_, subscribers| {
+            subscribers.retain(|subscriber| subscriber.id != id);
+            !subscribers.is_empty()
+        });
+    }
+
+    /// Sample every tick subscriber's queue and evict those behind for too long
+    /// Evicted subscribers get the reason after whatever is already queued for them
+    fn check_slow_consumers(&mut self) {
+        let mut queues: HashMap<SubscriptionId, &SubscriberTx> = HashMap::new();
+        let pattern_subscribers = self.pattern_subscribers.iter().map(|(_, s)| s);
+        for subscriber in self
+            .subscribers
+            .values()
+            .flatten()
+            .chain(pattern_subscribers)
+        {
+            if !subscriber.tx.is_closed() {
+                queues.entry(subscriber.id).or_insert(&subscriber.tx);
+            }
+        }
+        let samples = queues
+            .iter()
+            .map(|(id, tx)| (*id, tx.depth(), tx.received()));
+        let evictions: Vec<(SubscriptionId, SubscriberTx, SlowConsumerReason)> = self
+            .slow_consumers
+            .check(Instant::now(), samples)
+            .into_iter()
+            .map(|(id, reason)| (id, queues[&id].clone(), reason))
+            .collect();
+        for (id, tx, reason) in evictions {
+            self.evict(id, tx, reason);
+        }
+    }
+
+    /// Drop subscriber `id` everywhere and tell it why after whatever it already has queued
+    fn evict(&mut self, id: SubscriptionId, tx: SubscriberTx, reason: SlowConsumerReason) {
+        println!("Evicting subscriber {id}: {reason}");
+        self.handle_unsubscribe_by_id(id);
+        tx.send_last(MarketUpdate::Evicted(reason));
     }
 
     /// Handle statistics request - collect stats and send via oneshot
@@ -178,7 +1073,52 @@ impl MarketDataHub {
         let stats: Vec<PriceStats> = self
             .subscribers
             .keys()
-            .filter_map(|symbol| self.aggregator.get_statistics(symbol))
+            .filter_map(|symbol| self.price_stats(symbol))
+            .collect();
+
+        if response_tx.send(stats).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
+    /// Aggregator statistics for `symbol` with the hub's gap counts filled in
+    fn price_stats(&self, symbol: &str) -> Option<PriceStats> {
+        let stats = self.aggregator.get_statistics(symbol)?;
+        Some(PriceStats {
+            gaps: self.sequences.stats(symbol),
+            ..stats
+        })
+    }
+
+    /// Handle subscription listing request - pattern subscriptions count once they match
+    fn handle_get_subscriptions(
+        &self,
+        symbol: &str,
+        response_tx: oneshot::Sender<Vec<SubscriptionId>>,
+    ) {
+        let ids = self
+            .subscribers
+            .get(symbol)
+            .map(|subscribers| subscribers.iter().map(|s| s.id).collect())
+            .unwrap_or_default();
+        if response_tx.send(ids).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
+    /// Handle quote statistics request - symbols with tick or quote subscribers
+    async fn handle_get_quote_stats(&self, response_tx: oneshot::Sender<Vec<QuoteStats>>) {
+        let mut symbols: Vec<&String> = self
+            .subscribers
+            .keys()
+            .chain(self.quote_subscribers.keys())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+
+        let stats: Vec<QuoteStats> = symbols
+            .into_iter()
+            .filter_map(|symbol| self.aggregator.get_quote_statistics(symbol))
             .collect();
 
         if response_tx.send(stats).is_err() {
@@ -186,17 +1126,49 @@ impl MarketDataHub {
         }
     }
 
+    /// Handle windowed statistics request - same symbols as GetStats, over a rolling window
+    async fn handle_get_window_stats(
+        &mut self,
+        window: StatsWindow,
+        response_tx: oneshot::Sender<Result<Vec<WindowStats>, WindowError>>,
+    ) {
+        if !self.aggregator.is_tracking_window(window) {
+            if let Err(e) = self.aggregator.track_window(window) {
+                println!("Refusing to track window {window:?}: {e}");
+                if response_tx.send(Err(e)).is_err() {
+                    println!("Error sending message to response oneshot channel");
+                }
+                return;
+            }
+            println!("Now tracking window {window:?}");
+        }
+        // Expire against market time rather than the wall clock so replayed data still has windows
+        if let Some(now) = self.aggregator.latest_timestamp() {
+            self.aggregator.expire_windows(now);
+        }
+
+        let stats: Vec<WindowStats> = self
+            .subscribers
+            .keys()
+            .filter_map(|symbol| self.aggregator.get_window_statistics(symbol, window))
+            .collect();
+
+        if response_tx.send(Ok(stats)).is_err() {
+            println!("Error sending message to response oneshot channel");
+        }
+    }
+
     /// Client API: Subscribe to a symbol (returns receiver for market ticks)
     /// This is the public API that clients use - it sends a command and waits for response
     pub async fn subscribe_to_symbol(
         &self,
         symbol: String,
-    ) -> Result<mpsc::Receiver<MarketTick>, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Subscription, Box<dyn std::error::Error + Send + Sync>> {
         // TODO: Create oneshot channel for response
         // TODO: Send Subscribe command to hub
         // TODO: Wait for response with timeout (use tokio::time::timeout)
         // TODO: Return the receiver or error
-        let (oneshot_sender, oneshot_recv) = oneshot::channel::<mpsc::Receiver<MarketTick>>();
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<Subscription>();
         self.command_tx
             .send(MarketCommand::Subscribe(symbol, oneshot_sender))
             .await?;
@@ -204,6 +1176,71 @@ impl MarketDataHub {
         Ok(receiver)
     }
 
+    /// Client API: Subscribe with options such as a replay starting point
+    pub async fn subscribe(
+        &self,
+        request: SubscriptionRequest,
+    ) -> Result<Subscription, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<Subscription>();
+        self.command_tx
+            .send(MarketCommand::SubscribeWith(request, oneshot_sender))
+            .await?;
+        let receiver = timeout(Duration::from_secs(5), oneshot_recv).await??;
+        Ok(receiver)
+    }
+
+    /// Client API: Subscribe to indicator updates for a symbol
+    pub async fn subscribe_to_indicators(
+        &self,
+        symbol: String,
+    ) -> Result<mpsc::Receiver<IndicatorUpdate>, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<mpsc::Receiver<IndicatorUpdate>>();
+        self.command_tx
+            .send(MarketCommand::SubscribeIndicators(symbol, oneshot_sender))
+            .await?;
+        let receiver = timeout(Duration::from_secs(5), oneshot_recv).await??;
+        Ok(receiver)
+    }
+
+    /// Client API: Subscribe to bid/ask quotes for a symbol
+    pub async fn subscribe_to_quotes(
+        &self,
+        symbol: String,
+    ) -> Result<mpsc::Receiver<Quote>, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<mpsc::Receiver<Quote>>();
+        self.command_tx
+            .send(MarketCommand::SubscribeQuotes(symbol, oneshot_sender))
+            .await?;
+        let receiver = timeout(Duration::from_secs(5), oneshot_recv).await??;
+        Ok(receiver)
+    }
+
+    /// Client API: Subscribe to a symbol's order book
+    pub async fn subscribe_to_book(
+        &self,
+        symbol: String,
+        mode: BookSubscription,
+    ) -> Result<mpsc::Receiver<BookMessage>, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<mpsc::Receiver<BookMessage>>();
+        self.command_tx
+            .send(MarketCommand::SubscribeBook(symbol, mode, oneshot_sender))
+            .await?;
+        let receiver = timeout(Duration::from_secs(5), oneshot_recv).await??;
+        Ok(receiver)
+    }
+
+    /// Client API: Get quote statistics for all subscribed symbols
+    pub async fn get_quote_statistics(
+        &self,
+    ) -> Result<Vec<QuoteStats>, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel::<Vec<QuoteStats>>();
+        self.command_tx
+            .send(MarketCommand::GetQuoteStats(oneshot_sender))
+            .await?;
+        let stats = timeout(Duration::from_secs(5), oneshot_recv).await??;
+        Ok(stats)
+    }
+
     /// Client API: Get current statistics for all symbols
     pub async fn get_statistics(
         &self,
@@ -220,6 +1257,19 @@ impl MarketDataHub {
         Ok(receiver)
     }
 
+    /// Client API: Get rolling statistics over `window` for all subscribed symbols
+    pub async fn get_window_statistics(
+        &self,
+        window: StatsWindow,
+    ) -> Result<Vec<WindowStats>, Box<dyn std::error::Error + Send + Sync>> {
+        let (oneshot_sender, oneshot_recv) = oneshot::channel();
+        self.command_tx
+            .send(MarketCommand::GetWindowStats(window, oneshot_sender))
+            .await?;
+        let stats = timeout(Duration::from_secs(5), oneshot_recv).await???;
+        Ok(stats)
+    }
+
     /// Client API: Unsubscribe from a symbol
     pub async fn unsubscribe_from_symbol(
         &self,
@@ -249,6 +1299,16 @@ impl MarketDataHub {
     }
 }
 
+/// Tick an optional timer; a missing timer never fires
+async fn tick_optional(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 // Key learning points for this exercise:
 //
 // 1. **Command Pattern**: MarketCommand enum encapsulates different operations
@@ -273,3 +1333,638 @@ impl MarketDataHub {
 //    - tokio::select! for handling multiple async operations
 //    - Non-blocking sends with proper error handling
 //    - Coordinated shutdown across multiple components
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::delivery::SUBSCRIBER_CAPACITY;
+    use crate::processor::gaps::GapStats;
+    use crate::processor::subscription::TickFilter;
+    use crate::storage::recorder::TickRecorder;
+    use rust_decimal::Decimal;
+
+    type HubTask = JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>>;
+
+    /// Run a hub set up by `configure`, returning its data and command senders
+    fn spawn_hub(
+        configure: impl FnOnce(MarketDataHub) -> MarketDataHub,
+    ) -> (
+        mpsc::Sender<MarketEvent>,
+        mpsc::Sender<MarketCommand>,
+        HubTask,
+    ) {
+        let (data_tx, data_rx) = mpsc::channel::<MarketEvent>(10);
+        let mut hub = configure(MarketDataHub::new(data_rx));
+        let commands = hub.get_command_sender();
+        let hub_task = tokio::spawn(async move { hub.start().await });
+        (data_tx, commands, hub_task)
+    }
+
+    async fn subscribe(
+        commands: &mpsc::Sender<MarketCommand>,
+        request: SubscriptionRequest,
+    ) -> Subscription {
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::SubscribeWith(request, tx))
+            .await
+            .unwrap();
+        rx.await.unwrap()
+    }
+
+    async fn shutdown(commands: mpsc::Sender<MarketCommand>, hub_task: HubTask) {
+        commands.send(MarketCommand::Shutdown).await.unwrap();
+        hub_task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quotes_on_the_data_channel() {
+        let (event_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::SubscribeQuotes("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let mut quotes = rx.await.unwrap();
+
+        let quote = Quote::new(
+            "AAPL".to_string(),
+            Decimal::new(10000, 2),
+            Decimal::new(10004, 2),
+            100,
+            300,
+            "XNAS".to_string(),
+        );
+        event_tx.send(quote.into()).await.unwrap();
+        let received = quotes.recv().await.unwrap();
+        assert_eq!(received.spread(), Decimal::new(4, 2));
+
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::GetQuoteStats(tx))
+            .await
+            .unwrap();
+        let stats = rx.await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].last_mid_price, Decimal::new(10002, 2));
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_indicators_only_run_for_subscribed_symbols() {
+        let (_data_tx, data_rx) = mpsc::channel::<MarketEvent>(10);
+        let mut hub = MarketDataHub::new(data_rx);
+        let (tx, rx) = oneshot::channel();
+        hub.handle_subscribe_indicators("AAPL".to_string(), tx)
+            .await;
+        let mut updates = rx.await.unwrap();
+
+        hub.fan_out_tick(MarketTick::new("MSFT".to_string(), Decimal::ONE, 1))
+            .await;
+        hub.fan_out_tick(MarketTick::new("AAPL".to_string(), Decimal::TWO, 1))
+            .await;
+        assert!(!hub.indicators.is_tracking("MSFT"));
+        assert_eq!(updates.recv().await.unwrap().symbol, "AAPL");
+
+        hub.handle_unsubscribe("AAPL".to_string()).await;
+        assert!(!hub.indicators.is_tracking("AAPL"));
+    }
+
+    #[tokio::test]
+    async fn test_book_deltas_and_snapshots() {
+        use crate::models::{BookAction, BookSide};
+
+        let (event_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let add = |side, cents, size| {
+            MarketEvent::Book(BookUpdate::new(
+                "AAPL".to_string(),
+                side,
+                BookAction::Add,
+                Decimal::new(cents, 2),
+                size,
+            ))
+        };
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::SubscribeBook(
+                "AAPL".to_string(),
+                BookSubscription::Deltas,
+                tx,
+            ))
+            .await
+            .unwrap();
+        let mut deltas = rx.await.unwrap();
+
+        event_tx.send(add(BookSide::Bid, 10000, 300)).await.unwrap();
+        let Some(BookMessage::Delta(delta)) = deltas.recv().await else {
+            panic!("expected delta");
+        };
+        assert_eq!(delta.side, BookSide::Bid);
+
+        // The book now exists, so a new subscriber starts with a snapshot of it
+        let (tx, rx) = oneshot::channel();
+        let every = Duration::from_millis(50);
+        commands
+            .send(MarketCommand::SubscribeBook(
+                "AAPL".to_string(),
+                BookSubscription::Snapshots { depth: 1, every },
+                tx,
+            ))
+            .await
+            .unwrap();
+        let mut snapshots = rx.await.unwrap();
+        let Some(BookMessage::Snapshot(initial)) = snapshots.recv().await else {
+            panic!("expected initial snapshot");
+        };
+        assert_eq!(initial.bids.len(), 1);
+        assert!(initial.asks.is_empty());
+        assert_eq!(initial.imbalance, Some(Decimal::ONE));
+
+        event_tx.send(add(BookSide::Ask, 10010, 200)).await.unwrap();
+        let Some(BookMessage::Delta(delta)) = deltas.recv().await else {
+            panic!("expected delta");
+        };
+        assert_eq!(delta.side, BookSide::Ask);
+
+        // Periodic snapshots pick up the ask
+        let mut saw_ask = false;
+        for _ in 0..5 {
+            if let Some(BookMessage::Snapshot(snapshot)) = snapshots.recv().await
+                && snapshot.asks.len() == 1
+            {
+                saw_ask = true;
+                break;
+            }
+        }
+        assert!(saw_ask);
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_gap_events_and_duplicates() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let mut updates = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+
+        for sequence in [1, 2, 2, 5] {
+            let tick = MarketTick::new("AAPL".to_string(), Decimal::new(10000, 2), 100)
+                .with_sequence("feed", sequence);
+            data_tx.send(tick.into()).await.unwrap();
+        }
+        let mut received = vec![];
+        for _ in 0..4 {
+            received.push(match updates.recv().await.unwrap() {
+                MarketUpdate::Tick(tick) => format!("tick {}", tick.sequence.unwrap()),
+                MarketUpdate::Gap(gap) => format!("gap {}", gap.missing()),
+                other => panic!("unexpected {other:?}"),
+            });
+        }
+        // The duplicate 2 is dropped and the gap is announced before tick 5
+        assert_eq!(received, ["tick 1", "tick 2", "gap 2", "tick 5"]);
+
+        let (tx, rx) = oneshot::channel();
+        commands.send(MarketCommand::GetStats(tx)).await.unwrap();
+        let stats = rx.await.unwrap();
+        assert_eq!(stats[0].count, 3);
+        assert_eq!(
+            stats[0].gaps,
+            GapStats {
+                gaps: 1,
+                missing_ticks: 2,
+                duplicates: 1,
+                restarts: 0,
+            }
+        );
+
+        shutdown(commands, hub_task).await;
+    }
+
+    fn tick_at(secs: i64) -> MarketTick {
+        MarketTick::with_timestamp(
+            "AAPL".to_string(),
+            Decimal::new(10000 + secs, 2),
+            100,
+            DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+        )
+        .with_sequence("feed", secs as u64 + 1)
+    }
+
+    async fn next_tick(updates: &mut Subscription) -> MarketTick {
+        match updates.recv().await.unwrap() {
+            MarketUpdate::Tick(tick) => tick,
+            other => panic!("expected a tick, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_sequence_then_live() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub.with_replay_buffer(3));
+
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        for secs in 0..5 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+
+        let request = SubscriptionRequest::new("AAPL").from_sequence(4);
+        let mut replayed = subscribe(&commands, request).await;
+        // Asking for more than the buffer holds reports the shortfall as a gap
+        let request = SubscriptionRequest::new("AAPL").from_sequence(1);
+        let mut too_old = subscribe(&commands, request).await;
+        data_tx.send(tick_at(5).into()).await.unwrap();
+
+        let mut sequences = vec![];
+        for _ in 0..3 {
+            sequences.push(next_tick(&mut replayed).await.sequence.unwrap());
+        }
+        assert_eq!(sequences, [4, 5, 6]);
+        match too_old.recv().await.unwrap() {
+            MarketUpdate::Gap(gap) => assert_eq!((gap.expected, gap.received), (1, 3)),
+            other => panic!("expected a gap, got {other:?}"),
+        }
+        assert_eq!(next_tick(&mut too_old).await.sequence, Some(3));
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_timestamp_reads_recordings() {
+        let dir = std::env::temp_dir().join(format!("hub_replay_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut recorder = TickRecorder::new(&dir);
+        for secs in 0..5 {
+            recorder.record(&tick_at(secs)).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let (data_tx, commands, hub_task) =
+            spawn_hub(|hub| hub.with_replay_buffer(2).with_recordings(&dir));
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        // Tick 4 is both recorded and buffered, where the recordings meet the buffer
+        for secs in 3..6 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+
+        let from = DateTime::from_timestamp(1_700_000_001, 0).unwrap();
+        let request = SubscriptionRequest::new("AAPL").from_timestamp(from);
+        let mut replayed = subscribe(&commands, request).await;
+        data_tx.send(tick_at(6).into()).await.unwrap();
+
+        let mut seconds = vec![];
+        for _ in 0..6 {
+            seconds.push(next_tick(&mut replayed).await.timestamp.timestamp() - 1_700_000_000);
+        }
+        assert_eq!(seconds, [1, 2, 3, 4, 5, 6]);
+
+        shutdown(commands, hub_task).await;
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unread_replay_does_not_stall_hub() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub);
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        let request = SubscriptionRequest::new("AAPL")
+            .from_sequence(1)
+            .with_delivery(DeliveryPolicy::DropNewest);
+        let mut replayed = subscribe(&commands, request).await;
+
+        // Nothing reads the replay, so its live leg fills up and drops instead of blocking
+        for secs in 0..1100 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+        assert!(replayed.delivery_stats().dropped > 0);
+        assert_eq!(next_tick(&mut replayed).await.sequence, Some(1));
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_unread_blocking_replay_does_not_stall_hub() {
+        let (data_tx, commands, hub_task) =
+            spawn_hub(|hub| hub.with_replay_buffer(1500).with_replay_staging(2000));
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        for secs in 0..1500 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+        let request = SubscriptionRequest::new("AAPL").from_sequence(1);
+        let mut replayed = subscribe(&commands, request).await;
+
+        // Nothing reads the replay, so live ticks are staged behind it rather than blocking
+        for secs in 1500..2600 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+        for seq in 1..=2600 {
+            assert_eq!(next_tick(&mut replayed).await.sequence, Some(seq));
+        }
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_outgrowing_staging_is_evicted() {
+        let (data_tx, commands, hub_task) =
+            spawn_hub(|hub| hub.with_replay_buffer(200).with_replay_staging(10));
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        for secs in 0..200 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+        let request = SubscriptionRequest::new("AAPL").from_sequence(1);
+        let mut replayed = subscribe(&commands, request).await;
+        for secs in 200..250 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+
+        // What was replayed before the staging limit ran out, then the eviction notice
+        let mut replayed_ticks = 0;
+        let reason = loop {
+            match replayed.recv().await.unwrap() {
+                MarketUpdate::Tick(tick) => {
+                    replayed_ticks += 1;
+                    assert_eq!(tick.sequence, Some(replayed_ticks));
+                }
+                MarketUpdate::Evicted(reason) => break reason,
+                other => panic!("expected a tick or eviction, got {other:?}"),
+            }
+        };
+        assert_eq!(reason, SlowConsumerReason::ReplayStaging { limit: 10 });
+        assert!(replayed_ticks < 200);
+        assert!(replayed.recv().await.is_none());
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_replay_waits_for_recorder_to_catch_up() {
+        let dir = std::env::temp_dir().join(format!("hub_replay_lag_{}", std::process::id()));
+        let (tap_tx, tap_rx) = mpsc::channel(10);
+        let recorder = TickRecorder::new(&dir);
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| {
+            hub.with_tap(tap_tx)
+                .with_replay_buffer(2)
+                .with_recordings(&dir)
+                .with_recording_watermark(recorder.watermark())
+        });
+        let recorder = tokio::spawn(recorder.run(tap_rx));
+        let mut live = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+        // Let the recorder's first flush pass so these ticks wait for the next one
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // Unsequenced, so a tick the recorder has not written yet could only go missing silently
+        for secs in 0..5 {
+            let mut tick = tick_at(secs);
+            tick.sequence = None;
+            tick.source = None;
+            data_tx.send(tick.into()).await.unwrap();
+            next_tick(&mut live).await;
+        }
+
+        let from = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let request = SubscriptionRequest::new("AAPL").from_timestamp(from);
+        let mut replayed = subscribe(&commands, request).await;
+        let mut seconds = vec![];
+        for _ in 0..5 {
+            seconds.push(next_tick(&mut replayed).await.timestamp.timestamp() - 1_700_000_000);
+        }
+        assert_eq!(seconds, [0, 1, 2, 3, 4]);
+
+        shutdown(commands, hub_task).await;
+        recorder.await.unwrap().unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_last_value_on_subscribe() {
+        let (tap_tx, mut tap_rx) = mpsc::channel(10);
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub.with_tap(tap_tx));
+
+        data_tx.send(tick_at(0).into()).await.unwrap();
+        data_tx.send(tick_at(1).into()).await.unwrap();
+        tap_rx.recv().await.unwrap();
+        tap_rx.recv().await.unwrap();
+
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::Subscribe("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let mut updates = rx.await.unwrap();
+        match updates.recv().await.unwrap() {
+            MarketUpdate::Snapshot { tick, stats } => {
+                assert_eq!(tick.price, Decimal::new(10001, 2));
+                assert_eq!(stats.unwrap().count, 2);
+            }
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+        data_tx.send(tick_at(2).into()).await.unwrap();
+        assert_eq!(next_tick(&mut updates).await.price, Decimal::new(10002, 2));
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_one_subscriber_only() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let mut subscriptions = vec![];
+        for _ in 0..3 {
+            subscriptions.push(subscribe(&commands, SubscriptionRequest::new("AAPL")).await);
+        }
+        let mut kept = subscriptions.pop().unwrap();
+        let mut cancelled = subscriptions.pop().unwrap();
+        assert_ne!(kept.id(), cancelled.id());
+        // Dropping the handle unsubscribes it
+        drop(subscriptions);
+        commands
+            .send(MarketCommand::UnsubscribeById(cancelled.id()))
+            .await
+            .unwrap();
+        // Commands are handled in order, so both unsubscribes are done before the listing
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::GetSubscriptions("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        assert_eq!(rx.await.unwrap(), [kept.id()]);
+
+        data_tx.send(tick_at(0).into()).await.unwrap();
+        assert_eq!(next_tick(&mut kept).await.price, Decimal::new(10000, 2));
+        assert!(cancelled.recv().await.is_none());
+        data_tx.send(tick_at(1).into()).await.unwrap();
+        assert_eq!(next_tick(&mut kept).await.price, Decimal::new(10001, 2));
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_pattern_subscriptions_pick_up_new_symbols() {
+        let (tap_tx, mut tap_rx) = mpsc::channel(10);
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub.with_tap(tap_tx));
+        let tick = |symbol: &str| MarketTick::new(symbol.to_string(), Decimal::ONE, 1);
+
+        data_tx.send(tick("AAPL").into()).await.unwrap();
+        tap_rx.recv().await.unwrap();
+
+        let mut subscriptions = vec![];
+        for pattern in [SymbolPattern::Prefix("A".to_string()), SymbolPattern::All] {
+            let request = SubscriptionRequest::matching(pattern);
+            subscriptions.push(subscribe(&commands, request).await);
+        }
+        for symbol in ["MSFT", "AMZN", "AAPL"] {
+            data_tx.send(tick(symbol).into()).await.unwrap();
+        }
+
+        // AAPL was seen before the subscriptions, AMZN and MSFT only after
+        let prefix = &mut subscriptions[0];
+        assert_eq!(next_tick(prefix).await.symbol, "AMZN");
+        assert_eq!(next_tick(prefix).await.symbol, "AAPL");
+        let all = &mut subscriptions[1];
+        for symbol in ["MSFT", "AMZN", "AAPL"] {
+            assert_eq!(next_tick(all).await.symbol, symbol);
+        }
+        // Unsubscribing a symbol leaves the pattern subscriptions matching it in place
+        commands
+            .send(MarketCommand::Unsubscribe("AAPL".to_string()))
+            .await
+            .unwrap();
+        data_tx.send(tick("AAPL").into()).await.unwrap();
+        assert_eq!(next_tick(&mut subscriptions[0]).await.symbol, "AAPL");
+        assert_eq!(next_tick(&mut subscriptions[1]).await.symbol, "AAPL");
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let request = SubscriptionRequest::new("AAPL").with_filter(TickFilter::SignificantVolume);
+        let mut large_trades = subscribe(&commands, request).await;
+        let mut everything = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+
+        for volume in [500, 5000, 10, 2000] {
+            let tick = MarketTick::new("AAPL".to_string(), Decimal::ONE, volume);
+            data_tx.send(tick.into()).await.unwrap();
+        }
+        for _ in 0..4 {
+            next_tick(&mut everything).await;
+        }
+        assert_eq!(next_tick(&mut large_trades).await.volume, 5000);
+        assert_eq!(next_tick(&mut large_trades).await.volume, 2000);
+        assert!(large_trades.try_recv().is_err());
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_slow_conflated_subscriber_does_not_stall_hub() {
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub);
+
+        let request = SubscriptionRequest::new("AAPL").with_delivery(DeliveryPolicy::Conflate);
+        let mut conflated = subscribe(&commands, request).await;
+        let mut fast = subscribe(&commands, SubscriptionRequest::new("AAPL")).await;
+
+        // Far more than a blocking subscriber's channel holds, while `conflated` reads nothing
+        let total = 3000;
+        let producer = tokio::spawn(async move {
+            for volume in 1..=total {
+                let tick = MarketTick::new("AAPL".to_string(), Decimal::ONE, volume);
+                data_tx.send(tick.into()).await.unwrap();
+            }
+        });
+        for _ in 0..total {
+            next_tick(&mut fast).await;
+        }
+        producer.await.unwrap();
+
+        assert_eq!(next_tick(&mut conflated).await.volume, total);
+        assert_eq!(conflated.delivery_stats().conflated, total - 1);
+        assert!(conflated.try_recv().is_err());
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_evicted_with_reason() {
+        let policy = SlowConsumerPolicy::new(5, Duration::from_secs(60))
+            .with_eviction(Duration::ZERO)
+            .with_check_interval(Duration::from_millis(10));
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub.with_slow_consumer_policy(policy));
+
+        let mut subscribers = vec![];
+        for _ in 0..2 {
+            subscribers.push(subscribe(&commands, SubscriptionRequest::new("AAPL")).await);
+        }
+        let (mut slow, mut fast) = (subscribers.remove(0), subscribers.remove(0));
+
+        // `slow` reads nothing until it has been evicted
+        for secs in 0..10 {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut fast).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        data_tx.send(tick_at(10).into()).await.unwrap();
+        assert_eq!(next_tick(&mut fast).await.sequence, Some(11));
+
+        let mut queued = 0;
+        let reason = loop {
+            match slow.recv().await.unwrap() {
+                MarketUpdate::Tick(_) => queued += 1,
+                MarketUpdate::Evicted(reason) => break reason,
+                other => panic!("unexpected {other:?}"),
+            }
+        };
+        assert!(queued > 5);
+        assert!(matches!(
+            reason,
+            SlowConsumerReason::QueueDepth { limit: 5, .. }
+        ));
+        assert!(slow.recv().await.is_none());
+
+        shutdown(commands, hub_task).await;
+    }
+
+    #[tokio::test]
+    async fn test_full_blocking_subscriber_is_evicted() {
+        // Checks never come round in time, so only the send path can evict
+        let policy = SlowConsumerPolicy::new(5, Duration::from_secs(60))
+            .with_eviction(Duration::from_millis(50))
+            .with_check_interval(Duration::from_secs(3600));
+        let (data_tx, commands, hub_task) = spawn_hub(|hub| hub.with_slow_consumer_policy(policy));
+
+        let mut subscribers = vec![];
+        for _ in 0..2 {
+            subscribers.push(subscribe(&commands, SubscriptionRequest::new("AAPL")).await);
+        }
+        let (mut slow, mut fast) = (subscribers.remove(0), subscribers.remove(0));
+
+        let total = SUBSCRIBER_CAPACITY as i64 + 100;
+        for secs in 0..total {
+            data_tx.send(tick_at(secs).into()).await.unwrap();
+            next_tick(&mut fast).await;
+        }
+
+        let mut queued = 0;
+        let reason = loop {
+            match slow.recv().await.unwrap() {
+                MarketUpdate::Tick(_) => queued += 1,
+                MarketUpdate::Evicted(reason) => break reason,
+                other => panic!("unexpected {other:?}"),
+            }
+        };
+        assert_eq!(queued, SUBSCRIBER_CAPACITY);
+        assert!(matches!(reason, SlowConsumerReason::Blocked { .. }));
+        assert!(slow.recv().await.is_none());
+
+        shutdown(commands, hub_task).await;
+    }
+}
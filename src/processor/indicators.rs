@@ -0,0 +1,568 @@
+use crate::models::MarketTick;
+use crate::processor::bars::Bar;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Simple moving average over the last `period` values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sma {
+    period: usize,
+    values: VecDeque<Decimal>,
+    sum: Decimal,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma {
+            period: period.max(1),
+            values: VecDeque::with_capacity(period),
+            sum: Decimal::ZERO,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        self.values.push_back(value);
+        self.sum += value;
+        if self.values.len() > self.period {
+            self.sum -= self.values.pop_front().unwrap_or_default();
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        (self.values.len() == self.period).then(|| self.sum / Decimal::from(self.period))
+    }
+}
+
+/// Exponential moving average, seeded with the SMA of the first `period` values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ema {
+    alpha: Decimal,
+    seed: Sma,
+    current: Option<Decimal>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Ema {
+            alpha: Decimal::TWO / Decimal::from(period + 1),
+            seed: Sma::new(period),
+            current: None,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        self.current = match self.current {
+            Some(prev) => Some(prev + self.alpha * (value - prev)),
+            None => self.seed.update(value),
+        };
+        self.current
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        self.current
+    }
+}
+
+// Wilder's smoothing, used by RSI and ATR: seeded with a plain average, then
+// avg = (avg * (n - 1) + x) / n
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WilderAverage {
+    period: usize,
+    seed_sum: Decimal,
+    seed_count: usize,
+    current: Option<Decimal>,
+}
+
+impl WilderAverage {
+    fn new(period: usize) -> Self {
+        WilderAverage {
+            period: period.max(1),
+            seed_sum: Decimal::ZERO,
+            seed_count: 0,
+            current: None,
+        }
+    }
+
+    fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        let period = Decimal::from(self.period);
+        self.current = match self.current {
+            Some(prev) => Some((prev * (period - Decimal::ONE) + value) / period),
+            None => {
+                self.seed_sum += value;
+                self.seed_count += 1;
+                (self.seed_count == self.period).then(|| self.seed_sum / period)
+            }
+        };
+        self.current
+    }
+}
+
+/// Relative strength index (0-100) using Wilder's smoothing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rsi {
+    previous: Option<Decimal>,
+    gains: WilderAverage,
+    losses: WilderAverage,
+    current: Option<Decimal>,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi {
+            previous: None,
+            gains: WilderAverage::new(period),
+            losses: WilderAverage::new(period),
+            current: None,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        let previous = self.previous.replace(value)?;
+        let change = value - previous;
+        let avg_gain = self.gains.update(change.max(Decimal::ZERO));
+        let avg_loss = self.losses.update((-change).max(Decimal::ZERO));
+        self.current = match (avg_gain, avg_loss) {
+            (Some(_), Some(loss)) if loss.is_zero() => Some(Decimal::ONE_HUNDRED),
+            (Some(gain), Some(loss)) => {
+                Some(Decimal::ONE_HUNDRED - Decimal::ONE_HUNDRED / (Decimal::ONE + gain / loss))
+            }
+            _ => None,
+        };
+        self.current
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        self.current
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MacdValue {
+    pub macd: Decimal,
+    pub signal: Decimal,
+    pub histogram: Decimal,
+}
+
+/// Moving average convergence/divergence: fast EMA - slow EMA, with an EMA signal line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+    current: Option<MacdValue>,
+}
+
+impl Macd {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Self {
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+            current: None,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<MacdValue> {
+        let fast = self.fast.update(value);
+        let slow = self.slow.update(value);
+        let (Some(fast), Some(slow)) = (fast, slow) else {
+            return None;
+        };
+        let macd = fast - slow;
+        self.current = self.signal.update(macd).map(|signal| MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        });
+        self.current
+    }
+
+    pub fn value(&self) -> Option<MacdValue> {
+        self.current
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BollingerValue {
+    pub middle: Decimal,
+    pub upper: Decimal,
+    pub lower: Decimal,
+}
+
+/// Bollinger Bands: SMA +/- `width` population standard deviations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BollingerBands {
+    width: Decimal,
+    sma: Sma,
+    sum_squares: Decimal,
+    current: Option<BollingerValue>,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, width: Decimal) -> Self {
+        BollingerBands {
+            width,
+            sma: Sma::new(period),
+            sum_squares: Decimal::ZERO,
+            current: None,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<BollingerValue> {
+        if self.sma.values.len() == self.sma.period
+            && let Some(oldest) = self.sma.values.front()
+        {
+            self.sum_squares -= oldest * oldest;
+        }
+        self.sum_squares += value * value;
+        let middle = self.sma.update(value)?;
+
+        let variance = (self.sum_squares / Decimal::from(self.sma.period) - middle * middle)
+            .max(Decimal::ZERO);
+        let band = self.width * variance.sqrt().unwrap_or_default();
+        self.current = Some(BollingerValue {
+            middle,
+            upper: middle + band,
+            lower: middle - band,
+        });
+        self.current
+    }
+
+    pub fn value(&self) -> Option<BollingerValue> {
+        self.current
+    }
+}
+
+/// Average true range, Wilder-smoothed; needs high/low/close so is best fed from bars
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Atr {
+    previous_close: Option<Decimal>,
+    average: WilderAverage,
+    current: Option<Decimal>,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Atr {
+            previous_close: None,
+            average: WilderAverage::new(period),
+            current: None,
+        }
+    }
+
+    pub fn update(&mut self, high: Decimal, low: Decimal, close: Decimal) -> Option<Decimal> {
+        let true_range = match self.previous_close.replace(close) {
+            Some(prev) => (high - low)
+                .max((high - prev).abs())
+                .max((low - prev).abs()),
+            None => high - low,
+        };
+        self.current = self.average.update(true_range);
+        self.current
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        self.current
+    }
+}
+
+/// Realized volatility: sqrt of the sum of squared log returns over the last `period` returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealizedVolatility {
+    period: usize,
+    previous: Option<Decimal>,
+    squared_returns: VecDeque<Decimal>,
+    sum: Decimal,
+}
+
+impl RealizedVolatility {
+    pub fn new(period: usize) -> Self {
+        RealizedVolatility {
+            period: period.max(1),
+            previous: None,
+            squared_returns: VecDeque::with_capacity(period),
+            sum: Decimal::ZERO,
+        }
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        if value <= Decimal::ZERO {
+            return self.value();
+        }
+        let previous = self.previous.replace(value)?;
+        let log_return = (value / previous).checked_ln()?;
+        let squared = log_return * log_return;
+        self.squared_returns.push_back(squared);
+        self.sum += squared;
+        if self.squared_returns.len() > self.period {
+            self.sum -= self.squared_returns.pop_front().unwrap_or_default();
+        }
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        if self.squared_returns.len() < self.period {
+            return None;
+        }
+        self.sum.max(Decimal::ZERO).sqrt()
+    }
+}
+
+/// Periods and parameters for every indicator in an `IndicatorSet`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorConfig {
+    pub sma_period: usize,
+    pub ema_period: usize,
+    pub rsi_period: usize,
+    pub macd_fast: usize,
+    pub macd_slow: usize,
+    pub macd_signal: usize,
+    pub bollinger_period: usize,
+    pub bollinger_width: Decimal,
+    pub atr_period: usize,
+    pub volatility_period: usize,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        IndicatorConfig {
+            sma_period: 20,
+            ema_period: 20,
+            rsi_period: 14,
+            macd_fast: 12,
+            macd_slow: 26,
+            macd_signal: 9,
+            bollinger_period: 20,
+            bollinger_width: Decimal::TWO,
+            atr_period: 14,
+            volatility_period: 30,
+        }
+    }
+}
+
+/// Latest indicator values for a symbol; `None` until an indicator has enough data
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorUpdate {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub sma: Option<Decimal>,
+    pub ema: Option<Decimal>,
+    pub rsi: Option<Decimal>,
+    pub macd: Option<MacdValue>,
+    pub bollinger: Option<BollingerValue>,
+    pub atr: Option<Decimal>,
+    pub realized_volatility: Option<Decimal>,
+}
+
+/// All indicators for one symbol, updated together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicatorSet {
+    sma: Sma,
+    ema: Ema,
+    rsi: Rsi,
+    macd: Macd,
+    bollinger: BollingerBands,
+    atr: Atr,
+    volatility: RealizedVolatility,
+}
+
+impl IndicatorSet {
+    pub fn new(config: &IndicatorConfig) -> Self {
+        IndicatorSet {
+            sma: Sma::new(config.sma_period),
+            ema: Ema::new(config.ema_period),
+            rsi: Rsi::new(config.rsi_period),
+            macd: Macd::new(config.macd_fast, config.macd_slow, config.macd_signal),
+            bollinger: BollingerBands::new(config.bollinger_period, config.bollinger_width),
+            atr: Atr::new(config.atr_period),
+            volatility: RealizedVolatility::new(config.volatility_period),
+        }
+    }
+
+    pub fn update(&mut self, high: Decimal, low: Decimal, close: Decimal) {
+        self.sma.update(close);
+        self.ema.update(close);
+        self.rsi.update(close);
+        self.macd.update(close);
+        self.bollinger.update(close);
+        self.atr.update(high, low, close);
+        self.volatility.update(close);
+    }
+
+    pub fn snapshot(&self, symbol: &str, timestamp: DateTime<Utc>) -> IndicatorUpdate {
+        IndicatorUpdate {
+            symbol: symbol.to_string(),
+            timestamp,
+            sma: self.sma.value(),
+            ema: self.ema.value(),
+            rsi: self.rsi.value(),
+            macd: self.macd.value(),
+            bollinger: self.bollinger.value(),
+            atr: self.atr.value(),
+            realized_volatility: self.volatility.value(),
+        }
+    }
+}
+
+/// Per-symbol indicator sets
+///
+/// Feed it either ticks (each tick is treated as a zero-range bar at its price)
+/// or completed bars, not both, since every update advances every indicator.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IndicatorEngine {
+    config: IndicatorConfig,
+    symbols: HashMap<String, IndicatorSet>,
+}
+
+impl Default for IndicatorEngine {
+    fn default() -> Self {
+        Self::new(IndicatorConfig::default())
+    }
+}
+
+impl IndicatorEngine {
+    pub fn new(config: IndicatorConfig) -> Self {
+        IndicatorEngine {
+            config,
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn on_tick(&mut self, tick: &MarketTick) -> IndicatorUpdate {
+        self.advance_tick(tick)
+            .snapshot(&tick.symbol, tick.timestamp)
+    }
+
+    /// Update a symbol's indicators from a tick without building an `IndicatorUpdate`
+    pub fn advance_tick(&mut self, tick: &MarketTick) -> &IndicatorSet {
+        self.advance(&tick.symbol, tick.price, tick.price, tick.price)
+    }
+
+    pub fn on_bar(&mut self, bar: &Bar) -> IndicatorUpdate {
+        self.update(&bar.symbol, bar.end(), bar.high, bar.low, bar.close)
+    }
+
+    pub fn latest(&self, symbol: &str, timestamp: DateTime<Utc>) -> Option<IndicatorUpdate> {
+        Some(self.symbols.get(symbol)?.snapshot(symbol, timestamp))
+    }
+
+    /// True once a symbol has indicator state, from updates or a restored snapshot
+    pub fn is_tracking(&self, symbol: &str) -> bool {
+        self.symbols.contains_key(symbol)
+    }
+
+    /// Drop a symbol's indicator state; it starts from scratch if updated again
+    pub fn forget(&mut self, symbol: &str) {
+        self.symbols.remove(symbol);
+    }
+
+    fn update(
+        &mut self,
+        symbol: &str,
+        timestamp: DateTime<Utc>,
+        high: Decimal,
+        low: Decimal,
+        close: Decimal,
+    ) -> IndicatorUpdate {
+        self.advance(symbol, high, low, close)
+            .snapshot(symbol, timestamp)
+    }
+
+    fn advance(
+        &mut self,
+        symbol: &str,
+        high: Decimal,
+        low: Decimal,
+        close: Decimal,
+    ) -> &IndicatorSet {
+        let set = self
+            .symbols
+            .entry(symbol.to_string())
+            .or_insert_with(|| IndicatorSet::new(&self.config));
+        set.update(high, low, close);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sma_and_ema() {
+        let mut sma = Sma::new(3);
+        let mut ema = Ema::new(3);
+        let outputs: Vec<_> = [1, 2, 3, 4]
+            .into_iter()
+            .map(|v| (sma.update(Decimal::from(v)), ema.update(Decimal::from(v))))
+            .collect();
+        assert_eq!(outputs[1], (None, None));
+        assert_eq!(outputs[2], (Some(Decimal::from(2)), Some(Decimal::from(2))));
+        // alpha = 0.5: 2 + 0.5 * (4 - 2)
+        assert_eq!(outputs[3], (Some(Decimal::from(3)), Some(Decimal::from(3))));
+    }
+
+    #[test]
+    fn test_rsi_extremes() {
+        let mut rising = Rsi::new(3);
+        let mut alternating = Rsi::new(2);
+        for v in 1..=5 {
+            rising.update(Decimal::from(v));
+        }
+        for v in [10, 11, 10, 11, 10] {
+            alternating.update(Decimal::from(v));
+        }
+        assert_eq!(rising.value(), Some(Decimal::ONE_HUNDRED));
+        // Gains and losses of equal size in the seed, then a loss
+        let rsi = alternating.value().unwrap();
+        assert!(rsi > Decimal::ZERO && rsi < Decimal::from(50));
+    }
+
+    #[test]
+    fn test_bollinger_and_atr() {
+        let mut bands = BollingerBands::new(4, Decimal::TWO);
+        let mut atr = Atr::new(2);
+        for v in [2, 4, 4, 4, 5, 5, 7, 9] {
+            bands.update(Decimal::from(v));
+        }
+        // Last four values 5,5,7,9: mean 6.5, population stddev sqrt(2.75)
+        let value = bands.value().unwrap();
+        assert_eq!(value.middle, Decimal::new(65, 1));
+        assert_eq!(
+            (value.upper - value.middle).round_dp(6),
+            (Decimal::TWO * Decimal::new(275, 2).sqrt().unwrap()).round_dp(6)
+        );
+
+        atr.update(Decimal::from(10), Decimal::from(8), Decimal::from(9));
+        atr.update(Decimal::from(12), Decimal::from(10), Decimal::from(11));
+        // True ranges 2 and 3
+        assert_eq!(atr.value(), Some(Decimal::new(25, 1)));
+    }
+
+    #[test]
+    fn test_engine_per_symbol() {
+        let config = IndicatorConfig {
+            volatility_period: 2,
+            ..IndicatorConfig::default()
+        };
+        let mut engine = IndicatorEngine::new(config);
+        for cents in [10000, 10100, 10000] {
+            engine.on_tick(&MarketTick::new(
+                "AAPL".to_string(),
+                Decimal::new(cents, 2),
+                10,
+            ));
+        }
+        let update = engine.on_tick(&MarketTick::new("MSFT".to_string(), Decimal::ONE, 1));
+        assert_eq!(update.realized_volatility, None);
+
+        let aapl = engine.latest("AAPL", Utc::now()).unwrap();
+        let expected = (Decimal::new(101, 2).ln() * Decimal::TWO.sqrt().unwrap()).round_dp(6);
+        assert_eq!(aapl.realized_volatility.unwrap().round_dp(6), expected);
+        assert_eq!(aapl.sma, None);
+    }
+}
@@ -8,6 +8,50 @@ pub mod aggregator;
 
 pub use aggregator::*;
 
+pub mod bars;
+
+pub use bars::*;
+
+pub mod sketch;
+
+pub use sketch::*;
+
+pub mod stats;
+
+pub use stats::*;
+
+pub mod window;
+
+pub use window::*;
+
+pub mod indicators;
+
+pub use indicators::*;
+
+pub mod order_book;
+
+pub use order_book::*;
+
+pub mod delivery;
+
+pub use delivery::*;
+
+pub mod gaps;
+
+pub use gaps::*;
+
+pub mod history;
+
+pub use history::*;
+
+pub mod slow_consumer;
+
+pub use slow_consumer::*;
+
+pub mod subscription;
+
+pub use subscription::*;
+
 pub mod hub;
 
 pub use hub::*;
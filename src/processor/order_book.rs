@@ -0,0 +1,276 @@
+use crate::models::{BookAction, BookSide, BookUpdate};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: Decimal,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopOfBook {
+    pub best_bid: Option<BookLevel>,
+    pub best_ask: Option<BookLevel>,
+}
+
+impl TopOfBook {
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask?.price - self.best_bid?.price)
+    }
+
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.best_ask?.price + self.best_bid?.price) / Decimal::TWO)
+    }
+}
+
+/// The best `depth` levels on each side, best price first
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    /// `OrderBook::imbalance` over the levels in this snapshot
+    pub imbalance: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookError {
+    /// Add for a price level that already exists
+    DuplicateLevel(BookSide, Decimal),
+    /// Modify or delete for a price level that does not exist
+    UnknownLevel(BookSide, Decimal),
+    /// Update routed to the book of a different symbol
+    WrongSymbol(String),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::DuplicateLevel(side, price) => {
+                write!(f, "{side:?} level {price} already exists")
+            }
+            BookError::UnknownLevel(side, price) => write!(f, "{side:?} level {price} not found"),
+            BookError::WrongSymbol(symbol) => write!(f, "update for {symbol} sent to wrong book"),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+/// Price-level limit order book for one symbol, rebuilt from `BookUpdate`s
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    symbol: String,
+    bids: BTreeMap<Decimal, u64>,
+    asks: BTreeMap<Decimal, u64>,
+    last_update: Option<DateTime<Utc>>,
+}
+
+impl OrderBook {
+    pub fn new(symbol: String) -> Self {
+        OrderBook {
+            symbol,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update: None,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Apply a price-level update; a modify to size zero removes the level
+    pub fn apply(&mut self, update: &BookUpdate) -> Result<(), BookError> {
+        if update.symbol != self.symbol {
+            return Err(BookError::WrongSymbol(update.symbol.clone()));
+        }
+        let levels = match update.side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        };
+        match update.action {
+            BookAction::Add => {
+                if levels.contains_key(&update.price) {
+                    return Err(BookError::DuplicateLevel(update.side, update.price));
+                }
+                if update.size > 0 {
+                    levels.insert(update.price, update.size);
+                }
+            }
+            BookAction::Modify => {
+                let Some(size) = levels.get_mut(&update.price) else {
+                    return Err(BookError::UnknownLevel(update.side, update.price));
+                };
+                if update.size == 0 {
+                    levels.remove(&update.price);
+                } else {
+                    *size = update.size;
+                }
+            }
+            BookAction::Delete => {
+                if levels.remove(&update.price).is_none() {
+                    return Err(BookError::UnknownLevel(update.side, update.price));
+                }
+            }
+        }
+        self.last_update = self.last_update.max(Some(update.timestamp));
+        Ok(())
+    }
+
+    pub fn best_bid(&self) -> Option<BookLevel> {
+        self.bids.iter().next_back().map(|(price, size)| BookLevel {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    pub fn best_ask(&self) -> Option<BookLevel> {
+        self.asks.iter().next().map(|(price, size)| BookLevel {
+            price: *price,
+            size: *size,
+        })
+    }
+
+    pub fn top_of_book(&self) -> TopOfBook {
+        TopOfBook {
+            best_bid: self.best_bid(),
+            best_ask: self.best_ask(),
+        }
+    }
+
+    pub fn depth(&self, levels: usize) -> BookSnapshot {
+        let to_level = |(price, size): (&Decimal, &u64)| BookLevel {
+            price: *price,
+            size: *size,
+        };
+        BookSnapshot {
+            symbol: self.symbol.clone(),
+            timestamp: self.last_update,
+            bids: self.bids.iter().rev().take(levels).map(to_level).collect(),
+            asks: self.asks.iter().take(levels).map(to_level).collect(),
+            imbalance: self.imbalance(levels),
+        }
+    }
+
+    /// Full book, every level on both sides
+    pub fn snapshot(&self) -> BookSnapshot {
+        self.depth(usize::MAX)
+    }
+
+    /// (bid size - ask size) / (bid size + ask size) over the best `levels` levels
+    /// Ranges from -1 (all asks) to 1 (all bids); `None` for an empty book
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        // Summed as Decimal so huge resting sizes cannot overflow u64
+        let bid_size: Decimal = self
+            .bids
+            .values()
+            .rev()
+            .take(levels)
+            .copied()
+            .map(Decimal::from)
+            .sum();
+        let ask_size: Decimal = self
+            .asks
+            .values()
+            .take(levels)
+            .copied()
+            .map(Decimal::from)
+            .sum();
+        let total = bid_size + ask_size;
+        if total.is_zero() {
+            return None;
+        }
+        Some((bid_size - ask_size) / total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(side: BookSide, action: BookAction, cents: i64, size: u64) -> BookUpdate {
+        BookUpdate::new(
+            "AAPL".to_string(),
+            side,
+            action,
+            Decimal::new(cents, 2),
+            size,
+        )
+    }
+
+    #[test]
+    fn test_book_reconstruction() {
+        let mut book = OrderBook::new("AAPL".to_string());
+        for (side, cents, size) in [
+            (BookSide::Bid, 10000, 300),
+            (BookSide::Bid, 9990, 500),
+            (BookSide::Ask, 10010, 200),
+            (BookSide::Ask, 10020, 100),
+        ] {
+            book.apply(&update(side, BookAction::Add, cents, size))
+                .unwrap();
+        }
+        book.apply(&update(BookSide::Bid, BookAction::Modify, 10000, 100))
+            .unwrap();
+        book.apply(&update(BookSide::Ask, BookAction::Delete, 10010, 0))
+            .unwrap();
+
+        let top = book.top_of_book();
+        assert_eq!(top.best_bid.unwrap().size, 100);
+        assert_eq!(top.best_ask.unwrap().price, Decimal::new(10020, 2));
+        assert_eq!(top.spread(), Some(Decimal::new(20, 2)));
+
+        let depth = book.depth(1);
+        assert_eq!(depth.bids.len(), 1);
+        assert_eq!(depth.asks.len(), 1);
+        // Bid 100 vs ask 100 at the top level
+        assert_eq!(depth.imbalance, Some(Decimal::ZERO));
+        assert_eq!(book.snapshot().bids[1].price, Decimal::new(9990, 2));
+        // Bids 600 vs asks 100
+        assert_eq!(
+            book.imbalance(5),
+            Some(Decimal::new(5, 0) / Decimal::new(7, 0))
+        );
+    }
+
+    #[test]
+    fn test_imbalance_with_huge_sizes() {
+        let mut book = OrderBook::new("AAPL".to_string());
+        for (side, cents) in [
+            (BookSide::Bid, 10000),
+            (BookSide::Bid, 9990),
+            (BookSide::Ask, 10010),
+        ] {
+            book.apply(&update(side, BookAction::Add, cents, u64::MAX))
+                .unwrap();
+        }
+        assert_eq!(book.imbalance(5), Some(Decimal::ONE / Decimal::from(3)));
+    }
+
+    #[test]
+    fn test_invalid_updates() {
+        let mut book = OrderBook::new("AAPL".to_string());
+        book.apply(&update(BookSide::Bid, BookAction::Add, 10000, 1))
+            .unwrap();
+        assert_eq!(
+            book.apply(&update(BookSide::Bid, BookAction::Add, 10000, 1)),
+            Err(BookError::DuplicateLevel(
+                BookSide::Bid,
+                Decimal::new(10000, 2)
+            ))
+        );
+        assert!(
+            book.apply(&update(BookSide::Ask, BookAction::Delete, 10000, 0))
+                .is_err()
+        );
+        book.apply(&update(BookSide::Bid, BookAction::Modify, 10000, 0))
+            .unwrap();
+        assert_eq!(book.top_of_book().best_bid, None);
+        assert_eq!(book.imbalance(1), None);
+    }
+}
@@ -0,0 +1,275 @@
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Median, 95th and 99th percentile estimates
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Quantiles {
+    pub median: Decimal,
+    pub p95: Decimal,
+    pub p99: Decimal,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Mergeable streaming quantile estimator (a merging t-digest)
+///
+/// Values are buffered and periodically folded into a bounded set of
+/// centroids. Centroids near the tails are kept small, so p95/p99 stay
+/// accurate while memory is bounded by roughly `compression` centroids.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SketchState", into = "SketchState")]
+pub struct QuantileSketch {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+// Serialized form; an empty sketch's infinite min/max has no JSON representation
+#[derive(Serialize, Deserialize)]
+struct SketchState {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl From<QuantileSketch> for SketchState {
+    fn from(sketch: QuantileSketch) -> Self {
+        let empty = sketch.count == 0;
+        SketchState {
+            compression: sketch.compression,
+            centroids: sketch.centroids,
+            buffer: sketch.buffer,
+            count: sketch.count,
+            min: (!empty).then_some(sketch.min),
+            max: (!empty).then_some(sketch.max),
+        }
+    }
+}
+
+impl From<SketchState> for QuantileSketch {
+    fn from(state: SketchState) -> Self {
+        QuantileSketch {
+            compression: state.compression,
+            centroids: state.centroids,
+            buffer: state.buffer,
+            count: state.count,
+            min: state.min.unwrap_or(f64::INFINITY),
+            max: state.max.unwrap_or(f64::NEG_INFINITY),
+        }
+    }
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new(100.0)
+    }
+}
+
+impl QuantileSketch {
+    pub fn new(compression: f64) -> Self {
+        QuantileSketch {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        self.buffer.push(value);
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() >= self.buffer_limit() {
+            self.compress();
+        }
+    }
+
+    pub fn insert_decimal(&mut self, value: Decimal) {
+        if let Some(value) = value.to_f64() {
+            self.insert(value);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Fold another sketch into this one, as if all its values had been inserted here
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        if other.count == 0 {
+            return;
+        }
+        self.centroids.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.compress();
+    }
+
+    /// Estimate the value at quantile `q` (0.0..=1.0)
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if self.buffer.is_empty() {
+            return Some(self.interpolate(q.clamp(0.0, 1.0)));
+        }
+        let mut compressed = self.clone();
+        compressed.compress();
+        Some(compressed.interpolate(q.clamp(0.0, 1.0)))
+    }
+
+    pub fn quantiles(&self) -> Option<Quantiles> {
+        let to_decimal = |q| self.quantile(q).and_then(Decimal::from_f64);
+        Some(Quantiles {
+            median: to_decimal(0.5)?,
+            p95: to_decimal(0.95)?,
+            p99: to_decimal(0.99)?,
+        })
+    }
+
+    fn buffer_limit(&self) -> usize {
+        (self.compression as usize * 5).max(16)
+    }
+
+    // Scale function k1: k(q) = d/(2pi) * asin(2q - 1), and its inverse
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn k_inverse(&self, k: f64) -> f64 {
+        ((2.0 * PI * k / self.compression).sin() + 1.0) / 2.0
+    }
+
+    fn compress(&mut self) {
+        let mut all: Vec<Centroid> = self.centroids.drain(..).collect();
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        if all.is_empty() {
+            return;
+        }
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut weight_before = 0.0;
+        let mut limit = total * self.k_inverse(self.k(0.0) + 1.0);
+        let mut iter = all.into_iter();
+        let mut current = iter.next().expect("checked non-empty");
+
+        for next in iter {
+            if weight_before + current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight;
+                merged.push(current);
+                limit = total * self.k_inverse(self.k(weight_before / total) + 1.0);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    // Linear interpolation between centroid centres, using min/max for the tails
+    fn interpolate(&self, q: f64) -> f64 {
+        let centroids = &self.centroids;
+        if centroids.len() == 1 {
+            return centroids[0].mean;
+        }
+        let target = q * self.count as f64;
+        let first = centroids[0];
+        if target < first.weight / 2.0 {
+            let t = target / (first.weight / 2.0);
+            return self.min + t * (first.mean - self.min);
+        }
+
+        let mut cumulative = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let step = (pair[0].weight + pair[1].weight) / 2.0;
+            if target < cumulative + step {
+                let t = (target - cumulative) / step;
+                return pair[0].mean + t * (pair[1].mean - pair[0].mean);
+            }
+            cumulative += step;
+        }
+
+        let last = centroids[centroids.len() - 1];
+        let t = ((target - cumulative) / (last.weight / 2.0)).min(1.0);
+        last.mean + t * (self.max - last.mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected} +/- {tolerance}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn test_quantiles_uniform() {
+        let mut sketch = QuantileSketch::default();
+        for i in 1..=100_000 {
+            sketch.insert(i as f64);
+        }
+        assert!(sketch.centroids.len() < 200);
+        assert_close(sketch.quantile(0.5).unwrap(), 50_000.0, 500.0);
+        assert_close(sketch.quantile(0.95).unwrap(), 95_000.0, 200.0);
+        assert_close(sketch.quantile(0.99).unwrap(), 99_000.0, 100.0);
+    }
+
+    #[test]
+    fn test_merge_matches_single_sketch() {
+        let mut left = QuantileSketch::default();
+        let mut right = QuantileSketch::default();
+        for i in 0..20_000 {
+            if i % 3 == 0 {
+                left.insert(i as f64);
+            } else {
+                right.insert(i as f64);
+            }
+        }
+        left.merge(&right);
+        assert_eq!(left.count(), 20_000);
+        assert_close(left.quantile(0.5).unwrap(), 10_000.0, 200.0);
+        assert_close(left.quantile(0.99).unwrap(), 19_800.0, 60.0);
+    }
+
+    #[test]
+    fn test_empty_and_single_value() {
+        let mut sketch = QuantileSketch::default();
+        assert!(sketch.quantiles().is_none());
+        sketch.insert_decimal(Decimal::new(12345, 2));
+        let quantiles = sketch.quantiles().unwrap();
+        assert_eq!(quantiles.median, Decimal::new(12345, 2));
+        assert_eq!(quantiles.p99, Decimal::new(12345, 2));
+    }
+}
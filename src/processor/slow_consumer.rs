@@ -0,0 +1,240 @@
+use crate::processor::subscription::SubscriptionId;
+use std::collections::HashMap;
+use std::fmt;
+use tokio::time::{Duration, Instant};
+
+/// When the hub considers a subscriber too far behind, and what it does about it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlowConsumerPolicy {
+    /// Queued updates above which a subscriber is behind
+    pub max_queue_depth: usize,
+    /// How long a subscriber may leave updates queued without receiving any
+    pub max_lag: Duration,
+    /// Evict subscribers that stay behind this long; `None` only warns
+    /// A `Blocking` subscriber the hub has waited on this long is evicted without a check
+    pub evict_after: Option<Duration>,
+    /// How often queues are sampled; lag is only as precise as this
+    pub check_every: Duration,
+}
+
+impl SlowConsumerPolicy {
+    pub fn new(max_queue_depth: usize, max_lag: Duration) -> Self {
+        SlowConsumerPolicy {
+            max_queue_depth,
+            max_lag,
+            evict_after: None,
+            check_every: Duration::from_millis(500),
+        }
+    }
+
+    pub fn with_eviction(mut self, grace: Duration) -> Self {
+        self.evict_after = Some(grace);
+        self
+    }
+
+    /// Zero is raised to a millisecond, since the check timer needs a period
+    pub fn with_check_interval(mut self, every: Duration) -> Self {
+        self.check_every = every.max(Duration::from_millis(1));
+        self
+    }
+}
+
+impl Default for SlowConsumerPolicy {
+    /// Warn at 80% of a subscriber's queue or 5s without catching up, never evict
+    fn default() -> Self {
+        Self::new(800, Duration::from_secs(5))
+    }
+}
+
+/// Why a subscriber was found to be behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerReason {
+    QueueDepth {
+        depth: usize,
+        limit: usize,
+    },
+    Lag {
+        lag: Duration,
+        limit: Duration,
+    },
+    /// The hub waited this long for room in a full `Blocking` queue
+    Blocked {
+        waited: Duration,
+    },
+    /// More live updates arrived than a replaying `Blocking` subscriber may hold back
+    ReplayStaging {
+        limit: usize,
+    },
+}
+
+impl fmt::Display for SlowConsumerReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlowConsumerReason::QueueDepth { depth, limit } => {
+                write!(f, "{depth} updates queued, limit is {limit}")
+            }
+            SlowConsumerReason::Lag { lag, limit } => {
+                write!(f, "nothing received for {lag:?}, limit is {limit:?}")
+            }
+            SlowConsumerReason::Blocked { waited } => {
+                write!(f, "hub waited {waited:?} for room in a full queue")
+            }
+            SlowConsumerReason::ReplayStaging { limit } => {
+                write!(f, "over {limit} live updates held back behind its replay")
+            }
+        }
+    }
+}
+
+struct ConsumerHealth {
+    /// Last check that found the queue empty or some updates received since the one before
+    caught_up_at: Instant,
+    received: u64,
+    behind_since: Option<Instant>,
+    warned: bool,
+}
+
+/// Tracks queue depth and lag per subscriber across checks
+pub struct SlowConsumerMonitor {
+    policy: SlowConsumerPolicy,
+    health: HashMap<SubscriptionId, ConsumerHealth>,
+}
+
+impl SlowConsumerMonitor {
+    pub fn new(policy: SlowConsumerPolicy) -> Self {
+        SlowConsumerMonitor {
+            policy,
+            health: HashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &SlowConsumerPolicy {
+        &self.policy
+    }
+
+    /// When the hub should give up waiting for room in subscriber `id`'s full `Blocking`
+    /// queue; `None` without eviction, where it waits as long as it takes
+    pub fn send_deadline(&self, id: SubscriptionId, now: Instant) -> Option<Instant> {
+        let grace = self.policy.evict_after?;
+        let behind_since = self.health.get(&id).and_then(|health| health.behind_since);
+        Some(behind_since.unwrap_or(now) + grace)
+    }
+
+    /// Sample every current subscriber's queue depth and how many updates it has received,
+    /// warning once each time one falls behind; returns the subscribers to evict
+    pub fn check(
+        &mut self,
+        now: Instant,
+        queues: impl IntoIterator<Item = (SubscriptionId, usize, u64)>,
+    ) -> Vec<(SubscriptionId, SlowConsumerReason)> {
+        let mut seen = HashMap::new();
+        let mut evict = vec![];
+        for (id, depth, received) in queues {
+            let health = self.health.remove(&id).unwrap_or(ConsumerHealth {
+                caught_up_at: now,
+                received,
+                behind_since: None,
+                warned: false,
+            });
+            let health = seen.entry(id).or_insert(health);
+            // A consumer keeping pace never sees an empty queue, but its count keeps moving
+            if depth == 0 || received != health.received {
+                health.caught_up_at = now;
+                health.received = received;
+            }
+            let lag = now - health.caught_up_at;
+            let reason = if depth > self.policy.max_queue_depth {
+                Some(SlowConsumerReason::QueueDepth {
+                    depth,
+                    limit: self.policy.max_queue_depth,
+                })
+            } else if lag > self.policy.max_lag {
+                Some(SlowConsumerReason::Lag {
+                    lag,
+                    limit: self.policy.max_lag,
+                })
+            } else {
+                None
+            };
+            let Some(reason) = reason else {
+                health.behind_since = None;
+                health.warned = false;
+                continue;
+            };
+            if !health.warned {
+                println!("Subscriber {id} is falling behind: {reason}");
+                health.warned = true;
+            }
+            let behind_since = *health.behind_since.get_or_insert(now);
+            if let Some(grace) = self.policy.evict_after
+                && now - behind_since >= grace
+            {
+                evict.push((id, reason));
+            }
+        }
+        // Anything not reported this time has unsubscribed
+        self.health = seen;
+        for (id, _) in &evict {
+            self.health.remove(id);
+        }
+        evict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warns_then_evicts_after_grace() {
+        let policy = SlowConsumerPolicy::new(10, Duration::from_secs(2))
+            .with_eviction(Duration::from_secs(3));
+        let mut monitor = SlowConsumerMonitor::new(policy);
+        let (slow, fine) = (SubscriptionId(1), SubscriptionId(2));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(
+            monitor
+                .check(at(0), [(slow, 5, 0), (fine, 0, 0)])
+                .is_empty()
+        );
+        // Receives nothing, so lagging from 2s on; evicted once that has lasted 3s
+        // `fine` always has a backlog but keeps receiving, so it never lags
+        assert!(
+            monitor
+                .check(at(3), [(slow, 5, 0), (fine, 3, 4)])
+                .is_empty()
+        );
+        assert!(
+            monitor
+                .check(at(5), [(slow, 5, 0), (fine, 3, 9)])
+                .is_empty()
+        );
+        let evicted = monitor.check(at(6), [(slow, 5, 0), (fine, 3, 12)]);
+        assert_eq!(
+            evicted,
+            [(
+                slow,
+                SlowConsumerReason::Lag {
+                    lag: Duration::from_secs(6),
+                    limit: Duration::from_secs(2)
+                }
+            )]
+        );
+
+        // A deep queue counts straight away; recovering resets the grace period
+        assert!(monitor.check(at(7), [(fine, 50, 12)]).is_empty());
+        assert_eq!(monitor.send_deadline(fine, at(8)), Some(at(10)));
+        assert!(monitor.check(at(9), [(fine, 0, 62)]).is_empty());
+        assert_eq!(monitor.send_deadline(fine, at(9)), Some(at(12)));
+        assert!(monitor.check(at(11), [(fine, 50, 62)]).is_empty());
+        assert_eq!(monitor.check(at(14), [(fine, 50, 62)]).len(), 1);
+    }
+
+    #[test]
+    fn test_zero_check_interval_is_raised() {
+        let policy = SlowConsumerPolicy::default().with_check_interval(Duration::ZERO);
+        assert!(!policy.check_every.is_zero());
+    }
+}
@@ -0,0 +1,403 @@
+use crate::models::{MarketTick, Quote};
+use crate::processor::sketch::{QuantileSketch, Quantiles};
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Constant-memory running statistics for one symbol
+///
+/// Everything `PriceStats` reports is derived from these accumulators, so
+/// memory use does not grow with the number of ticks. The optional history
+/// buffer keeps only the most recent `capacity` prices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningStats {
+    count: usize,
+    sum: Decimal,
+    sum_squares: Decimal,
+    min: Option<Decimal>,
+    max: Option<Decimal>,
+    total_volume: u64,
+    notional: Decimal,
+    significant_count: usize,
+    price_sketch: QuantileSketch,
+    volume_sketch: QuantileSketch,
+    history: Option<PriceHistory>,
+    #[serde(default)]
+    overflowed: bool,
+}
+
+impl RunningStats {
+    pub fn new(history_capacity: Option<usize>) -> Self {
+        RunningStats {
+            count: 0,
+            sum: Decimal::ZERO,
+            sum_squares: Decimal::ZERO,
+            min: None,
+            max: None,
+            total_volume: 0,
+            notional: Decimal::ZERO,
+            significant_count: 0,
+            price_sketch: QuantileSketch::default(),
+            volume_sketch: QuantileSketch::default(),
+            history: history_capacity.map(PriceHistory::new),
+            overflowed: false,
+        }
+    }
+
+    pub fn add(&mut self, tick: &MarketTick) {
+        self.count += 1;
+        self.overflowed |= !accumulate(&mut self.sum, Some(tick.price));
+        self.overflowed |= !accumulate(&mut self.sum_squares, tick.price.checked_mul(tick.price));
+        self.min = Some(self.min.map_or(tick.price, |min| min.min(tick.price)));
+        self.max = Some(self.max.map_or(tick.price, |max| max.max(tick.price)));
+        self.total_volume = self.total_volume.saturating_add(tick.volume);
+        self.overflowed |= !accumulate(
+            &mut self.notional,
+            tick.price.checked_mul(Decimal::from(tick.volume)),
+        );
+        if tick.is_significant_volume() {
+            self.significant_count += 1;
+        }
+        self.price_sketch.insert_decimal(tick.price);
+        self.volume_sketch.insert(tick.volume as f64);
+        if let Some(history) = &mut self.history {
+            history.push(tick.price);
+        }
+    }
+
+    /// Combine another symbol accumulator into this one
+    /// History from `other` is appended after ours, still bounded by our capacity
+    pub fn merge(&mut self, other: &RunningStats) {
+        self.count += other.count;
+        self.overflowed |= other.overflowed;
+        self.overflowed |= !accumulate(&mut self.sum, Some(other.sum));
+        self.overflowed |= !accumulate(&mut self.sum_squares, Some(other.sum_squares));
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.total_volume = self.total_volume.saturating_add(other.total_volume);
+        self.overflowed |= !accumulate(&mut self.notional, Some(other.notional));
+        self.significant_count += other.significant_count;
+        self.price_sketch.merge(&other.price_sketch);
+        self.volume_sketch.merge(&other.volume_sketch);
+        if let (Some(history), Some(other_history)) = (&mut self.history, &other.history) {
+            other_history
+                .prices
+                .iter()
+                .for_each(|price| history.push(*price));
+        }
+    }
+
+    /// Same statistics with a history buffer of `capacity`, keeping the most recent prices
+    pub fn with_history_capacity(&self, capacity: Option<usize>) -> RunningStats {
+        let mut resized = RunningStats::new(capacity);
+        resized.merge(self);
+        resized
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn sum(&self) -> Decimal {
+        self.sum
+    }
+
+    pub fn min(&self) -> Option<Decimal> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Decimal> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<Decimal> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.sum / Decimal::from(self.count))
+    }
+
+    /// Population standard deviation of price, from the sum of squares
+    pub fn std_dev(&self) -> Option<Decimal> {
+        let mean = self.mean()?;
+        let variance = self.sum_squares / Decimal::from(self.count) - mean.checked_mul(mean)?;
+        // Rounding can push a zero variance slightly negative
+        variance.max(Decimal::ZERO).sqrt()
+    }
+
+    pub fn total_volume(&self) -> u64 {
+        self.total_volume
+    }
+
+    pub fn notional(&self) -> Decimal {
+        self.notional
+    }
+
+    /// Volume-weighted average price, `None` if no volume has traded
+    pub fn vwap(&self) -> Option<Decimal> {
+        if self.total_volume == 0 {
+            return None;
+        }
+        Some(self.notional / Decimal::from(self.total_volume))
+    }
+
+    pub fn significant_count(&self) -> usize {
+        self.significant_count
+    }
+
+    /// A price sum outgrew `Decimal` and stopped accumulating, so the mean, standard
+    /// deviation, notional and VWAP no longer cover every tick
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Estimated median/p95/p99 of price
+    pub fn price_quantiles(&self) -> Option<Quantiles> {
+        self.price_sketch.quantiles()
+    }
+
+    /// Estimated median/p95/p99 of per-tick volume
+    pub fn volume_quantiles(&self) -> Option<Quantiles> {
+        self.volume_sketch.quantiles()
+    }
+
+    /// Most recent prices, oldest first, if a history buffer was configured
+    pub fn recent_prices(&self) -> Option<&VecDeque<Decimal>> {
+        self.history.as_ref().map(|history| &history.prices)
+    }
+}
+
+/// Running spread and mid-price statistics for one symbol's quotes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunningQuoteStats {
+    count: usize,
+    crossed_count: usize,
+    spread_sum: Decimal,
+    mid_sum: Decimal,
+    min_spread: Option<Decimal>,
+    max_spread: Option<Decimal>,
+    last: Option<Quote>,
+}
+
+impl RunningQuoteStats {
+    pub fn add(&mut self, quote: &Quote) {
+        let spread = quote.spread();
+        self.count += 1;
+        if quote.is_crossed() {
+            self.crossed_count += 1;
+        }
+        self.spread_sum += spread;
+        self.mid_sum += quote.mid_price();
+        self.min_spread = Some(self.min_spread.map_or(spread, |min| min.min(spread)));
+        self.max_spread = Some(self.max_spread.map_or(spread, |max| max.max(spread)));
+        if self
+            .last
+            .as_ref()
+            .is_none_or(|last| quote.timestamp >= last.timestamp)
+        {
+            self.last = Some(quote.clone());
+        }
+    }
+
+    pub fn merge(&mut self, other: &RunningQuoteStats) {
+        self.count += other.count;
+        self.crossed_count += other.crossed_count;
+        self.spread_sum += other.spread_sum;
+        self.mid_sum += other.mid_sum;
+        self.min_spread = match (self.min_spread, other.min_spread) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max_spread = match (self.max_spread, other.max_spread) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        if let Some(other_last) = &other.last
+            && self
+                .last
+                .as_ref()
+                .is_none_or(|last| other_last.timestamp >= last.timestamp)
+        {
+            self.last = Some(other_last.clone());
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Quotes seen with bid >= ask
+    pub fn crossed_count(&self) -> usize {
+        self.crossed_count
+    }
+
+    pub fn avg_spread(&self) -> Option<Decimal> {
+        (self.count > 0).then(|| self.spread_sum / Decimal::from(self.count))
+    }
+
+    pub fn avg_mid_price(&self) -> Option<Decimal> {
+        (self.count > 0).then(|| self.mid_sum / Decimal::from(self.count))
+    }
+
+    pub fn min_spread(&self) -> Option<Decimal> {
+        self.min_spread
+    }
+
+    pub fn max_spread(&self) -> Option<Decimal> {
+        self.max_spread
+    }
+
+    /// Most recent quote by timestamp
+    pub fn last(&self) -> Option<&Quote> {
+        self.last.as_ref()
+    }
+}
+
+// Adds `value` to `total`; false, leaving `total` as it was, if `value` or the sum overflowed
+fn accumulate(total: &mut Decimal, value: Option<Decimal>) -> bool {
+    match value.and_then(|value| total.checked_add(value)) {
+        Some(sum) => {
+            *total = sum;
+            true
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PriceHistory {
+    capacity: usize,
+    prices: VecDeque<Decimal>,
+}
+
+impl PriceHistory {
+    fn new(capacity: usize) -> Self {
+        PriceHistory {
+            capacity,
+            prices: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, price: Decimal) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.prices.len() == self.capacity {
+            self.prices.pop_front();
+        }
+        self.prices.push_back(price);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(bid_cents: i64, ask_cents: i64) -> Quote {
+        Quote::new(
+            "AAPL".to_string(),
+            Decimal::new(bid_cents, 2),
+            Decimal::new(ask_cents, 2),
+            100,
+            100,
+            "XNAS".to_string(),
+        )
+    }
+
+    fn tick(cents: i64) -> MarketTick {
+        MarketTick::new("AAPL".to_string(), Decimal::new(cents, 2), 10)
+    }
+
+    #[test]
+    fn test_running_stats() {
+        let mut stats = RunningStats::new(None);
+        for cents in [200, 400, 400, 400, 500, 500, 700, 900] {
+            stats.add(&tick(cents));
+        }
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.min(), Some(Decimal::new(200, 2)));
+        assert_eq!(stats.max(), Some(Decimal::new(900, 2)));
+        assert_eq!(stats.mean(), Some(Decimal::new(5, 0)));
+        assert_eq!(stats.std_dev(), Some(Decimal::new(2, 0)));
+        assert!(stats.recent_prices().is_none());
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut stats = RunningStats::new(Some(3));
+        for cents in 1..=10 {
+            stats.add(&tick(cents));
+        }
+        let recent: Vec<_> = stats.recent_prices().unwrap().iter().copied().collect();
+        assert_eq!(
+            recent,
+            vec![Decimal::new(8, 2), Decimal::new(9, 2), Decimal::new(10, 2)]
+        );
+        assert_eq!(stats.count(), 10);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = RunningStats::new(None);
+        let mut right = RunningStats::new(None);
+        for cents in [200, 400, 400, 400] {
+            left.add(&tick(cents));
+        }
+        for cents in [500, 500, 700, 900] {
+            right.add(&tick(cents));
+        }
+        left.merge(&right);
+        assert_eq!(left.count(), 8);
+        assert_eq!(left.min(), Some(Decimal::new(200, 2)));
+        assert_eq!(left.max(), Some(Decimal::new(900, 2)));
+        assert_eq!(left.std_dev(), Some(Decimal::new(2, 0)));
+        assert_eq!(left.total_volume(), 80);
+        assert_eq!(left.price_quantiles().unwrap().median, Decimal::new(45, 1));
+    }
+
+    #[test]
+    fn test_overflow_is_flagged_instead_of_panicking() {
+        let mut stats = RunningStats::new(None);
+        let huge = MarketTick::new("AAPL".to_string(), Decimal::MAX / Decimal::TWO, 10);
+        stats.add(&huge);
+        assert!(stats.overflowed());
+        assert_eq!(stats.sum(), Decimal::MAX / Decimal::TWO);
+        assert_eq!(stats.std_dev(), None);
+
+        stats.add(&huge);
+        stats.add(&huge);
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.max(), Some(Decimal::MAX / Decimal::TWO));
+
+        let mut merged = RunningStats::new(None);
+        merged.add(&tick(100));
+        assert!(!merged.overflowed());
+        merged.merge(&stats);
+        assert!(merged.overflowed());
+        assert_eq!(merged.count(), 4);
+    }
+
+    #[test]
+    fn test_quote_stats() {
+        let mut stats = RunningQuoteStats::default();
+        stats.add(&quote(10000, 10010));
+        stats.add(&quote(10020, 10050));
+        stats.add(&quote(10060, 10060));
+
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.crossed_count(), 1);
+        assert_eq!(stats.min_spread(), Some(Decimal::ZERO));
+        assert_eq!(stats.max_spread(), Some(Decimal::new(30, 2)));
+        assert_eq!(
+            stats.avg_spread(),
+            Some(Decimal::new(40, 2) / Decimal::from(3))
+        );
+        assert_eq!(stats.last().unwrap().mid_price(), Decimal::new(10060, 2));
+    }
+}
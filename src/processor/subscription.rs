@@ -0,0 +1,329 @@
+use crate::models::MarketTick;
+use crate::processor::delivery::{DeliveryPolicy, DeliveryStats, SubscriberRx};
+use crate::processor::hub::{MarketCommand, MarketUpdate};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+use std::fmt;
+use tokio::sync::mpsc;
+
+/// Where a subscription's stream should begin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartFrom {
+    /// The first tick whose producer sequence number is at least this
+    /// Sequence numbers are per source, so prefer `Timestamp` for multi-source symbols
+    Sequence(u64),
+    /// The first tick stamped at or after this time
+    Timestamp(DateTime<Utc>),
+}
+
+impl StartFrom {
+    /// Whether `tick` is at or past this starting point
+    pub fn includes(&self, tick: &MarketTick) -> bool {
+        match *self {
+            StartFrom::Sequence(n) => tick.sequence.is_some_and(|seq| seq >= n),
+            StartFrom::Timestamp(t) => tick.timestamp >= t,
+        }
+    }
+}
+
+/// Which symbols a subscription receives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolPattern {
+    Exact(String),
+    Prefix(String),
+    /// `*` matches any run of characters, `?` exactly one
+    Glob(String),
+    Set(BTreeSet<String>),
+    All,
+}
+
+impl SymbolPattern {
+    pub fn matches(&self, symbol: &str) -> bool {
+        match self {
+            SymbolPattern::Exact(s) => s == symbol,
+            SymbolPattern::Prefix(prefix) => symbol.starts_with(prefix.as_str()),
+            SymbolPattern::Glob(glob) => glob_matches(glob.as_bytes(), symbol.as_bytes()),
+            SymbolPattern::Set(symbols) => symbols.contains(symbol),
+            SymbolPattern::All => true,
+        }
+    }
+}
+
+fn glob_matches(glob: &[u8], text: &[u8]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    // The last `*` seen and where in the text its match currently ends
+    let mut star = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            // Mismatch: let the last `*` swallow one more character and retry after it
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    star = Some((star_g, star_t + 1));
+                    g = star_g + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == b'*')
+}
+
+/// A condition a tick must meet to be delivered, checked by the hub before sending
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TickFilter {
+    /// Only ticks where `MarketTick::is_significant_volume` holds
+    SignificantVolume,
+    MinVolume(u64),
+    /// Inclusive price range; either side may be open
+    PriceBand {
+        min: Option<Decimal>,
+        max: Option<Decimal>,
+    },
+    /// Price moved at least this many percent from the last delivered tick
+    PercentChange(Decimal),
+    /// Every nth tick that passes the other filters, starting with the first
+    EveryNth(u64),
+}
+
+/// A subscription's filters plus the state the stateful ones need, for a single symbol
+#[derive(Debug, Clone, Default)]
+pub struct TickFilters {
+    filters: Vec<TickFilter>,
+    last_delivered: Option<Decimal>,
+    passed: u64,
+}
+
+impl TickFilters {
+    pub fn new(filters: Vec<TickFilter>) -> Self {
+        TickFilters {
+            filters,
+            ..Default::default()
+        }
+    }
+
+    /// Whether `tick` should be delivered; all filters must pass
+    pub fn accept(&mut self, tick: &MarketTick) -> bool {
+        if self.filters.is_empty() {
+            return true;
+        }
+        let mut sample_every = None;
+        for filter in &self.filters {
+            let pass = match *filter {
+                TickFilter::SignificantVolume => tick.is_significant_volume(),
+                TickFilter::MinVolume(min) => tick.volume >= min,
+                TickFilter::PriceBand { min, max } => {
+                    min.is_none_or(|min| tick.price >= min)
+                        && max.is_none_or(|max| tick.price <= max)
+                }
+                TickFilter::PercentChange(pct) => match self.last_delivered {
+                    // A move too large to represent is certainly big enough
+                    Some(last) if !last.is_zero() => (tick.price - last)
+                        .checked_div(last)
+                        .and_then(|change| change.abs().checked_mul(Decimal::ONE_HUNDRED))
+                        .is_none_or(|change| change >= pct),
+                    _ => true,
+                },
+                // Sampling counts only ticks that pass everything else
+                TickFilter::EveryNth(n) => {
+                    sample_every = Some(n.max(1));
+                    true
+                }
+            };
+            if !pass {
+                return false;
+            }
+        }
+        if let Some(n) = sample_every {
+            self.passed += 1;
+            if !(self.passed - 1).is_multiple_of(n) {
+                return false;
+            }
+        }
+        self.last_delivered = Some(tick.price);
+        true
+    }
+}
+
+/// Everything a client can ask for when subscribing to ticks
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionRequest {
+    pub symbols: SymbolPattern,
+    /// `None` for live ticks only; replay needs an exact symbol
+    pub start: Option<StartFrom>,
+    pub filters: Vec<TickFilter>,
+    pub delivery: DeliveryPolicy,
+}
+
+impl SubscriptionRequest {
+    pub fn new(symbol: impl Into<String>) -> Self {
+        Self::matching(SymbolPattern::Exact(symbol.into()))
+    }
+
+    /// Every symbol matching `symbols`, including ones the hub has not seen yet
+    pub fn matching(symbols: SymbolPattern) -> Self {
+        SubscriptionRequest {
+            symbols,
+            start: None,
+            filters: Vec::new(),
+            delivery: DeliveryPolicy::Blocking,
+        }
+    }
+
+    /// What to do when this subscriber falls behind; `Blocking` unless set
+    pub fn with_delivery(mut self, policy: DeliveryPolicy) -> Self {
+        self.delivery = policy;
+        self
+    }
+
+    /// Only deliver ticks passing `filter`, as well as any filters already added
+    pub fn with_filter(mut self, filter: TickFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Replay history from sequence number `sequence` before switching to live ticks
+    pub fn from_sequence(mut self, sequence: u64) -> Self {
+        self.start = Some(StartFrom::Sequence(sequence));
+        self
+    }
+
+    /// Replay history from `timestamp` before switching to live ticks
+    pub fn from_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.start = Some(StartFrom::Timestamp(timestamp));
+        self
+    }
+}
+
+/// Identifies one tick subscription within a hub
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(pub u64);
+
+impl fmt::Display for SubscriptionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A live tick subscription; dropping it unsubscribes
+pub struct Subscription {
+    id: SubscriptionId,
+    rx: SubscriberRx,
+    commands: mpsc::Sender<MarketCommand>,
+}
+
+impl Subscription {
+    pub(crate) fn new(
+        id: SubscriptionId,
+        rx: SubscriberRx,
+        commands: mpsc::Sender<MarketCommand>,
+    ) -> Self {
+        Subscription { id, rx, commands }
+    }
+
+    pub fn id(&self) -> SubscriptionId {
+        self.id
+    }
+
+    /// Next update, `None` once the hub has dropped this subscription
+    pub async fn recv(&mut self) -> Option<MarketUpdate> {
+        self.rx.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Result<MarketUpdate, mpsc::error::TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    /// Updates dropped or conflated so far; always zero for `Blocking` subscriptions
+    pub fn delivery_stats(&self) -> DeliveryStats {
+        self.rx.stats()
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.rx.close();
+        // Best effort: if the command queue is full the hub still drops the closed
+        // channel the next time it sends to it
+        let _ = self
+            .commands
+            .try_send(MarketCommand::UnsubscribeById(self.id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_patterns() {
+        let glob = SymbolPattern::Glob("BR?.*".to_string());
+        assert!(glob.matches("BRK.A"));
+        assert!(glob.matches("BRK."));
+        assert!(!glob.matches("BRKA"));
+        assert!(!glob.matches("BR.A"));
+        assert!(SymbolPattern::Glob("*".to_string()).matches(""));
+        assert!(SymbolPattern::Glob("*A*B?".to_string()).matches("XAYAB1"));
+        // Backtracking over many stars would take exponential time on this
+        let stars = SymbolPattern::Glob("*".repeat(30) + "B");
+        assert!(!stars.matches(&"A".repeat(60)));
+
+        assert!(SymbolPattern::Prefix("ES".to_string()).matches("ESZ5"));
+        assert!(!SymbolPattern::Prefix("ES".to_string()).matches("NQZ5"));
+        let set = SymbolPattern::Set(["AAPL".to_string(), "MSFT".to_string()].into());
+        assert!(set.matches("MSFT") && !set.matches("TSLA"));
+        assert!(SymbolPattern::All.matches("ANYTHING"));
+    }
+
+    #[test]
+    fn test_filters() {
+        let tick = |cents: i64, volume: u64| {
+            MarketTick::new("AAPL".to_string(), Decimal::new(cents, 2), volume)
+        };
+        let mut filters = TickFilters::new(vec![
+            TickFilter::MinVolume(100),
+            TickFilter::PriceBand {
+                min: Some(Decimal::new(9000, 2)),
+                max: None,
+            },
+            TickFilter::PercentChange(Decimal::ONE),
+        ]);
+        assert!(filters.accept(&tick(10000, 100)));
+        assert!(!filters.accept(&tick(10000, 99)));
+        assert!(!filters.accept(&tick(8000, 500)));
+        // Under 1% from the last delivered 100.00
+        assert!(!filters.accept(&tick(10099, 500)));
+        assert!(filters.accept(&tick(9900, 500)));
+
+        let mut extreme = TickFilters::new(vec![TickFilter::PercentChange(Decimal::ONE)]);
+        let priced = |price| MarketTick::new("AAPL".to_string(), price, 1);
+        assert!(extreme.accept(&priced(Decimal::new(1, 28))));
+        // The change overflows a Decimal
+        assert!(extreme.accept(&priced(Decimal::MAX / Decimal::TWO)));
+
+        let mut sampled =
+            TickFilters::new(vec![TickFilter::SignificantVolume, TickFilter::EveryNth(2)]);
+        let delivered: Vec<_> = [2000, 10, 2000, 2000, 2000]
+            .into_iter()
+            .map(|volume| sampled.accept(&tick(10000, volume)))
+            .collect();
+        assert_eq!(delivered, [true, false, false, true, false]);
+    }
+}
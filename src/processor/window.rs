@@ -0,0 +1,300 @@
+use crate::models::MarketTick;
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::prelude::*;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Longest time window that can be tracked
+pub const MAX_WINDOW_SECS: u64 = 7 * 24 * 60 * 60;
+/// Ticks kept per symbol in any one window; a time window that sees more keeps only the latest
+pub const MAX_WINDOW_ENTRIES: usize = 10_000;
+/// Largest tick-count window that can be tracked
+pub const MAX_WINDOW_TICKS: usize = MAX_WINDOW_ENTRIES;
+/// Windows tracked at once, configured ones included
+pub const MAX_TRACKED_WINDOWS: usize = 16;
+
+/// How far back a rolling window reaches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatsWindow {
+    /// Ticks whose timestamp is within the last N seconds
+    Seconds(u64),
+    /// The last N ticks
+    Ticks(usize),
+}
+
+impl StatsWindow {
+    /// Reject windows longer than `MAX_WINDOW_SECS` or `MAX_WINDOW_TICKS`
+    pub fn validate(self) -> Result<Self, WindowError> {
+        match self {
+            StatsWindow::Seconds(secs) if secs > MAX_WINDOW_SECS => Err(WindowError::TooLong(self)),
+            StatsWindow::Ticks(n) if n > MAX_WINDOW_TICKS => Err(WindowError::TooLong(self)),
+            _ => Ok(self),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowError {
+    TooLong(StatsWindow),
+    /// Already tracking `MAX_TRACKED_WINDOWS` other windows
+    TooManyWindows,
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::TooLong(window) => write!(f, "window {window:?} is too long"),
+            WindowError::TooManyWindows => {
+                write!(f, "already tracking {MAX_TRACKED_WINDOWS} windows")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+/// Statistics over a rolling window for one symbol
+#[derive(Debug, Clone)]
+pub struct WindowStats {
+    pub symbol: String,
+    pub window: StatsWindow,
+    pub count: usize,
+    pub min_price: Decimal,
+    pub max_price: Decimal,
+    pub avg_price: Decimal,
+    pub vwap: Option<Decimal>,
+    pub std_dev: Decimal,
+    pub total_volume: u64,
+    /// The window held more than `MAX_WINDOW_ENTRIES` ticks; these cover only the most recent
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+struct WindowEntry {
+    id: u64,
+    timestamp: DateTime<Utc>,
+    price: Decimal,
+    volume: u64,
+}
+
+/// Incrementally maintained statistics over a rolling window
+///
+/// Sums are updated on every push and eviction, and min/max use monotonic
+/// queues, so each tick costs amortised O(1) regardless of window size.
+/// At most `MAX_WINDOW_ENTRIES` ticks are retained, whatever the window's duration.
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    window: StatsWindow,
+    entries: VecDeque<WindowEntry>,
+    next_id: u64,
+    sum: Decimal,
+    sum_squares: Decimal,
+    total_volume: u64,
+    notional: Decimal,
+    // Candidates for min (increasing prices) and max (decreasing prices), by entry id
+    min_queue: VecDeque<(u64, Decimal)>,
+    max_queue: VecDeque<(u64, Decimal)>,
+    // Timestamp of the newest tick evicted to stay under MAX_WINDOW_ENTRIES while still in the window
+    capped_at: Option<DateTime<Utc>>,
+}
+
+impl RollingWindow {
+    pub fn new(window: StatsWindow) -> Self {
+        RollingWindow {
+            window,
+            entries: VecDeque::new(),
+            next_id: 0,
+            sum: Decimal::ZERO,
+            sum_squares: Decimal::ZERO,
+            total_volume: 0,
+            notional: Decimal::ZERO,
+            min_queue: VecDeque::new(),
+            max_queue: VecDeque::new(),
+            capped_at: None,
+        }
+    }
+
+    pub fn push(&mut self, tick: &MarketTick) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.sum += tick.price;
+        self.sum_squares += tick.price * tick.price;
+        self.total_volume += tick.volume;
+        self.notional += tick.price * Decimal::from(tick.volume);
+
+        while self.min_queue.back().is_some_and(|(_, p)| *p >= tick.price) {
+            self.min_queue.pop_back();
+        }
+        self.min_queue.push_back((id, tick.price));
+        while self.max_queue.back().is_some_and(|(_, p)| *p <= tick.price) {
+            self.max_queue.pop_back();
+        }
+        self.max_queue.push_back((id, tick.price));
+
+        self.entries.push_back(WindowEntry {
+            id,
+            timestamp: tick.timestamp,
+            price: tick.price,
+            volume: tick.volume,
+        });
+
+        match self.window {
+            StatsWindow::Ticks(n) => {
+                while self.entries.len() > n {
+                    self.evict_front();
+                }
+            }
+            StatsWindow::Seconds(_) => self.expire(tick.timestamp),
+        }
+        while self.entries.len() > MAX_WINDOW_ENTRIES {
+            if let Some(entry) = self.evict_front() {
+                self.capped_at = self.capped_at.max(Some(entry.timestamp));
+            }
+        }
+    }
+
+    /// Drop entries older than the window as of `now`; a no-op for tick-count windows
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        let StatsWindow::Seconds(secs) = self.window else {
+            return;
+        };
+        let cutoff = i64::try_from(secs)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .and_then(|window| now.checked_sub_signed(window));
+        // Reaching back past the earliest representable time, nothing is old enough to drop
+        let Some(cutoff) = cutoff else {
+            return;
+        };
+        while self.entries.front().is_some_and(|e| e.timestamp <= cutoff) {
+            self.evict_front();
+        }
+        if self.capped_at.is_some_and(|t| t <= cutoff) {
+            self.capped_at = None;
+        }
+    }
+
+    fn evict_front(&mut self) -> Option<WindowEntry> {
+        let entry = self.entries.pop_front()?;
+        self.sum -= entry.price;
+        self.sum_squares -= entry.price * entry.price;
+        self.total_volume -= entry.volume;
+        self.notional -= entry.price * Decimal::from(entry.volume);
+        if self
+            .min_queue
+            .front()
+            .is_some_and(|(id, _)| *id == entry.id)
+        {
+            self.min_queue.pop_front();
+        }
+        if self
+            .max_queue
+            .front()
+            .is_some_and(|(id, _)| *id == entry.id)
+        {
+            self.max_queue.pop_front();
+        }
+        Some(entry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn statistics(&self, symbol: &str) -> Option<WindowStats> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let count = Decimal::from(self.entries.len());
+        let avg_price = self.sum / count;
+        let variance = (self.sum_squares / count - avg_price * avg_price).max(Decimal::ZERO);
+        let vwap =
+            (self.total_volume > 0).then(|| self.notional / Decimal::from(self.total_volume));
+
+        Some(WindowStats {
+            symbol: symbol.to_string(),
+            window: self.window,
+            count: self.entries.len(),
+            min_price: self.min_queue.front()?.1,
+            max_price: self.max_queue.front()?.1,
+            avg_price,
+            vwap,
+            std_dev: variance.sqrt().unwrap_or_default(),
+            total_volume: self.total_volume,
+            truncated: self.capped_at.is_some(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tick_at(secs: i64, price: i64, volume: u64) -> MarketTick {
+        let mut tick = MarketTick::new("AAPL".to_string(), Decimal::from(price), volume);
+        tick.timestamp = Utc.timestamp_opt(secs, 0).unwrap();
+        tick
+    }
+
+    #[test]
+    fn test_tick_count_window() {
+        let mut window = RollingWindow::new(StatsWindow::Ticks(3));
+        for (i, price) in [5, 1, 9, 4, 6].into_iter().enumerate() {
+            window.push(&tick_at(i as i64, price, 10));
+        }
+        let stats = window.statistics("AAPL").unwrap();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.min_price, Decimal::from(4));
+        assert_eq!(stats.max_price, Decimal::from(9));
+        assert_eq!(stats.avg_price, Decimal::from(19) / Decimal::from(3));
+        assert_eq!(stats.total_volume, 30);
+    }
+
+    #[test]
+    fn test_time_window_eviction() {
+        let mut window = RollingWindow::new(StatsWindow::Seconds(60));
+        window.push(&tick_at(0, 100, 1));
+        window.push(&tick_at(30, 50, 3));
+        window.push(&tick_at(70, 80, 1));
+
+        let stats = window.statistics("AAPL").unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.max_price, Decimal::from(80));
+        assert_eq!(stats.vwap, Some(Decimal::new(575, 1)));
+
+        window.expire(Utc.timestamp_opt(200, 0).unwrap());
+        assert!(window.is_empty());
+        assert!(window.statistics("AAPL").is_none());
+
+        // Far too long to ever be tracked, but expiring must not overflow either
+        let huge = StatsWindow::Seconds(u64::MAX);
+        assert_eq!(huge.validate(), Err(WindowError::TooLong(huge)));
+        let mut window = RollingWindow::new(huge);
+        window.push(&tick_at(0, 100, 1));
+        window.expire(DateTime::<Utc>::MAX_UTC);
+        assert_eq!(window.len(), 1);
+    }
+
+    #[test]
+    fn test_time_window_retains_bounded_entries() {
+        let mut window = RollingWindow::new(StatsWindow::Seconds(60));
+        for i in 0..MAX_WINDOW_ENTRIES + 5 {
+            window.push(&tick_at(10, i as i64, 1));
+        }
+        assert_eq!(window.len(), MAX_WINDOW_ENTRIES);
+        let stats = window.statistics("AAPL").unwrap();
+        assert!(stats.truncated);
+        assert_eq!(stats.min_price, Decimal::from(5));
+
+        // Once the capped ticks would have aged out anyway, the window is complete again
+        window.push(&tick_at(75, 1, 1));
+        assert_eq!(window.len(), 1);
+        assert!(!window.statistics("AAPL").unwrap().truncated);
+    }
+}
@@ -0,0 +1,643 @@
+use crate::models::{MarketTick, TradingSession};
+use chrono::DateTime;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Stream header: magic bytes, then a version byte and the price scale
+const MAGIC: &[u8; 4] = b"MTCK";
+pub const CODEC_VERSION: u8 = 2;
+/// Default decimal places for fixed-point prices
+pub const DEFAULT_PRICE_SCALE: u32 = 8;
+const MAX_PRICE_SCALE: u32 = 18;
+/// Size of the stream header; the first frame starts at this offset
+pub const HEADER_LEN: u64 = 6;
+
+const FRAME_SYMBOL: u8 = 1;
+const FRAME_TICK: u8 = 2;
+const FRAME_SOURCE: u8 = 3;
+// Version 1: kind + symbol id + price + volume + timestamp + session
+const V1_TICK_FRAME_LEN: usize = 1 + 4 + 8 + 8 + 8 + 1;
+// then source id + sequence flag + sequence
+const TICK_FRAME_LEN: usize = V1_TICK_FRAME_LEN + 4 + 1 + 8;
+// Symbols and sources are short names; anything longer is treated as corrupt input
+const MAX_SYMBOL_LEN: usize = u8::MAX as usize;
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    /// Stream does not start with the codec's magic bytes
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// Price has more decimal places than the stream's scale, or does not fit in an i64
+    PriceOutOfRange(Decimal),
+    /// Timestamp outside the range of i64 nanoseconds (roughly 1677-2262)
+    TimestampOutOfRange,
+    SymbolTooLong(String),
+    SourceTooLong(String),
+    /// Tick frame refers to a symbol id that was never defined
+    UnknownSymbol(u32),
+    /// Frame is malformed; the stream cannot be resynchronised
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "io error: {e}"),
+            CodecError::BadMagic => write!(f, "not a tick stream"),
+            CodecError::UnsupportedVersion(v) => write!(f, "unsupported codec version {v}"),
+            CodecError::PriceOutOfRange(price) => {
+                write!(
+                    f,
+                    "price {price} does not fit the stream's fixed-point scale"
+                )
+            }
+            CodecError::TimestampOutOfRange => write!(f, "timestamp out of nanosecond range"),
+            CodecError::SymbolTooLong(symbol) => write!(f, "symbol {symbol} is too long"),
+            CodecError::SourceTooLong(source) => write!(f, "source {source} is too long"),
+            CodecError::UnknownSymbol(id) => write!(f, "symbol id {id} used before definition"),
+            CodecError::Corrupt(reason) => write!(f, "corrupt frame: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<io::Error> for CodecError {
+    fn from(e: io::Error) -> Self {
+        CodecError::Io(e)
+    }
+}
+
+impl From<CodecError> for io::Error {
+    fn from(e: CodecError) -> Self {
+        match e {
+            CodecError::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+fn session_byte(session: Option<TradingSession>) -> u8 {
+    match session {
+        None => 0,
+        Some(TradingSession::PreMarket) => 1,
+        Some(TradingSession::Regular) => 2,
+        Some(TradingSession::PostMarket) => 3,
+    }
+}
+
+fn session_from_byte(byte: u8) -> Result<Option<TradingSession>, CodecError> {
+    match byte {
+        0 => Ok(None),
+        1 => Ok(Some(TradingSession::PreMarket)),
+        2 => Ok(Some(TradingSession::Regular)),
+        3 => Ok(Some(TradingSession::PostMarket)),
+        _ => Err(CodecError::Corrupt("unknown session")),
+    }
+}
+
+/// Writes ticks as a versioned stream of length-prefixed frames
+///
+/// Each frame is a `u16` little-endian length followed by a kind byte. The
+/// first tick for a symbol is preceded by a symbol frame assigning it an id;
+/// tick frames then carry the id, the price as an `i64` at the stream's
+/// fixed-point scale, volume, nanosecond timestamp, session, source id and
+/// sequence number. Sources are given ids by their own frames, like symbols.
+pub struct StreamEncoder<W: Write> {
+    writer: W,
+    price_scale: u32,
+    symbols: HashMap<String, u32>,
+    sources: HashMap<String, u32>,
+    frame: Vec<u8>,
+    bytes_written: u64,
+}
+
+impl<W: Write> StreamEncoder<W> {
+    /// Write the stream header with the default price scale
+    pub fn new(writer: W) -> Result<Self, CodecError> {
+        Self::with_price_scale(writer, DEFAULT_PRICE_SCALE)
+    }
+
+    /// Write the stream header; prices are stored as integer multiples of 10^-`price_scale`
+    pub fn with_price_scale(mut writer: W, price_scale: u32) -> Result<Self, CodecError> {
+        let price_scale = price_scale.min(MAX_PRICE_SCALE);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[CODEC_VERSION, price_scale as u8])?;
+        Ok(StreamEncoder {
+            writer,
+            price_scale,
+            symbols: HashMap::new(),
+            sources: HashMap::new(),
+            frame: Vec::with_capacity(TICK_FRAME_LEN),
+            bytes_written: HEADER_LEN,
+        })
+    }
+
+    pub fn encode(&mut self, tick: &MarketTick) -> Result<(), CodecError> {
+        let mut price = tick.price;
+        price.rescale(self.price_scale);
+        if price != tick.price {
+            return Err(CodecError::PriceOutOfRange(tick.price));
+        }
+        let mantissa =
+            i64::try_from(price.mantissa()).map_err(|_| CodecError::PriceOutOfRange(tick.price))?;
+        let nanos = tick
+            .timestamp
+            .timestamp_nanos_opt()
+            .ok_or(CodecError::TimestampOutOfRange)?;
+        let symbol_id = self.intern(FRAME_SYMBOL, &tick.symbol)?;
+        // 0 is no source, so defined sources are stored as id + 1
+        let source_id = match &tick.source {
+            Some(source) => self.intern(FRAME_SOURCE, source)? + 1,
+            None => 0,
+        };
+
+        self.frame.clear();
+        self.frame.push(FRAME_TICK);
+        self.frame.extend_from_slice(&symbol_id.to_le_bytes());
+        self.frame.extend_from_slice(&mantissa.to_le_bytes());
+        self.frame.extend_from_slice(&tick.volume.to_le_bytes());
+        self.frame.extend_from_slice(&nanos.to_le_bytes());
+        self.frame.push(session_byte(tick.session));
+        self.frame.extend_from_slice(&source_id.to_le_bytes());
+        self.frame.push(tick.sequence.is_some() as u8);
+        self.frame
+            .extend_from_slice(&tick.sequence.unwrap_or_default().to_le_bytes());
+        self.write_frame()
+    }
+
+    /// Bytes written so far, including the header
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn flush(&mut self) -> Result<(), CodecError> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write the definition of every symbol and source so far again, so a decoder that
+    /// seeks straight to this point knows all of their ids
+    pub fn redefine_all(&mut self) -> Result<(), CodecError> {
+        for kind in [FRAME_SYMBOL, FRAME_SOURCE] {
+            let names = match kind {
+                FRAME_SOURCE => &self.sources,
+                _ => &self.symbols,
+            };
+            let mut defined: Vec<(u32, String)> =
+                names.iter().map(|(name, id)| (*id, name.clone())).collect();
+            // Decoders take new ids in order
+            defined.sort_unstable();
+            for (id, name) in defined {
+                self.write_definition(kind, id, &name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Id of a symbol or source (per `kind`), writing its definition frame the first time
+    fn intern(&mut self, kind: u8, name: &str) -> Result<u32, CodecError> {
+        let names = match kind {
+            FRAME_SOURCE => &mut self.sources,
+            _ => &mut self.symbols,
+        };
+        if let Some(id) = names.get(name) {
+            return Ok(*id);
+        }
+        if name.len() > MAX_SYMBOL_LEN {
+            return Err(match kind {
+                FRAME_SOURCE => CodecError::SourceTooLong(name.to_string()),
+                _ => CodecError::SymbolTooLong(name.to_string()),
+            });
+        }
+        let id = names.len() as u32;
+        names.insert(name.to_string(), id);
+        if let Err(e) = self.write_definition(kind, id, name) {
+            // Not defined after all; a retry writes the definition again
+            match kind {
+                FRAME_SOURCE => self.sources.remove(name),
+                _ => self.symbols.remove(name),
+            };
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    fn write_definition(&mut self, kind: u8, id: u32, name: &str) -> Result<(), CodecError> {
+        self.frame.clear();
+        self.frame.push(kind);
+        self.frame.extend_from_slice(&id.to_le_bytes());
+        self.frame.extend_from_slice(name.as_bytes());
+        self.write_frame()
+    }
+
+    fn write_frame(&mut self) -> Result<(), CodecError> {
+        let len = self.frame.len() as u16;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&self.frame)?;
+        self.bytes_written += 2 + self.frame.len() as u64;
+        Ok(())
+    }
+}
+
+/// Reads a stream written by `StreamEncoder`, including version 1 streams, whose
+/// ticks have no source or sequence number
+///
+/// Frames are read into one reusable buffer, and `decode_into` overwrites an
+/// existing tick in place, so steady-state decoding does not allocate. The
+/// only allocations are for each symbol's and source's first definition.
+pub struct StreamDecoder<R: Read> {
+    reader: R,
+    version: u8,
+    price_scale: u32,
+    symbols: Vec<String>,
+    sources: Vec<String>,
+    frame: Vec<u8>,
+    position: u64,
+}
+
+impl<R: Read> StreamDecoder<R> {
+    /// Read and validate the stream header
+    pub fn new(mut reader: R) -> Result<Self, CodecError> {
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(CodecError::BadMagic);
+        }
+        if !(1..=CODEC_VERSION).contains(&header[4]) {
+            return Err(CodecError::UnsupportedVersion(header[4]));
+        }
+        if header[5] as u32 > MAX_PRICE_SCALE {
+            return Err(CodecError::Corrupt("price scale out of range"));
+        }
+        Ok(StreamDecoder {
+            reader,
+            version: header[4],
+            price_scale: header[5] as u32,
+            symbols: Vec::new(),
+            sources: Vec::new(),
+            frame: Vec::with_capacity(TICK_FRAME_LEN),
+            position: HEADER_LEN,
+        })
+    }
+
+    /// Byte offset of the next frame, matching `StreamEncoder::bytes_written` at the time it was written
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Decode the next tick over `tick`, reusing its symbol allocation
+    /// Returns false at a clean end of stream
+    pub fn decode_into(&mut self, tick: &mut MarketTick) -> Result<bool, CodecError> {
+        loop {
+            if !self.read_frame()? {
+                return Ok(false);
+            }
+            match self.frame[0] {
+                FRAME_SYMBOL => self.define(FRAME_SYMBOL)?,
+                FRAME_SOURCE if self.version >= 2 => self.define(FRAME_SOURCE)?,
+                FRAME_TICK => {
+                    self.fill_tick(tick)?;
+                    return Ok(true);
+                }
+                _ => return Err(CodecError::Corrupt("unknown frame kind")),
+            }
+        }
+    }
+
+    /// Decode the next tick into a new `MarketTick`
+    pub fn decode(&mut self) -> Result<Option<MarketTick>, CodecError> {
+        let mut tick =
+            MarketTick::with_timestamp(String::new(), Decimal::ZERO, 0, DateTime::UNIX_EPOCH);
+        Ok(self.decode_into(&mut tick)?.then_some(tick))
+    }
+
+    fn read_frame(&mut self) -> Result<bool, CodecError> {
+        // Only a stream ending exactly on a frame boundary is a clean end
+        let mut len = [0u8; 2];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.reader.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(CodecError::Corrupt("truncated frame length")),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let len = u16::from_le_bytes(len) as usize;
+        if len == 0 {
+            return Err(CodecError::Corrupt("empty frame"));
+        }
+        self.frame.resize(len, 0);
+        self.reader.read_exact(&mut self.frame)?;
+        self.position += 2 + len as u64;
+        Ok(true)
+    }
+
+    fn define(&mut self, kind: u8) -> Result<(), CodecError> {
+        let id = u32::from_le_bytes(field(&self.frame, 1)?) as usize;
+        let name = std::str::from_utf8(&self.frame[5..])
+            .map_err(|_| CodecError::Corrupt("name is not utf-8"))?;
+        let names = match kind {
+            FRAME_SOURCE => &mut self.sources,
+            _ => &mut self.symbols,
+        };
+        match names.get(id) {
+            // Seen again after seeking back over its definition
+            Some(known) if known == name => Ok(()),
+            None if id == names.len() => {
+                names.push(name.to_string());
+                Ok(())
+            }
+            _ => Err(CodecError::Corrupt("ids out of order")),
+        }
+    }
+
+    fn fill_tick(&self, tick: &mut MarketTick) -> Result<(), CodecError> {
+        let expected_len = match self.version {
+            1 => V1_TICK_FRAME_LEN,
+            _ => TICK_FRAME_LEN,
+        };
+        if self.frame.len() != expected_len {
+            return Err(CodecError::Corrupt("tick frame has wrong length"));
+        }
+        let id = u32::from_le_bytes(field(&self.frame, 1)?);
+        let symbol = self
+            .symbols
+            .get(id as usize)
+            .ok_or(CodecError::UnknownSymbol(id))?;
+        let mantissa = i64::from_le_bytes(field(&self.frame, 5)?);
+
+        tick.symbol.clear();
+        tick.symbol.push_str(symbol);
+        tick.price = Decimal::new(mantissa, self.price_scale).normalize();
+        tick.volume = u64::from_le_bytes(field(&self.frame, 13)?);
+        tick.timestamp =
+            DateTime::from_timestamp_nanos(i64::from_le_bytes(field(&self.frame, 21)?));
+        tick.session = session_from_byte(self.frame[29])?;
+        if self.version == 1 {
+            tick.source = None;
+            tick.sequence = None;
+            return Ok(());
+        }
+
+        match u32::from_le_bytes(field(&self.frame, 30)?) {
+            0 => tick.source = None,
+            id => {
+                let source = self
+                    .sources
+                    .get(id as usize - 1)
+                    .ok_or(CodecError::Corrupt("source id used before definition"))?;
+                match &mut tick.source {
+                    Some(existing) => {
+                        existing.clear();
+                        existing.push_str(source);
+                    }
+                    None => tick.source = Some(source.clone()),
+                }
+            }
+        }
+        let sequence = u64::from_le_bytes(field(&self.frame, 35)?);
+        tick.sequence = match self.frame[34] {
+            0 => None,
+            1 => Some(sequence),
+            _ => return Err(CodecError::Corrupt("unknown sequence flag")),
+        };
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> StreamDecoder<R> {
+    /// Continue decoding from a frame boundary, e.g. an offset recorded in an index
+    /// Symbols and sources defined before `offset` must already have been read, or be
+    /// defined again at `offset` by `StreamEncoder::redefine_all`
+    pub fn seek(&mut self, offset: u64) -> Result<(), CodecError> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.position = offset;
+        Ok(())
+    }
+}
+
+fn field<const N: usize>(frame: &[u8], at: usize) -> Result<[u8; N], CodecError> {
+    frame
+        .get(at..at + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(CodecError::Corrupt("frame too short"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn session() -> impl Strategy<Value = Option<TradingSession>> {
+        prop_oneof![
+            Just(None),
+            Just(Some(TradingSession::PreMarket)),
+            Just(Some(TradingSession::Regular)),
+            Just(Some(TradingSession::PostMarket)),
+        ]
+    }
+
+    prop_compose! {
+        fn tick()(
+            symbol in "[A-Z]{1,5}(\\.[A-Z])?",
+            mantissa in 0i64..1_000_000_000_000,
+            // Up to $10bn, comfortably inside an i64 at the default scale
+            scale in 2u32..=DEFAULT_PRICE_SCALE,
+            volume in any::<u64>(),
+            nanos in any::<i64>(),
+            session in session(),
+            source in prop::option::of("[a-z]{1,8}"),
+            sequence in prop::option::of(any::<u64>()),
+        ) -> MarketTick {
+            let mut tick = MarketTick::with_timestamp(
+                symbol,
+                Decimal::new(mantissa, scale),
+                volume,
+                DateTime::from_timestamp_nanos(nanos),
+            );
+            tick.session = session;
+            tick.source = source;
+            tick.sequence = sequence;
+            tick
+        }
+    }
+
+    fn encode_all(ticks: &[MarketTick]) -> Vec<u8> {
+        let mut encoder = StreamEncoder::new(Vec::new()).unwrap();
+        for tick in ticks {
+            encoder.encode(tick).unwrap();
+        }
+        encoder.into_inner()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(ticks in prop::collection::vec(tick(), 0..50)) {
+            let bytes = encode_all(&ticks);
+            let mut decoder = StreamDecoder::new(bytes.as_slice()).unwrap();
+            for expected in &ticks {
+                let decoded = decoder.decode().unwrap().unwrap();
+                prop_assert_eq!(&decoded.symbol, &expected.symbol);
+                prop_assert_eq!(decoded.price, expected.price);
+                prop_assert_eq!(decoded.volume, expected.volume);
+                prop_assert_eq!(decoded.timestamp, expected.timestamp);
+                prop_assert_eq!(decoded.session, expected.session);
+                prop_assert_eq!(&decoded.source, &expected.source);
+                prop_assert_eq!(decoded.sequence, expected.sequence);
+            }
+            prop_assert!(decoder.decode().unwrap().is_none());
+        }
+
+        #[test]
+        fn prop_truncated_stream_errors(ticks in prop::collection::vec(tick(), 1..10), cut in 1..2 + TICK_FRAME_LEN) {
+            // Cutting into the last tick frame must be an error, never a silently shorter stream
+            let bytes = encode_all(&ticks);
+            let mut decoder = StreamDecoder::new(&bytes[..bytes.len() - cut]).unwrap();
+            for _ in 1..ticks.len() {
+                prop_assert!(decoder.decode().unwrap().is_some());
+            }
+            prop_assert!(decoder.decode().is_err());
+        }
+    }
+
+    #[test]
+    fn test_symbols_are_interned() {
+        let tick = |symbol: &str| {
+            MarketTick::with_timestamp(
+                symbol.to_string(),
+                Decimal::new(18712, 2),
+                100,
+                DateTime::UNIX_EPOCH,
+            )
+        };
+        let one = encode_all(&[tick("AAPL")]);
+        let two = encode_all(&[tick("AAPL"), tick("AAPL")]);
+        // The second AAPL tick is just a tick frame, with no symbol definition
+        assert_eq!(two.len() - one.len(), 2 + TICK_FRAME_LEN);
+
+        let mut decoder = StreamDecoder::new(two.as_slice()).unwrap();
+        let mut reused = tick("");
+        assert!(decoder.decode_into(&mut reused).unwrap());
+        let capacity = reused.symbol.capacity();
+        assert!(decoder.decode_into(&mut reused).unwrap());
+        assert_eq!(reused.symbol, "AAPL");
+        assert_eq!(reused.symbol.capacity(), capacity);
+        assert!(!decoder.decode_into(&mut reused).unwrap());
+    }
+
+    #[test]
+    fn test_seek_to_redefined_ids() {
+        let tick = |symbol: &str, source: Option<&str>| {
+            let mut tick = MarketTick::with_timestamp(
+                symbol.to_string(),
+                Decimal::new(18712, 2),
+                100,
+                DateTime::UNIX_EPOCH,
+            );
+            tick.source = source.map(str::to_string);
+            tick
+        };
+        let mut encoder = StreamEncoder::new(io::Cursor::new(Vec::new())).unwrap();
+        encoder.encode(&tick("AAPL", None)).unwrap();
+        encoder.encode(&tick("MSFT", Some("feed"))).unwrap();
+        let offset = encoder.bytes_written();
+        encoder.redefine_all().unwrap();
+        encoder.encode(&tick("MSFT", Some("feed"))).unwrap();
+        encoder.encode(&tick("AAPL", Some("backup"))).unwrap();
+
+        let mut decoder =
+            StreamDecoder::new(io::Cursor::new(encoder.into_inner().into_inner())).unwrap();
+        decoder.seek(offset).unwrap();
+        let msft = decoder.decode().unwrap().unwrap();
+        assert_eq!(
+            (msft.symbol.as_str(), msft.source.as_deref()),
+            ("MSFT", Some("feed"))
+        );
+        let aapl = decoder.decode().unwrap().unwrap();
+        assert_eq!(
+            (aapl.symbol.as_str(), aapl.source.as_deref()),
+            ("AAPL", Some("backup"))
+        );
+        // Reading from the start takes the repeated definitions in its stride
+        decoder.seek(HEADER_LEN).unwrap();
+        let mut count = 0;
+        while decoder.decode().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_reads_version_1_streams() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 2]);
+        let mut frame = |body: &[u8]| {
+            bytes.extend_from_slice(&(body.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(body);
+        };
+        frame(&[&[FRAME_SYMBOL][..], &0u32.to_le_bytes(), b"AAPL"].concat());
+        frame(
+            &[
+                &[FRAME_TICK][..],
+                &0u32.to_le_bytes(),
+                &18712i64.to_le_bytes(),
+                &100u64.to_le_bytes(),
+                &0i64.to_le_bytes(),
+                &[2],
+            ]
+            .concat(),
+        );
+
+        let tick = StreamDecoder::new(bytes.as_slice())
+            .unwrap()
+            .decode()
+            .unwrap()
+            .unwrap();
+        assert_eq!(tick.price, Decimal::new(18712, 2));
+        assert_eq!(tick.session, Some(TradingSession::Regular));
+        assert_eq!((tick.source, tick.sequence), (None, None));
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let mut encoder = StreamEncoder::with_price_scale(Vec::new(), 2).unwrap();
+        let too_precise = MarketTick::with_timestamp(
+            "AAPL".to_string(),
+            Decimal::new(1, 3),
+            1,
+            DateTime::UNIX_EPOCH,
+        );
+        assert!(matches!(
+            encoder.encode(&too_precise),
+            Err(CodecError::PriceOutOfRange(_))
+        ));
+        let too_large = MarketTick {
+            price: Decimal::from(i64::MAX),
+            ..too_precise
+        };
+        assert!(matches!(
+            encoder.encode(&too_large),
+            Err(CodecError::PriceOutOfRange(_))
+        ));
+
+        assert!(matches!(
+            StreamDecoder::new(&b"JSON{}"[..]),
+            Err(CodecError::BadMagic)
+        ));
+        let mut future = encode_all(&[]);
+        future[4] = CODEC_VERSION + 1;
+        assert!(matches!(
+            StreamDecoder::new(future.as_slice()),
+            Err(CodecError::UnsupportedVersion(_))
+        ));
+    }
+}
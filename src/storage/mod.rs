@@ -0,0 +1,13 @@
+//! Persistence for market data
+
+pub mod codec;
+pub mod recorder;
+pub mod snapshot;
+pub mod tick_store;
+pub mod wal;
+
+pub use codec::*;
+pub use recorder::*;
+pub use snapshot::*;
+pub use tick_store::*;
+pub use wal::*;
@@ -0,0 +1,597 @@
+use crate::models::MarketTick;
+use crate::storage::codec::{StreamDecoder, StreamEncoder};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::{mpsc, watch};
+use tokio::time::{Duration, interval};
+
+const DEFAULT_MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const INDEX_SUFFIX: &str = ".idx.json";
+// Unrecordable ticks logged individually by `run`; the rest are only counted
+const LOGGED_SKIPS: u64 = 10;
+// Ticks `run` collects before handing them to a blocking thread to write
+const WRITE_BATCH: usize = 1024;
+// Ticks `recorded_ticks` reads ahead of its consumer
+const QUERY_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordFormat {
+    /// One JSON-encoded `MarketTick` per line
+    JsonLines,
+    /// `StreamEncoder` binary frames, one stream per file
+    Binary,
+}
+
+impl RecordFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::JsonLines => "jsonl",
+            RecordFormat::Binary => "bin",
+        }
+    }
+}
+
+/// Sidecar index written next to each recording file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingIndex {
+    pub path: PathBuf,
+    pub format: RecordFormat,
+    pub symbols: BTreeSet<String>,
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub tick_count: u64,
+}
+
+impl RecordingIndex {
+    fn new(path: PathBuf, format: RecordFormat) -> Self {
+        RecordingIndex {
+            path,
+            format,
+            symbols: BTreeSet::new(),
+            first_timestamp: None,
+            last_timestamp: None,
+            tick_count: 0,
+        }
+    }
+
+    fn add(&mut self, tick: &MarketTick) {
+        if !self.symbols.contains(&tick.symbol) {
+            self.symbols.insert(tick.symbol.clone());
+        }
+        self.first_timestamp = Some(
+            self.first_timestamp
+                .map_or(tick.timestamp, |t| t.min(tick.timestamp)),
+        );
+        self.last_timestamp = self.last_timestamp.max(Some(tick.timestamp));
+        self.tick_count += 1;
+    }
+
+    /// Whether this file may hold ticks for `symbol` between `from` and `to` inclusive
+    pub fn covers(&self, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        match (self.first_timestamp, self.last_timestamp) {
+            (Some(first), Some(last)) => {
+                self.symbols.contains(symbol) && first <= to && from <= last
+            }
+            _ => false,
+        }
+    }
+}
+
+enum RecordingWriter {
+    JsonLines {
+        writer: BufWriter<File>,
+        bytes_written: u64,
+    },
+    Binary(StreamEncoder<BufWriter<File>>),
+}
+
+impl RecordingWriter {
+    fn write(&mut self, tick: &MarketTick) -> io::Result<()> {
+        match self {
+            RecordingWriter::JsonLines {
+                writer,
+                bytes_written,
+            } => {
+                let mut line = serde_json::to_vec(tick)?;
+                line.push(b'\n');
+                writer.write_all(&line)?;
+                *bytes_written += line.len() as u64;
+            }
+            RecordingWriter::Binary(encoder) => encoder.encode(tick)?,
+        }
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        match self {
+            RecordingWriter::JsonLines { bytes_written, .. } => *bytes_written,
+            RecordingWriter::Binary(encoder) => encoder.bytes_written(),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            RecordingWriter::JsonLines { writer, .. } => writer.flush(),
+            RecordingWriter::Binary(encoder) => Ok(encoder.flush()?),
+        }
+    }
+}
+
+/// The newest tick timestamp per symbol a `TickRecorder` has flushed, so readers of a
+/// recording still being written can tell when it holds the ticks they need
+#[derive(Debug, Clone)]
+pub struct RecordingWatermark(watch::Receiver<HashMap<String, DateTime<Utc>>>);
+
+impl RecordingWatermark {
+    /// Wait until ticks for `symbol` up to `timestamp` can be read back; false if the
+    /// recorder stops first
+    pub async fn wait_for(&mut self, symbol: &str, timestamp: DateTime<Utc>) -> bool {
+        self.0
+            .wait_for(|flushed| flushed.get(symbol).is_some_and(|t| *t >= timestamp))
+            .await
+            .is_ok()
+    }
+}
+
+struct RecordingFile {
+    writer: RecordingWriter,
+    index: RecordingIndex,
+}
+
+/// Appends every tick it is given to rotating files with a per-file index
+///
+/// Files are named `<prefix>-<n>.<jsonl|bin>` with the index alongside as
+/// `<file>.idx.json`. The index is rewritten on every flush, so after a crash
+/// it lags the data file by at most one flush interval.
+pub struct TickRecorder {
+    dir: PathBuf,
+    prefix: String,
+    format: RecordFormat,
+    max_file_bytes: u64,
+    next_file_number: u64,
+    current: Option<RecordingFile>,
+    completed: Vec<RecordingIndex>,
+    skipped: u64,
+    /// Newest timestamp per symbol given to `record` since the last flush
+    unflushed: HashMap<String, DateTime<Utc>>,
+    flushed: watch::Sender<HashMap<String, DateTime<Utc>>>,
+}
+
+impl TickRecorder {
+    /// JSON Lines files named `ticks-*`, rotated at 64 MiB
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        TickRecorder {
+            dir: dir.into(),
+            prefix: "ticks".to_string(),
+            format: RecordFormat::JsonLines,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            next_file_number: 0,
+            current: None,
+            completed: Vec::new(),
+            skipped: 0,
+            unflushed: HashMap::new(),
+            flushed: watch::Sender::new(HashMap::new()),
+        }
+    }
+
+    /// Follows which ticks this recorder has flushed, for as long as it is recording
+    pub fn watermark(&self) -> RecordingWatermark {
+        RecordingWatermark(self.flushed.subscribe())
+    }
+
+    pub fn with_format(mut self, format: RecordFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Start a new file once the current one has reached `bytes`
+    pub fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    pub fn record(&mut self, tick: &MarketTick) -> io::Result<()> {
+        let needs_rotation = self.current.as_ref().is_none_or(|file| {
+            file.index.tick_count > 0 && file.writer.bytes_written() >= self.max_file_bytes
+        });
+        if needs_rotation {
+            self.rotate()?;
+        }
+
+        let file = self.current.as_mut().expect("rotate opens a file");
+        let written = file.writer.write(tick);
+        if written.is_ok() {
+            file.index.add(tick);
+        }
+        // Counted even if it failed to encode, since it will never be written
+        match self.unflushed.get_mut(&tick.symbol) {
+            Some(newest) => *newest = (*newest).max(tick.timestamp),
+            None => {
+                self.unflushed.insert(tick.symbol.clone(), tick.timestamp);
+            }
+        }
+        written
+    }
+
+    /// Flush buffered ticks and rewrite the current file's index
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.current {
+            file.writer.flush()?;
+            write_index(&file.index)?;
+        }
+        self.publish_flushed();
+        Ok(())
+    }
+
+    /// Close the current file and return the index of every file written
+    pub fn finish(mut self) -> io::Result<Vec<RecordingIndex>> {
+        self.close_current()?;
+        Ok(self.completed)
+    }
+
+    /// Record ticks until the channel closes, flushing once a second
+    /// Ticks are written in batches on a blocking thread, so file I/O never stalls an async worker
+    /// Ticks that cannot be encoded are logged and skipped; only I/O errors stop recording
+    pub async fn run(
+        mut self,
+        mut rx: mpsc::Receiver<MarketTick>,
+    ) -> io::Result<Vec<RecordingIndex>> {
+        let mut flush_timer = interval(FLUSH_INTERVAL);
+        let mut batch = Vec::with_capacity(WRITE_BATCH);
+        loop {
+            let flush = tokio::select! {
+                tick = rx.recv() => match tick {
+                    Some(tick) => {
+                        batch.push(tick);
+                        if batch.len() < WRITE_BATCH {
+                            continue;
+                        }
+                        false
+                    }
+                    None => break,
+                },
+                _ = flush_timer.tick() => true,
+            };
+            let ticks = std::mem::replace(&mut batch, Vec::with_capacity(WRITE_BATCH));
+            self = tokio::task::spawn_blocking(move || {
+                self.record_batch(&ticks)?;
+                if flush {
+                    self.flush()?;
+                }
+                Ok::<_, io::Error>(self)
+            })
+            .await
+            .map_err(io::Error::other)??;
+        }
+        tokio::task::spawn_blocking(move || {
+            self.record_batch(&batch)?;
+            if self.skipped > 0 {
+                println!("Skipped {} ticks that could not be recorded", self.skipped);
+            }
+            self.finish()
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    // Record each tick, logging and counting the ones that cannot be encoded
+    fn record_batch(&mut self, ticks: &[MarketTick]) -> io::Result<()> {
+        for tick in ticks {
+            match self.record(tick) {
+                Ok(()) => {}
+                // Encoding errors surface as InvalidData; anything else is the disk
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    self.skipped += 1;
+                    if self.skipped <= LOGGED_SKIPS {
+                        println!("Not recording tick for {}: {e}", tick.symbol);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.close_current()?;
+        fs::create_dir_all(&self.dir)?;
+        // Never overwrite a recording from an earlier run
+        let path = loop {
+            let path = self.dir.join(format!(
+                "{}-{:06}.{}",
+                self.prefix,
+                self.next_file_number,
+                self.format.extension()
+            ));
+            self.next_file_number += 1;
+            if !path.exists() {
+                break path;
+            }
+        };
+        let file = BufWriter::new(File::create(&path)?);
+        let writer = match self.format {
+            RecordFormat::JsonLines => RecordingWriter::JsonLines {
+                writer: file,
+                bytes_written: 0,
+            },
+            RecordFormat::Binary => RecordingWriter::Binary(StreamEncoder::new(file)?),
+        };
+        self.current = Some(RecordingFile {
+            writer,
+            index: RecordingIndex::new(path, self.format),
+        });
+        Ok(())
+    }
+
+    fn close_current(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.current.take() {
+            file.writer.flush()?;
+            write_index(&file.index)?;
+            self.completed.push(file.index);
+        }
+        self.publish_flushed();
+        Ok(())
+    }
+
+    // Advance the watermark past everything recorded since the last flush
+    fn publish_flushed(&mut self) {
+        if self.unflushed.is_empty() {
+            return;
+        }
+        let unflushed = std::mem::take(&mut self.unflushed);
+        self.flushed.send_modify(|flushed| {
+            for (symbol, timestamp) in unflushed {
+                let newest = flushed.entry(symbol).or_insert(timestamp);
+                *newest = (*newest).max(timestamp);
+            }
+        });
+    }
+}
+
+fn index_path(recording: &Path) -> PathBuf {
+    let mut path = recording.as_os_str().to_owned();
+    path.push(INDEX_SUFFIX);
+    PathBuf::from(path)
+}
+
+fn write_index(index: &RecordingIndex) -> io::Result<()> {
+    fs::write(index_path(&index.path), serde_json::to_vec_pretty(index)?)
+}
+
+/// Index of the recording at `path`
+pub fn read_index(path: &Path) -> io::Result<RecordingIndex> {
+    Ok(serde_json::from_slice(&fs::read(index_path(path))?)?)
+}
+
+/// Indexes of the recordings in `dir` that may hold `symbol` ticks between `from` and `to`,
+/// ordered by first timestamp
+pub fn find_recordings(
+    dir: &Path,
+    symbol: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> io::Result<Vec<RecordingIndex>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.to_string_lossy().ends_with(INDEX_SUFFIX) {
+            let index: RecordingIndex = serde_json::from_slice(&fs::read(&path)?)?;
+            if index.covers(symbol, from, to) {
+                found.push(index);
+            }
+        }
+    }
+    found.sort_by_key(|index| index.first_timestamp);
+    Ok(found)
+}
+
+/// Pass every tick in a recording to `f`, in the order it was written, until `f` returns false
+fn scan_recording(index: &RecordingIndex, mut f: impl FnMut(MarketTick) -> bool) -> io::Result<()> {
+    let reader = BufReader::new(File::open(&index.path)?);
+    match index.format {
+        RecordFormat::JsonLines => {
+            for line in reader.lines() {
+                let line = line?;
+                if !line.is_empty() && !f(serde_json::from_str(&line)?) {
+                    break;
+                }
+            }
+        }
+        RecordFormat::Binary => {
+            let mut decoder = StreamDecoder::new(reader)?;
+            while let Some(tick) = decoder.decode()? {
+                if !f(tick) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every tick in a recording, in the order it was written
+pub fn read_recording(index: &RecordingIndex) -> io::Result<Vec<MarketTick>> {
+    let mut ticks = Vec::new();
+    scan_recording(index, |tick| {
+        ticks.push(tick);
+        true
+    })?;
+    Ok(ticks)
+}
+
+/// Stream recorded `symbol` ticks stamped from `from` to `to` inclusive, in recording order
+/// Files are read a tick at a time on a blocking thread; the stream ends after the first error
+pub fn recorded_ticks(
+    dir: &Path,
+    symbol: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> mpsc::Receiver<io::Result<MarketTick>> {
+    let (tx, rx) = mpsc::channel(QUERY_BUFFER);
+    let (dir, symbol) = (dir.to_path_buf(), symbol.to_string());
+    tokio::task::spawn_blocking(move || {
+        let result = find_recordings(&dir, &symbol, from, to).and_then(|indexes| {
+            let mut open = true;
+            for index in indexes {
+                scan_recording(&index, |tick| {
+                    if tick.symbol == symbol && from <= tick.timestamp && tick.timestamp <= to {
+                        open = tx.blocking_send(Ok(tick)).is_ok();
+                    }
+                    open
+                })?;
+                if !open {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradingSession;
+    use crate::processor::{MarketCommand, MarketDataHub};
+    use rust_decimal::Decimal;
+    use tokio::sync::oneshot;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn tick(symbol: &str, secs: i64) -> MarketTick {
+        MarketTick::with_timestamp(
+            symbol.to_string(),
+            Decimal::new(10000 + secs, 2),
+            secs as u64,
+            DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+        )
+        .with_session(TradingSession::Regular)
+    }
+
+    #[test]
+    fn test_binary_rotation_and_index() {
+        let dir = temp_dir("recorder_binary");
+        let mut recorder = TickRecorder::new(&dir)
+            .with_format(RecordFormat::Binary)
+            .with_max_file_bytes(200);
+        for secs in 0..10 {
+            let symbol = if secs < 5 { "AAPL" } else { "MSFT" };
+            recorder.record(&tick(symbol, secs)).unwrap();
+        }
+        let indexes = recorder.finish().unwrap();
+        assert!(indexes.len() > 1);
+        assert_eq!(indexes.iter().map(|i| i.tick_count).sum::<u64>(), 10);
+        assert_eq!(read_index(&indexes[0].path).unwrap(), indexes[0]);
+
+        let from = DateTime::from_timestamp(1_700_000_006, 0).unwrap();
+        let found = find_recordings(&dir, "MSFT", from, from).unwrap();
+        assert_eq!(found.len(), 1);
+        let ticks = read_recording(&found[0]).unwrap();
+        assert!(ticks.iter().any(|t| t.timestamp == from));
+        assert_eq!(ticks[0].session, Some(TradingSession::Regular));
+        assert!(
+            find_recordings(&dir, "TSLA", from, from)
+                .unwrap()
+                .is_empty()
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watermark_follows_flushes() {
+        let dir = temp_dir("recorder_watermark");
+        let mut recorder = TickRecorder::new(&dir);
+        let mut watermark = recorder.watermark();
+        let through = tick("AAPL", 1).timestamp;
+        recorder.record(&tick("AAPL", 0)).unwrap();
+        recorder.record(&tick("AAPL", 1)).unwrap();
+        let pending = Duration::from_millis(10);
+        assert!(
+            tokio::time::timeout(pending, watermark.wait_for("AAPL", through))
+                .await
+                .is_err()
+        );
+
+        recorder.flush().unwrap();
+        assert!(watermark.wait_for("AAPL", through).await);
+        // Once the recorder is gone nothing more can be flushed
+        drop(recorder);
+        assert!(!watermark.wait_for("MSFT", through).await);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_skips_unencodable_ticks() {
+        let dir = temp_dir("recorder_skip");
+        let (tx, rx) = mpsc::channel(10);
+        let recorder = tokio::spawn(
+            TickRecorder::new(&dir)
+                .with_format(RecordFormat::Binary)
+                .run(rx),
+        );
+        let mut too_precise = tick("AAPL", 1);
+        too_precise.price = Decimal::new(1, 20);
+        for tick in [tick("AAPL", 0), too_precise, tick("AAPL", 2)] {
+            tx.send(tick).await.unwrap();
+        }
+        drop(tx);
+
+        let indexes = recorder.await.unwrap().unwrap();
+        assert_eq!(indexes[0].tick_count, 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_records_hub_stream() {
+        let dir = temp_dir("recorder_hub");
+        let (data_tx, data_rx) = mpsc::channel(10);
+        let (tap_tx, tap_rx) = mpsc::channel(10);
+        let mut hub = MarketDataHub::new(data_rx).with_tap(tap_tx);
+        let commands = hub.get_command_sender();
+        let recorder = tokio::spawn(TickRecorder::new(&dir).run(tap_rx));
+        let hub_task = tokio::spawn(async move { hub.start().await });
+
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::Subscribe("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let mut subscriber = rx.await.unwrap();
+        for secs in 0..3 {
+            data_tx.send(tick("AAPL", secs).into()).await.unwrap();
+        }
+        // The tap is fed before subscribers, so all three have been recorded once delivered
+        for _ in 0..3 {
+            subscriber.recv().await.unwrap();
+        }
+        commands.send(MarketCommand::Shutdown).await.unwrap();
+        hub_task.await.unwrap().unwrap();
+
+        let indexes = recorder.await.unwrap().unwrap();
+        assert_eq!(indexes.len(), 1);
+        let ticks = read_recording(&indexes[0]).unwrap();
+        assert_eq!(ticks.len(), 3);
+        assert_eq!(ticks[2].price, Decimal::new(10002, 2));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
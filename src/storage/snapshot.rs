@@ -0,0 +1,364 @@
+use crate::processor::aggregator::AggregatorState;
+use crate::processor::indicators::IndicatorEngine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Current snapshot format; bump it and add a migration when `Snapshot` changes shape
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Everything the hub needs to pick up where it left off after a restart
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub taken_at: DateTime<Utc>,
+    pub aggregator: AggregatorState,
+    pub indicators: IndicatorEngine,
+    /// WAL sequence number of the last tick the state includes, 0 without a WAL
+    /// Unacknowledged entries up to it are not counted again on restart
+    pub wal_sequence: u64,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Written by a newer release, or an older format with no migration
+    UnsupportedVersion(u64),
+    /// Snapshots were asked for every zero seconds
+    ZeroInterval,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "io error: {e}"),
+            SnapshotError::Json(e) => write!(f, "invalid snapshot: {e}"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            SnapshotError::ZeroInterval => write!(f, "snapshot interval must be non-zero"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::Json(e)
+    }
+}
+
+impl Snapshot {
+    pub fn new(aggregator: AggregatorState, indicators: IndicatorEngine) -> Self {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at: Utc::now(),
+            aggregator,
+            indicators,
+            wal_sequence: 0,
+        }
+    }
+
+    pub fn with_wal_sequence(mut self, seq: u64) -> Self {
+        self.wal_sequence = seq;
+        self
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnapshotError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Parse a snapshot of any supported version, migrating it to the current format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let value: serde_json::Value = serde_json::from_slice(bytes)?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+        let value = migrate(value, version)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Upgrade a raw snapshot one version at a time until it matches `SNAPSHOT_VERSION`
+fn migrate(value: serde_json::Value, version: u64) -> Result<serde_json::Value, SnapshotError> {
+    match version {
+        v if v == SNAPSHOT_VERSION as u64 => Ok(value),
+        1 => migrate(v1_to_v2(value), 2),
+        v => Err(SnapshotError::UnsupportedVersion(v)),
+    }
+}
+
+/// Version 2 added `wal_sequence`; older snapshots never skip WAL entries
+fn v1_to_v2(mut value: serde_json::Value) -> serde_json::Value {
+    value["wal_sequence"] = 0.into();
+    value["version"] = 2.into();
+    value
+}
+
+/// Write `bytes` next to `path`, fsync it and rename over `path`, then fsync the directory,
+/// so a crash leaves either the old or the new snapshot, never a torn or empty one
+pub fn write_snapshot(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
+    sync_dir(dir)
+}
+
+// Persist the rename; directories can only be opened and synced like this on Unix
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Load the snapshot at `path`, `None` if there is none yet
+pub fn read_snapshot(path: &Path) -> Result<Option<Snapshot>, SnapshotError> {
+    match fs::read(path) {
+        Ok(bytes) => Snapshot::from_bytes(&bytes).map(Some),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MarketTick;
+    use crate::processor::aggregator::PriceAggregator;
+    use crate::processor::bars::BarInterval;
+    use crate::processor::{MarketCommand, MarketDataHub};
+    use crate::storage::wal::{FsyncPolicy, WriteAheadLog};
+    use rust_decimal::Decimal;
+    use tokio::sync::{mpsc, oneshot};
+    use tokio::time::Duration;
+
+    fn tick(secs: i64, cents: i64) -> MarketTick {
+        MarketTick::with_timestamp(
+            "AAPL".to_string(),
+            Decimal::new(cents, 2),
+            100,
+            DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_round_trip_continues_where_it_left_off() {
+        let mut original = PriceAggregator::new()
+            .with_bar_intervals(&[BarInterval::OneMinute])
+            .with_history_capacity(5);
+        let mut indicators = IndicatorEngine::default();
+        for (secs, cents) in [(0, 10000), (20, 10100), (40, 9900)] {
+            indicators.on_tick(&tick(secs, cents));
+            original.add_tick(tick(secs, cents));
+        }
+
+        let bytes = Snapshot::new(original.state(), indicators.clone())
+            .to_bytes()
+            .unwrap();
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+        let mut restored = PriceAggregator::new()
+            .with_bar_intervals(&[BarInterval::OneMinute])
+            .with_history_capacity(5);
+        restored.restore(snapshot.aggregator);
+        let mut restored_indicators = snapshot.indicators;
+
+        // The next tick closes the restored open bar and updates every accumulator
+        for agg in [&mut original, &mut restored] {
+            agg.add_tick(tick(70, 10200));
+        }
+        let (a, b) = (
+            original.get_statistics("AAPL").unwrap(),
+            restored.get_statistics("AAPL").unwrap(),
+        );
+        assert_eq!(a.count, b.count);
+        assert_eq!(a.std_dev, b.std_dev);
+        assert_eq!(a.price_quantiles, b.price_quantiles);
+        assert_eq!(
+            original.drain_completed_bars(),
+            restored.drain_completed_bars()
+        );
+        assert_eq!(
+            indicators.on_tick(&tick(70, 10200)),
+            restored_indicators.on_tick(&tick(70, 10200))
+        );
+    }
+
+    #[test]
+    fn test_restore_keeps_current_configuration() {
+        let mut original = PriceAggregator::new()
+            .with_bar_intervals(&[BarInterval::OneMinute])
+            .with_history_capacity(5);
+        for (secs, cents) in [(0, 10000), (20, 10100), (70, 9900)] {
+            original.add_tick(tick(secs, cents));
+        }
+
+        let mut restored = PriceAggregator::new().with_history_capacity(2);
+        restored.restore(original.state());
+        assert_eq!(restored.get_statistics("AAPL").unwrap().count, 3);
+        // Only the two most recent prices fit, and one-minute bars are no longer configured
+        assert_eq!(
+            restored.recent_prices("AAPL").unwrap(),
+            &[Decimal::new(10100, 2), Decimal::new(9900, 2)]
+        );
+        assert!(restored.drain_completed_bars().is_empty());
+        assert!(
+            restored
+                .current_bar("AAPL", BarInterval::OneMinute)
+                .is_none()
+        );
+
+        let (_data_tx, data_rx) = mpsc::channel(1);
+        assert!(matches!(
+            MarketDataHub::new(data_rx).with_snapshots("unused.json", Duration::ZERO),
+            Err(SnapshotError::ZeroInterval)
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut value = serde_json::to_value(Snapshot::new(
+            PriceAggregator::new().state(),
+            IndicatorEngine::default(),
+        ))
+        .unwrap();
+        value["version"] = (SNAPSHOT_VERSION + 1).into();
+        let bytes = serde_json::to_vec(&value).unwrap();
+        assert!(matches!(
+            Snapshot::from_bytes(&bytes),
+            Err(SnapshotError::UnsupportedVersion(v)) if v == SNAPSHOT_VERSION as u64 + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hub_restores_on_startup() {
+        let path = std::env::temp_dir().join(format!("hub_snapshot_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let (data_tx, data_rx) = mpsc::channel(10);
+        let mut hub = MarketDataHub::new(data_rx)
+            .with_snapshots(&path, Duration::from_secs(60))
+            .unwrap();
+        let commands = hub.get_command_sender();
+        let hub_task = tokio::spawn(async move { hub.start().await });
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::Subscribe("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let mut subscriber = rx.await.unwrap();
+        for secs in 0..3 {
+            data_tx.send(tick(secs, 10000 + secs).into()).await.unwrap();
+            subscriber.recv().await.unwrap();
+        }
+        // Shutting down writes a final snapshot
+        commands.send(MarketCommand::Shutdown).await.unwrap();
+        hub_task.await.unwrap().unwrap();
+
+        let (_data_tx, data_rx) = mpsc::channel(10);
+        let mut hub = MarketDataHub::new(data_rx)
+            .with_snapshots(&path, Duration::from_secs(60))
+            .unwrap();
+        let commands = hub.get_command_sender();
+        let hub_task = tokio::spawn(async move { hub.start().await });
+        // Stats are reported for subscribed symbols only
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::Subscribe("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let _subscriber = rx.await.unwrap();
+        let (tx, rx) = oneshot::channel();
+        commands.send(MarketCommand::GetStats(tx)).await.unwrap();
+        let stats = rx.await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].count, 3);
+
+        commands.send(MarketCommand::Shutdown).await.unwrap();
+        hub_task.await.unwrap().unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_migrates_version_1() {
+        let mut value = serde_json::to_value(
+            Snapshot::new(PriceAggregator::new().state(), IndicatorEngine::default())
+                .with_wal_sequence(7),
+        )
+        .unwrap();
+        value["version"] = 1.into();
+        value.as_object_mut().unwrap().remove("wal_sequence");
+        let snapshot = Snapshot::from_bytes(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.wal_sequence, 0);
+    }
+
+    #[tokio::test]
+    async fn test_wal_entries_in_snapshot_are_not_counted_twice() {
+        let dir = std::env::temp_dir().join(format!("hub_snapshot_wal_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (snapshot_path, wal_path) = (dir.join("state.json"), dir.join("ticks.wal"));
+        // A crash after snapshotting two ticks but before their WAL acks were written
+        let mut wal = WriteAheadLog::open(&wal_path, FsyncPolicy::Always).unwrap();
+        let mut aggregator = PriceAggregator::new();
+        for secs in 0..3 {
+            let seq = wal.append(&tick(secs, 10000 + secs)).unwrap();
+            if seq <= 2 {
+                aggregator.add_tick(tick(secs, 10000 + secs));
+            }
+        }
+        drop(wal);
+        let snapshot =
+            Snapshot::new(aggregator.state(), IndicatorEngine::default()).with_wal_sequence(2);
+        write_snapshot(&snapshot_path, &snapshot.to_bytes().unwrap()).unwrap();
+
+        let (tap_tx, mut tap_rx) = mpsc::channel(10);
+        let (_data_tx, data_rx) = mpsc::channel(10);
+        let mut hub = MarketDataHub::new(data_rx)
+            .with_tap(tap_tx)
+            .with_snapshots(&snapshot_path, Duration::from_secs(60))
+            .unwrap()
+            .with_wal(&wal_path, FsyncPolicy::Never)
+            .unwrap();
+        let commands = hub.get_command_sender();
+        let hub_task = tokio::spawn(async move { hub.start().await });
+        // Every pending entry still reaches taps
+        for cents in 10000..10003 {
+            assert_eq!(tap_rx.recv().await.unwrap().price, Decimal::new(cents, 2));
+        }
+        let (tx, rx) = oneshot::channel();
+        commands
+            .send(MarketCommand::Subscribe("AAPL".to_string(), tx))
+            .await
+            .unwrap();
+        let _subscriber = rx.await.unwrap();
+        let (tx, rx) = oneshot::channel();
+        commands.send(MarketCommand::GetStats(tx)).await.unwrap();
+        assert_eq!(rx.await.unwrap()[0].count, 3);
+
+        commands.send(MarketCommand::Shutdown).await.unwrap();
+        hub_task.await.unwrap().unwrap();
+        let snapshot = read_snapshot(&snapshot_path).unwrap().unwrap();
+        assert_eq!(snapshot.wal_sequence, 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,547 @@
+use crate::models::MarketTick;
+use crate::storage::codec::{CodecError, HEADER_LEN, StreamDecoder, StreamEncoder};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, interval};
+
+const DEFAULT_SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+const DEFAULT_INDEX_INTERVAL: u64 = 256;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// Query results buffered ahead of the consumer
+const QUERY_BUFFER: usize = 1024;
+// Unstorable ticks logged individually by `run`; the rest are only counted
+const LOGGED_SKIPS: u64 = 10;
+// Ticks `run` collects before handing them to a blocking thread to write
+const WRITE_BATCH: usize = 1024;
+// start offset, end offset, min and max timestamp
+const INDEX_ENTRY_LEN: usize = 32;
+
+/// One sparse index entry: a run of ticks and the time range they span
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IndexEntry {
+    start: u64,
+    end: u64,
+    min_nanos: i64,
+    max_nanos: i64,
+}
+
+impl IndexEntry {
+    fn to_bytes(self) -> [u8; INDEX_ENTRY_LEN] {
+        let mut bytes = [0u8; INDEX_ENTRY_LEN];
+        bytes[0..8].copy_from_slice(&self.start.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.end.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.min_nanos.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.max_nanos.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; INDEX_ENTRY_LEN]) -> Self {
+        let word = |i: usize| bytes[i..i + 8].try_into().expect("8-byte slice");
+        IndexEntry {
+            start: u64::from_le_bytes(word(0)),
+            end: u64::from_le_bytes(word(8)),
+            min_nanos: i64::from_le_bytes(word(16)),
+            max_nanos: i64::from_le_bytes(word(24)),
+        }
+    }
+
+    fn overlaps(&self, from: i64, to: i64) -> bool {
+        self.min_nanos <= to && from <= self.max_nanos
+    }
+}
+
+struct OpenSegment {
+    day: NaiveDate,
+    encoder: StreamEncoder<BufWriter<File>>,
+    index: BufWriter<File>,
+    block: Option<IndexEntry>,
+    block_len: u64,
+}
+
+impl OpenSegment {
+    fn close_block(&mut self) -> io::Result<()> {
+        if let Some(mut block) = self.block.take() {
+            block.end = self.encoder.bytes_written();
+            self.index.write_all(&block.to_bytes())?;
+        }
+        self.block_len = 0;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Data before index, so an index entry never points past the data on disk
+        self.encoder.flush()?;
+        self.index.flush()
+    }
+}
+
+/// Embedded append-only tick database
+///
+/// Ticks are stored under `<root>/<symbol>/<YYYY-MM-DD>/` (UTC days) in
+/// numbered segment files written with `StreamEncoder`, each with a sparse
+/// index of `(start, end, min, max)` entries every `index_interval` ticks.
+/// Each indexed block repeats the symbol and source definitions in use, so
+/// queries seek straight to it. Segments are never rewritten: a restart, a full segment or a tick for a
+/// different day starts a new segment. Queries read what has been flushed.
+pub struct TickStore {
+    root: PathBuf,
+    max_segment_bytes: u64,
+    index_interval: u64,
+    open: HashMap<String, OpenSegment>,
+    skipped: u64,
+}
+
+impl TickStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        TickStore {
+            root: root.into(),
+            max_segment_bytes: DEFAULT_SEGMENT_BYTES,
+            index_interval: DEFAULT_INDEX_INTERVAL,
+            open: HashMap::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Start a new segment once the current one has reached `bytes`
+    pub fn with_max_segment_bytes(mut self, bytes: u64) -> Self {
+        self.max_segment_bytes = bytes;
+        self
+    }
+
+    /// Ticks per sparse index entry
+    pub fn with_index_interval(mut self, ticks: u64) -> Self {
+        self.index_interval = ticks.max(1);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn append(&mut self, tick: &MarketTick) -> io::Result<()> {
+        let day = tick.timestamp.date_naive();
+        let needs_new_segment = self.open.get(&tick.symbol).is_none_or(|segment| {
+            segment.day != day || segment.encoder.bytes_written() >= self.max_segment_bytes
+        });
+        if needs_new_segment {
+            self.close_segment(&tick.symbol)?;
+            let segment = self.open_segment(&tick.symbol, day)?;
+            self.open.insert(tick.symbol.clone(), segment);
+        }
+
+        let segment = self
+            .open
+            .get_mut(&tick.symbol)
+            .expect("segment opened above");
+        let start = segment.encoder.bytes_written();
+        if segment.block.is_none() {
+            // Each block opens with every id in use, so a query can seek straight to it
+            segment.encoder.redefine_all()?;
+        }
+        segment.encoder.encode(tick)?;
+        let nanos = tick.timestamp.timestamp_nanos_opt().unwrap_or_default();
+        let block = segment.block.get_or_insert(IndexEntry {
+            start,
+            end: start,
+            min_nanos: nanos,
+            max_nanos: nanos,
+        });
+        block.min_nanos = block.min_nanos.min(nanos);
+        block.max_nanos = block.max_nanos.max(nanos);
+        segment.block_len += 1;
+        if segment.block_len >= self.index_interval {
+            segment.close_block()?;
+        }
+        Ok(())
+    }
+
+    /// Make everything appended so far visible to queries
+    pub fn flush(&mut self) -> io::Result<()> {
+        for segment in self.open.values_mut() {
+            segment.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close all open segments, writing their final index entries
+    pub fn close(mut self) -> io::Result<()> {
+        let symbols: Vec<String> = self.open.keys().cloned().collect();
+        for symbol in symbols {
+            self.close_segment(&symbol)?;
+        }
+        Ok(())
+    }
+
+    /// Store ticks until the channel closes, flushing once a second
+    /// Pair with `MarketDataHub::with_tap` to persist everything the hub sees
+    /// Ticks are written in batches on a blocking thread, so file I/O never stalls an async worker
+    /// Ticks with an unencodable price or a symbol unusable as a directory are skipped and
+    /// counted; only I/O errors stop the store
+    pub async fn run(mut self, mut rx: mpsc::Receiver<MarketTick>) -> io::Result<()> {
+        let mut flush_timer = interval(FLUSH_INTERVAL);
+        let mut batch = Vec::with_capacity(WRITE_BATCH);
+        loop {
+            let flush = tokio::select! {
+                tick = rx.recv() => match tick {
+                    Some(tick) => {
+                        batch.push(tick);
+                        if batch.len() < WRITE_BATCH {
+                            continue;
+                        }
+                        false
+                    }
+                    None => break,
+                },
+                _ = flush_timer.tick() => true,
+            };
+            let ticks = std::mem::replace(&mut batch, Vec::with_capacity(WRITE_BATCH));
+            self = tokio::task::spawn_blocking(move || {
+                self.append_batch(&ticks)?;
+                if flush {
+                    self.flush()?;
+                }
+                Ok::<_, io::Error>(self)
+            })
+            .await
+            .map_err(io::Error::other)??;
+        }
+        tokio::task::spawn_blocking(move || {
+            self.append_batch(&batch)?;
+            if self.skipped > 0 {
+                println!("Skipped {} ticks that could not be stored", self.skipped);
+            }
+            self.close()
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    // Append each tick, logging and counting the ones that cannot be stored
+    fn append_batch(&mut self, ticks: &[MarketTick]) -> io::Result<()> {
+        for tick in ticks {
+            match self.append(tick) {
+                Ok(()) => {}
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput
+                    ) =>
+                {
+                    self.skipped += 1;
+                    if self.skipped <= LOGGED_SKIPS {
+                        println!("Not storing tick for {:?}: {e}", tick.symbol);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Stream stored ticks for `symbol` with `from <= timestamp <= to`, in append order
+    /// Reading happens on a blocking thread; the stream ends early on the first error
+    pub fn ticks(
+        &self,
+        symbol: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> mpsc::Receiver<io::Result<MarketTick>> {
+        let (tx, rx) = mpsc::channel(QUERY_BUFFER);
+        let symbol_dir = match symbol_dir(&self.root, symbol) {
+            Ok(dir) => dir,
+            Err(e) => {
+                let _ = tx.try_send(Err(e));
+                return rx;
+            }
+        };
+        let query = Query {
+            symbol_dir,
+            from,
+            to,
+        };
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = query.run(&tx) {
+                let _ = tx.blocking_send(Err(e));
+            }
+        });
+        rx
+    }
+
+    fn open_segment(&self, symbol: &str, day: NaiveDate) -> io::Result<OpenSegment> {
+        let dir = symbol_dir(&self.root, symbol)?.join(day.format("%Y-%m-%d").to_string());
+        fs::create_dir_all(&dir)?;
+        let mut number = 0;
+        let data_path = loop {
+            let path = dir.join(format!("segment-{number:06}.bin"));
+            if !path.exists() {
+                break path;
+            }
+            number += 1;
+        };
+        Ok(OpenSegment {
+            day,
+            encoder: StreamEncoder::new(BufWriter::new(File::create(&data_path)?))?,
+            index: BufWriter::new(File::create(data_path.with_extension("idx"))?),
+            block: None,
+            block_len: 0,
+        })
+    }
+
+    fn close_segment(&mut self, symbol: &str) -> io::Result<()> {
+        if let Some(mut segment) = self.open.remove(symbol) {
+            segment.close_block()?;
+            segment.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn symbol_dir(root: &Path, symbol: &str) -> io::Result<PathBuf> {
+    if symbol.is_empty() || symbol.starts_with('.') || symbol.contains(['/', '\\']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("symbol {symbol:?} cannot be used as a directory name"),
+        ));
+    }
+    Ok(root.join(symbol))
+}
+
+struct Query {
+    symbol_dir: PathBuf,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+}
+
+impl Query {
+    fn run(&self, tx: &mpsc::Sender<io::Result<MarketTick>>) -> io::Result<()> {
+        if self.from > self.to || !self.symbol_dir.exists() {
+            return Ok(());
+        }
+        let from = self.from.timestamp_nanos_opt().unwrap_or(i64::MIN);
+        let to = self.to.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        let (first_day, last_day) = (self.from.date_naive(), self.to.date_naive());
+
+        let mut days: Vec<(NaiveDate, PathBuf)> = fs::read_dir(&self.symbol_dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let day =
+                    NaiveDate::parse_from_str(path.file_name()?.to_str()?, "%Y-%m-%d").ok()?;
+                (first_day <= day && day <= last_day).then_some((day, path))
+            })
+            .collect();
+        days.sort();
+
+        for (_, dir) in days {
+            let mut segments: Vec<PathBuf> = fs::read_dir(&dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+                .collect();
+            segments.sort();
+            for segment in segments {
+                if !scan_segment(&segment, from, to, tx)? {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Send matching ticks from one segment; false once the receiver has gone away
+fn scan_segment(
+    path: &Path,
+    from: i64,
+    to: i64,
+    tx: &mpsc::Sender<io::Result<MarketTick>>,
+) -> io::Result<bool> {
+    let entries = read_index_entries(&path.with_extension("idx"))?;
+    let mut decoder = match StreamDecoder::new(BufReader::new(File::open(path)?)) {
+        Ok(decoder) => decoder,
+        // A segment created just before a crash may not even have its header
+        Err(CodecError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(true),
+        Err(e) => return Err(e.into()),
+    };
+    let mut tick =
+        MarketTick::with_timestamp(String::new(), Decimal::ZERO, 0, DateTime::UNIX_EPOCH);
+
+    // Blocks the index says overlap the range, then whatever was appended after the last entry
+    let tail_start = entries.last().map_or(HEADER_LEN, |entry| entry.end);
+    let blocks = entries
+        .iter()
+        .filter(|entry| entry.overlaps(from, to))
+        .map(|entry| (entry.start, Some(entry.end)))
+        .chain([(tail_start, None)]);
+
+    for (start, end) in blocks {
+        // Blocks define every id they use, so nothing before `start` needs reading
+        decoder.seek(start)?;
+        while end.is_none_or(|end| decoder.position() < end) {
+            match decoder.decode_into(&mut tick) {
+                Ok(true) => {}
+                Ok(false) => break,
+                // Tail cut short by a crash mid-write; everything before it is still valid
+                Err(CodecError::Io(e))
+                    if end.is_none() && e.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    break;
+                }
+                Err(CodecError::Corrupt("truncated frame length")) if end.is_none() => break,
+                Err(e) => return Err(e.into()),
+            }
+            let nanos = tick.timestamp.timestamp_nanos_opt().unwrap_or_default();
+            if from <= nanos && nanos <= to && tx.blocking_send(Ok(tick.clone())).is_err() {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn read_index_entries(path: &Path) -> io::Result<Vec<IndexEntry>> {
+    let mut bytes = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut bytes)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    // A partially written trailing entry is ignored
+    Ok(bytes
+        .chunks_exact(INDEX_ENTRY_LEN)
+        .map(|chunk| IndexEntry::from_bytes(chunk.try_into().expect("exact chunk")))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        // 2025-01-14 00:00 UTC
+        DateTime::from_timestamp(1_736_812_800, 0).unwrap() + TimeDelta::minutes(minutes)
+    }
+
+    fn tick(symbol: &str, minutes: i64) -> MarketTick {
+        MarketTick::with_timestamp(
+            symbol.to_string(),
+            Decimal::new(10000 + minutes, 2),
+            1,
+            at(minutes),
+        )
+    }
+
+    async fn collect(mut rx: mpsc::Receiver<io::Result<MarketTick>>) -> Vec<MarketTick> {
+        let mut ticks = Vec::new();
+        while let Some(tick) = rx.recv().await {
+            ticks.push(tick.unwrap());
+        }
+        ticks
+    }
+
+    #[tokio::test]
+    async fn test_time_range_query_across_days_and_segments() {
+        let root = temp_dir("tick_store_range");
+        let mut store = TickStore::new(&root)
+            .with_index_interval(10)
+            .with_max_segment_bytes(1024);
+        // Two days of one tick a minute for AAPL, interleaved with MSFT
+        for minute in 0..2 * 24 * 60 {
+            store.append(&tick("AAPL", minute)).unwrap();
+            if minute % 60 == 0 {
+                store.append(&tick("MSFT", minute)).unwrap();
+            }
+        }
+        store.flush().unwrap();
+
+        let ticks = collect(store.ticks("AAPL", at(1430), at(1450))).await;
+        assert_eq!(ticks.len(), 21);
+        assert_eq!(ticks[0].timestamp, at(1430));
+        assert_eq!(ticks[20].timestamp, at(1450));
+        assert!(ticks.iter().all(|t| t.symbol == "AAPL"));
+
+        let msft = collect(store.ticks("MSFT", at(0), at(10_000))).await;
+        assert_eq!(msft.len(), 48);
+        assert!(collect(store.ticks("TSLA", at(0), at(10))).await.is_empty());
+        assert!(
+            fs::read_dir(root.join("AAPL").join("2025-01-15"))
+                .unwrap()
+                .count()
+                > 2
+        );
+
+        store.close().unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reopen_appends_new_segment_and_reads_unindexed_tail() {
+        let root = temp_dir("tick_store_reopen");
+        let mut store = TickStore::new(&root).with_index_interval(100);
+        for minute in 0..5 {
+            store.append(&tick("AAPL", minute)).unwrap();
+        }
+        store.close().unwrap();
+
+        let mut store = TickStore::new(&root).with_index_interval(100);
+        for minute in 5..8 {
+            store.append(&tick("AAPL", minute)).unwrap();
+        }
+        // Flushed but never indexed: still found by scanning the tail
+        store.flush().unwrap();
+        let ticks = collect(store.ticks("AAPL", at(3), at(6))).await;
+        let minutes: Vec<i64> = ticks
+            .iter()
+            .map(|t| (t.timestamp - at(0)).num_minutes())
+            .collect();
+        assert_eq!(minutes, vec![3, 4, 5, 6]);
+
+        assert!(store.append(&tick("../etc", 0)).is_err());
+        let mut escaped = store.ticks("../etc", at(0), at(1));
+        assert!(escaped.recv().await.unwrap().is_err());
+        store.close().unwrap();
+
+        // `run` skips the bad tick and keeps storing
+        let (tx, rx) = mpsc::channel(10);
+        let running = tokio::spawn(TickStore::new(&root).run(rx));
+        for tick in [tick("AAPL", 8), tick("../etc", 9), tick("AAPL", 10)] {
+            tx.send(tick).await.unwrap();
+        }
+        drop(tx);
+        running.await.unwrap().unwrap();
+        let store = TickStore::new(&root);
+        assert_eq!(collect(store.ticks("AAPL", at(8), at(10))).await.len(), 2);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_past_mid_segment_source_definition() {
+        let root = temp_dir("tick_store_sources");
+        let mut store = TickStore::new(&root).with_index_interval(2);
+        // Unsequenced first, so the source is only defined once the sequenced ticks begin
+        store.append(&tick("AAPL", 0)).unwrap();
+        for minute in 1..10 {
+            let mut tick = tick("AAPL", minute);
+            tick.source = Some("feed".to_string());
+            tick.sequence = Some(minute as u64);
+            store.append(&tick).unwrap();
+        }
+        store.close().unwrap();
+
+        let ticks = collect(TickStore::new(&root).ticks("AAPL", at(7), at(9))).await;
+        let sequences: Vec<Option<u64>> = ticks.iter().map(|t| t.sequence).collect();
+        assert_eq!(sequences, vec![Some(7), Some(8), Some(9)]);
+        assert!(ticks.iter().all(|t| t.source.as_deref() == Some("feed")));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
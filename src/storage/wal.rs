@@ -0,0 +1,413 @@
+use crate::models::MarketTick;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+const DEFAULT_MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+// Requests queued for the writer thread before callers wait
+const WRITER_QUEUE: usize = 1024;
+
+/// When appended entries are forced to stable storage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsyncPolicy {
+    /// fsync after every append; nothing acknowledged to the OS is lost on power failure
+    Always,
+    /// fsync at most once per interval, driven by `sync`
+    Interval(Duration),
+    /// Leave it to the OS; survives a process crash but not a machine crash
+    Never,
+}
+
+/// One line of the log
+#[derive(Debug, Serialize, Deserialize)]
+enum WalRecord {
+    Tick {
+        seq: u64,
+        tick: MarketTick,
+    },
+    /// Every tick up to and including `seq` has been fanned out
+    Ack {
+        seq: u64,
+    },
+}
+
+/// Append-only JSON-lines log of ticks, written before they are fanned out
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: File,
+    policy: FsyncPolicy,
+    max_file_bytes: u64,
+    bytes_written: u64,
+    next_seq: u64,
+    acked: u64,
+    dirty: bool,
+    pending: Vec<(u64, MarketTick)>,
+}
+
+impl WriteAheadLog {
+    /// Open or create the log at `path`, recovering entries that were never acknowledged
+    /// A torn final line from a crash mid-write is dropped; corruption elsewhere is an error
+    pub fn open(path: impl Into<PathBuf>, policy: FsyncPolicy) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let found = recover(&path)?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.set_len(found.valid_len)?;
+        Ok(WriteAheadLog {
+            path,
+            file,
+            policy,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            bytes_written: found.valid_len,
+            next_seq: found.last_seq + 1,
+            acked: found.acked,
+            dirty: false,
+            pending: found.pending,
+        })
+    }
+
+    /// Compact the log once it grows past `bytes` and every entry has been acknowledged
+    pub fn with_max_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_file_bytes = bytes;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn policy(&self) -> FsyncPolicy {
+        self.policy
+    }
+
+    /// Sequence number of the last acknowledged tick, 0 if none
+    pub fn acknowledged(&self) -> u64 {
+        self.acked
+    }
+
+    /// Sequence number of the last tick appended, 0 if none
+    pub fn last_sequence(&self) -> u64 {
+        self.next_seq - 1
+    }
+
+    /// Entries found on open that were never acknowledged, oldest first
+    pub fn take_pending(&mut self) -> Vec<(u64, MarketTick)> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Durably append `tick` (per the fsync policy) and return its sequence number
+    pub fn append(&mut self, tick: &MarketTick) -> io::Result<u64> {
+        let seq = self.next_seq;
+        self.write_record(&WalRecord::Tick {
+            seq,
+            tick: tick.clone(),
+        })?;
+        if self.policy == FsyncPolicy::Always {
+            self.file.sync_data()?;
+        } else {
+            self.dirty = true;
+        }
+        self.next_seq += 1;
+        Ok(seq)
+    }
+
+    /// Mark every entry up to `seq` as delivered; they will not be replayed on recovery
+    pub fn acknowledge(&mut self, seq: u64) -> io::Result<()> {
+        if seq <= self.acked {
+            return Ok(());
+        }
+        self.acked = seq;
+        // Ack records are never synced on their own; losing one only means a redelivery
+        self.write_record(&WalRecord::Ack { seq })?;
+        if self.bytes_written > self.max_file_bytes && self.acked + 1 == self.next_seq {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// fsync anything appended since the last sync
+    pub fn sync(&mut self) -> io::Result<()> {
+        if self.dirty {
+            self.file.sync_data()?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Start the log over, keeping only the acknowledged sequence number so numbering continues
+    fn compact(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.bytes_written = 0;
+        self.write_record(&WalRecord::Ack { seq: self.acked })?;
+        self.file.sync_data()?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Move the log onto a dedicated blocking thread; take pending entries first
+    pub fn spawn_writer(self) -> WalWriter {
+        let (tx, rx) = mpsc::channel(WRITER_QUEUE);
+        let policy = self.policy;
+        let thread = tokio::task::spawn_blocking(move || run_writer(self, rx));
+        WalWriter { tx, policy, thread }
+    }
+
+    fn write_record(&mut self, record: &WalRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        // A single write per record so a crash tears at most the final line
+        self.file.write_all(&line)?;
+        self.bytes_written += line.len() as u64;
+        Ok(())
+    }
+}
+
+enum WalRequest {
+    Append(MarketTick, oneshot::Sender<io::Result<u64>>),
+    Acknowledge(u64),
+    Sync,
+}
+
+/// Handle to a `WriteAheadLog` running on its own thread, so writes and fsyncs never block
+/// an async worker; requests are applied in the order they are sent
+pub struct WalWriter {
+    tx: mpsc::Sender<WalRequest>,
+    policy: FsyncPolicy,
+    thread: JoinHandle<()>,
+}
+
+impl WalWriter {
+    pub fn policy(&self) -> FsyncPolicy {
+        self.policy
+    }
+
+    /// Append `tick` and wait until it is logged per the fsync policy
+    pub async fn append(&self, tick: MarketTick) -> io::Result<u64> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(WalRequest::Append(tick, reply_tx))
+            .await
+            .map_err(|_| writer_stopped())?;
+        reply_rx.await.map_err(|_| writer_stopped())?
+    }
+
+    /// Acknowledge every entry up to `seq`; errors are logged by the writer
+    pub async fn acknowledge(&self, seq: u64) {
+        let _ = self.tx.send(WalRequest::Acknowledge(seq)).await;
+    }
+
+    /// fsync anything appended since the last sync; errors are logged by the writer
+    pub async fn sync(&self) {
+        let _ = self.tx.send(WalRequest::Sync).await;
+    }
+
+    /// Finish outstanding requests, sync and stop the writer thread
+    pub async fn close(self) {
+        drop(self.tx);
+        if let Err(e) = self.thread.await {
+            println!("WAL writer failed: {e}");
+        }
+    }
+}
+
+fn writer_stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "WAL writer has stopped")
+}
+
+fn run_writer(mut wal: WriteAheadLog, mut rx: mpsc::Receiver<WalRequest>) {
+    while let Some(request) = rx.blocking_recv() {
+        match request {
+            WalRequest::Append(tick, reply) => {
+                let _ = reply.send(wal.append(&tick));
+            }
+            WalRequest::Acknowledge(seq) => {
+                if let Err(e) = wal.acknowledge(seq) {
+                    println!("Error acknowledging WAL entry {seq}: {e}");
+                }
+            }
+            WalRequest::Sync => {
+                if let Err(e) = wal.sync() {
+                    println!("Error syncing WAL: {e}");
+                }
+            }
+        }
+    }
+    if let Err(e) = wal.sync() {
+        println!("Error syncing WAL: {e}");
+    }
+}
+
+/// What a scan of an existing log found
+#[derive(Default)]
+struct Recovered {
+    pending: Vec<(u64, MarketTick)>,
+    last_seq: u64,
+    acked: u64,
+    /// Length of the prefix made of complete records
+    valid_len: u64,
+}
+
+fn recover(path: &Path) -> io::Result<Recovered> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Recovered::default()),
+        Err(e) => return Err(e),
+    };
+    let mut reader = BufReader::new(file);
+    let mut found = Recovered::default();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line)?;
+        if n == 0 {
+            break;
+        }
+        let record = match serde_json::from_slice::<WalRecord>(&line) {
+            Ok(record) => record,
+            Err(_) if line.last() != Some(&b'\n') => break,
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("corrupt WAL entry at byte {}: {e}", found.valid_len),
+                ));
+            }
+        };
+        match record {
+            WalRecord::Tick { seq, tick } => {
+                found.last_seq = seq;
+                found.pending.push((seq, tick));
+            }
+            WalRecord::Ack { seq } => {
+                found.acked = seq;
+                found.last_seq = found.last_seq.max(seq);
+                found.pending.retain(|(s, _)| *s > seq);
+            }
+        }
+        found.valid_len += n as u64;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}_{}.wal", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn tick(cents: i64) -> MarketTick {
+        MarketTick::new("AAPL".to_string(), Decimal::new(cents, 2), 100)
+    }
+
+    #[test]
+    fn test_recovers_unacknowledged_and_torn_tail() {
+        let path = temp_path("wal_recover");
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Always).unwrap();
+        assert_eq!(wal.append(&tick(10000)).unwrap(), 1);
+        assert_eq!(wal.append(&tick(10100)).unwrap(), 2);
+        wal.acknowledge(1).unwrap();
+        assert_eq!(wal.append(&tick(10200)).unwrap(), 3);
+        drop(wal);
+        // Simulate a crash part way through writing entry 4
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"Tick\":{\"seq\":4,\"ti").unwrap();
+        drop(file);
+
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Never).unwrap();
+        let pending = wal.take_pending();
+        assert_eq!(
+            pending.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        assert_eq!(pending[1].1.price, Decimal::new(10200, 2));
+        assert_eq!(wal.acknowledged(), 1);
+        // The torn entry is discarded and its sequence number reused
+        assert_eq!(wal.append(&tick(10300)).unwrap(), 4);
+        wal.acknowledge(4).unwrap();
+        drop(wal);
+
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Never).unwrap();
+        assert!(wal.take_pending().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_compaction_keeps_numbering() {
+        let path = temp_path("wal_compact");
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Interval(Duration::from_secs(1)))
+            .unwrap()
+            .with_max_file_bytes(512);
+        for i in 0..20 {
+            let seq = wal.append(&tick(10000 + i)).unwrap();
+            wal.acknowledge(seq).unwrap();
+        }
+        wal.sync().unwrap();
+        assert!(fs::metadata(&path).unwrap().len() <= 512);
+        drop(wal);
+
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Always).unwrap();
+        assert!(wal.take_pending().is_empty());
+        assert_eq!(wal.append(&tick(10000)).unwrap(), 21);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hub_replays_unacknowledged_ticks() {
+        let path = temp_path("wal_hub");
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Always).unwrap();
+        let seq = wal.append(&tick(10000)).unwrap();
+        wal.acknowledge(seq).unwrap();
+        // Crash after logging this one but before fanning it out
+        wal.append(&tick(10100)).unwrap();
+        drop(wal);
+
+        let (tap_tx, mut tap_rx) = tokio::sync::mpsc::channel(10);
+        let (data_tx, data_rx) = tokio::sync::mpsc::channel(10);
+        let mut hub = crate::processor::MarketDataHub::new(data_rx)
+            .with_tap(tap_tx)
+            .with_wal(&path, FsyncPolicy::Interval(Duration::from_millis(10)))
+            .unwrap();
+        let commands = hub.get_command_sender();
+        let hub_task = tokio::spawn(async move { hub.start().await });
+
+        assert_eq!(tap_rx.recv().await.unwrap().price, Decimal::new(10100, 2));
+        // Replay ran before anyone subscribed; the replayed tick is the last value
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        commands
+            .send(crate::processor::MarketCommand::Subscribe(
+                "AAPL".to_string(),
+                tx,
+            ))
+            .await
+            .unwrap();
+        let mut subscriber = rx.await.unwrap();
+        match subscriber.recv().await.unwrap() {
+            crate::processor::MarketUpdate::Snapshot { tick, .. } => {
+                assert_eq!(tick.price, Decimal::new(10100, 2))
+            }
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+        data_tx.send(tick(10200).into()).await.unwrap();
+        assert_eq!(tap_rx.recv().await.unwrap().price, Decimal::new(10200, 2));
+        commands
+            .send(crate::processor::MarketCommand::Shutdown)
+            .await
+            .unwrap();
+        hub_task.await.unwrap().unwrap();
+
+        let mut wal = WriteAheadLog::open(&path, FsyncPolicy::Always).unwrap();
+        assert!(wal.take_pending().is_empty());
+        assert_eq!(wal.acknowledged(), 3);
+        fs::remove_file(path).unwrap();
+    }
+}